@@ -1,19 +1,186 @@
 pub use nand7400::{
     assembler::{
-        config::{AssemblerConfig, Opcode, OpcodeArg},
+        config::{
+            pseudos::PseudoInstruction,
+            regions::{Region, RegionKind},
+            vectors::Vector,
+            ArgConstraint, AssemblerConfig, Endianness, Opcode, OpcodeArg, Register,
+        },
         errors::AssemblerError,
         parser::errors::ParsingError,
         parser::lexer::token::TokenKind,
         position::Position,
     },
+    emulator::errors::EmulatorError,
     formatter::Formatter,
 };
 
-use nand7400::assembler::Assembler as RustAssembler;
-use std::sync::Mutex;
+use miette::Diagnostic;
+use nand7400::{
+    assembler::{
+        config::{errors::ConfigError, profiles::PROFILE_NAMES, spans::line_column},
+        Assembler as RustAssembler,
+    },
+    emulator::{machine::Machine as RustMachine, snapshot::Snapshot as RustSnapshot, Bus},
+};
+use std::sync::{Arc, Mutex};
+
+/// An error wrapped by `AssemblerError::InIteration`.
+pub type BoxedAssemblerError = Box<AssemblerError>;
+
+/// An error wrapped by `ParsingError::InIteration`.
+pub type BoxedParsingError = Box<ParsingError>;
+
+// Need to include this so that UniFFI scaffolding is generated. It's in its own module because the generated code trips
+// a few lints that we can't fix ourselves.
+#[allow(
+    dead_code,
+    clippy::empty_line_after_doc_comments,
+    clippy::useless_conversion
+)]
+mod scaffolding {
+    use super::*;
+
+    uniffi::include_scaffolding!("ffi");
+
+    // The error wrapped by an `InIteration` error is passed over as its message, since errors can't hold themselves
+    // over FFI. They're only ever passed out of Rust, so they never have to be converted back.
+    impl UniffiCustomTypeConverter for BoxedAssemblerError {
+        type Builtin = String;
+
+        fn into_custom(val: Self::Builtin) -> uniffi::Result<Self> {
+            Err(uniffi::deps::anyhow::anyhow!(
+                "Errors can't be passed into Rust: {}",
+                val
+            ))
+        }
+
+        fn from_custom(obj: Self) -> Self::Builtin {
+            obj.to_string()
+        }
+    }
+
+    impl UniffiCustomTypeConverter for BoxedParsingError {
+        type Builtin = String;
+
+        fn into_custom(val: Self::Builtin) -> uniffi::Result<Self> {
+            Err(uniffi::deps::anyhow::anyhow!(
+                "Errors can't be passed into Rust: {}",
+                val
+            ))
+        }
+
+        fn from_custom(obj: Self) -> Self::Builtin {
+            obj.to_string()
+        }
+    }
+}
+
+use scaffolding::UniFfiTag;
+
+/// A warning from assembling some code, flattened so that it can be highlighted in an editor.
+pub struct AssemblerWarning {
+    /// The diagnostic code of the warning (e.g. `nand7400::warnings::deprecated_opcode`).
+    pub code: String,
+
+    /// The message describing the warning.
+    pub message: String,
+
+    /// The span of the warning in the source code.
+    pub span: Position,
+}
+
+/// A problem with an assembler configuration, flattened so that it can be highlighted in an editor. `line` and
+/// `column` start at 1, and are only there if the configuration was validated from JSON.
+pub struct ConfigDiagnostic {
+    /// The diagnostic code of the problem (e.g. `nand7400::errors::config::duplicate_opcode`).
+    pub code: String,
+
+    /// The message describing the problem.
+    pub message: String,
+
+    /// The path to the value with the problem (e.g. `opcodes[3].mnemonic`).
+    pub path: String,
+
+    /// The span of the value with the problem in the JSON.
+    pub span: Option<Position>,
+
+    /// The line the problem starts on.
+    pub line: Option<u32>,
+
+    /// The column the problem starts at.
+    pub column: Option<u32>,
+}
+
+impl ConfigDiagnostic {
+    /// Flattens a configuration error, using `source` to find the line and column.
+    fn new(error: ConfigError, source: Option<&str>) -> Self {
+        let line_column = source
+            .zip(error.span())
+            .map(|(source, span)| line_column(source, span.start as usize));
+
+        Self {
+            code: error.code().map_or(String::new(), |code| code.to_string()),
+            message: error.to_string(),
+            path: error.path().to_string(),
+            span: error.span(),
+            line: line_column.map(|(line, _)| line as u32),
+            column: line_column.map(|(_, column)| column as u32),
+        }
+    }
+}
+
+/// Validates an assembler configuration, returning every problem with it.
+pub fn validate_config(config: AssemblerConfig) -> Vec<ConfigDiagnostic> {
+    config
+        .validate()
+        .err()
+        .unwrap_or_default()
+        .into_iter()
+        .map(|error| ConfigDiagnostic::new(error, None))
+        .collect()
+}
+
+/// Validates an assembler configuration from JSON, returning every problem with it. The configuration can extend
+/// built-in profiles, but not other files.
+pub fn validate_config_json(source: &str) -> Vec<ConfigDiagnostic> {
+    AssemblerConfig::validate_json(source)
+        .err()
+        .unwrap_or_default()
+        .into_iter()
+        .map(|error| ConfigDiagnostic::new(error, Some(source)))
+        .collect()
+}
+
+/// Gets the configuration for the latest revision of the nand7400 computer.
+pub fn nand7400_config() -> AssemblerConfig {
+    AssemblerConfig::nand7400()
+}
 
-// Need to include this so that UniFFI scaffolding is generated.
-uniffi::include_scaffolding!("ffi");
+/// Gets a built-in configuration profile by name, if there is one with that name.
+pub fn profile_config(name: &str) -> Option<AssemblerConfig> {
+    AssemblerConfig::profile(name)
+}
+
+/// Gets the names of the built-in configuration profiles, oldest first.
+pub fn profile_names() -> Vec<String> {
+    PROFILE_NAMES.iter().map(|name| name.to_string()).collect()
+}
+
+/// Describes every opcode with the given name or alias in Markdown, if there are any.
+pub fn describe_opcode(config: AssemblerConfig, mnemonic: &str) -> Option<String> {
+    config.describe(mnemonic)
+}
+
+/// Renders an instruction set reference for a configuration in Markdown.
+pub fn reference_markdown(config: AssemblerConfig) -> String {
+    config.reference_markdown()
+}
+
+/// Renders an instruction set reference for a configuration as a standalone HTML page.
+pub fn reference_html(config: AssemblerConfig) -> String {
+    config.reference_html()
+}
 
 /// The FFI-safe version of the assembler from the `nand7400` crate.
 pub struct Assembler {
@@ -50,4 +217,213 @@ impl Assembler {
             .expect("An internal Mutex was poisoned! Some thread must have panicked while holding onto this Mutex!")
             .assemble(source)
     }
+
+    /// Assembles the given assembly code, returning a listing of where each instruction ended up.
+    pub fn listing(&self, source: &str) -> Result<String, AssemblerError> {
+        let (_, listing) = self.inner
+            .lock()
+            .as_mut()
+            .expect("An internal Mutex was poisoned! Some thread must have panicked while holding onto this Mutex!")
+            .assemble_with_listing(source)?;
+
+        Ok(listing.to_string())
+    }
+
+    /// Assembles the given assembly code, returning its labels and constants exported as text.
+    pub fn symbols(&self, source: &str) -> Result<String, AssemblerError> {
+        let (_, ast) = self.inner
+            .lock()
+            .as_mut()
+            .expect("An internal Mutex was poisoned! Some thread must have panicked while holding onto this Mutex!")
+            .assemble_with_ast(source)?;
+
+        Ok(ast.export_symbols())
+    }
+
+    /// Gets the warnings from the last time something was assembled successfully.
+    pub fn warnings(&self) -> Vec<AssemblerWarning> {
+        self.inner
+            .lock()
+            .as_mut()
+            .expect("An internal Mutex was poisoned! Some thread must have panicked while holding onto this Mutex!")
+            .warnings()
+            .iter()
+            .map(|warning| AssemblerWarning {
+                code: warning.code().map_or(String::new(), |code| code.to_string()),
+                message: warning.to_string(),
+                span: warning.span(),
+            })
+            .collect()
+    }
+
+    /// Gets the entry point from the last time something was assembled successfully, if it set one.
+    pub fn entry_point(&self) -> Option<u32> {
+        self.inner
+            .lock()
+            .as_mut()
+            .expect("An internal Mutex was poisoned! Some thread must have panicked while holding onto this Mutex!")
+            .entry_point()
+    }
+}
+
+/// The FFI-safe version of a nand7400 machine from the `nand7400` crate, running the nand7400's own instruction set
+/// with nothing mapped onto its bus.
+pub struct Machine {
+    /// The inner machine. It needs to be in a `Mutex` so that it can be shared across threads.
+    inner: Mutex<RustMachine>,
+}
+
+/// Public API for the machine.
+impl Machine {
+    /// Creates a new machine, with all of its memory and registers zeroed.
+    pub fn new() -> Self {
+        Self {
+            inner: Mutex::new(RustMachine::nand7400(Bus::new())),
+        }
+    }
+
+    /// Loads a binary image into memory at `start`.
+    pub fn load(&self, start: u16, image: Vec<u8>) {
+        self.lock().load(start, &image);
+    }
+
+    /// Resets the CPU to its initial state, starting execution at `pc`. Memory is left untouched.
+    pub fn reset(&self, pc: u16) {
+        self.lock().reset(pc);
+    }
+
+    /// Runs until the CPU halts, or until `max_steps` instructions have been executed. Returns the number of
+    /// instructions executed.
+    pub fn run(&self, max_steps: u64) -> Result<u64, EmulatorError> {
+        Ok(self.lock().run(max_steps as usize)? as u64)
+    }
+
+    /// The program counter.
+    pub fn pc(&self) -> u16 {
+        self.lock().cpu.pc
+    }
+
+    /// The general-purpose registers.
+    pub fn registers(&self) -> Vec<u8> {
+        self.lock().cpu.registers.clone()
+    }
+
+    /// Whether or not the CPU has halted.
+    pub fn halted(&self) -> bool {
+        self.lock().cpu.halted
+    }
+
+    /// Takes a snapshot of the machine. If `config` is given, the snapshot can only be restored alongside it.
+    pub fn capture(&self, config: Option<AssemblerConfig>) -> Arc<Snapshot> {
+        Arc::new(Snapshot {
+            inner: RustSnapshot::capture(&self.lock(), config.as_ref()),
+        })
+    }
+
+    /// Restores a snapshot onto the machine. If `config` is given and the snapshot was saved with one, they must
+    /// match. The machine is left untouched if the snapshot can't be restored.
+    pub fn restore(
+        &self,
+        snapshot: Arc<Snapshot>,
+        config: Option<AssemblerConfig>,
+    ) -> Result<(), EmulatorError> {
+        snapshot.inner.restore(&mut self.lock(), config.as_ref())
+    }
+}
+
+impl Default for Machine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Machine {
+    /// Locks the inner machine.
+    fn lock(&self) -> std::sync::MutexGuard<'_, RustMachine> {
+        self.inner
+            .lock()
+            .expect("An internal Mutex was poisoned! Some thread must have panicked while holding onto this Mutex!")
+    }
+}
+
+/// The FFI-safe version of an emulator snapshot from the `nand7400` crate. Snapshots are read-only here, and are taken and restored
+/// with a `Machine`.
+pub struct Snapshot {
+    /// The inner snapshot.
+    inner: RustSnapshot,
+}
+
+/// Public API for snapshots.
+impl Snapshot {
+    /// Reads a snapshot from JSON.
+    pub fn new(json: &str) -> Result<Self, EmulatorError> {
+        Ok(Self {
+            inner: RustSnapshot::from_json(json)?,
+        })
+    }
+
+    /// Loads a snapshot from a JSON file.
+    pub fn load(path: &str) -> Result<Self, EmulatorError> {
+        Ok(Self {
+            inner: RustSnapshot::load(path)?,
+        })
+    }
+
+    /// Saves the snapshot to a file, as JSON.
+    pub fn save(&self, path: &str) -> Result<(), EmulatorError> {
+        self.inner.save(path)
+    }
+
+    /// Converts the snapshot to JSON.
+    pub fn to_json(&self) -> String {
+        self.inner.to_json()
+    }
+
+    /// Checks whether the snapshot was saved with the given configuration. Snapshots saved without a configuration
+    /// match any of them.
+    pub fn matches_config(&self, config: AssemblerConfig) -> bool {
+        self.inner
+            .config_fingerprint
+            .is_none_or(|fingerprint| fingerprint == config.fingerprint())
+    }
+
+    /// The version of the snapshot format.
+    pub fn version(&self) -> u32 {
+        self.inner.version
+    }
+
+    /// The fingerprint of the configuration the snapshot was saved with, if any.
+    pub fn config_fingerprint(&self) -> Option<u64> {
+        self.inner.config_fingerprint
+    }
+
+    /// The program counter.
+    pub fn pc(&self) -> u16 {
+        self.inner.cpu.pc
+    }
+
+    /// The general-purpose registers.
+    pub fn registers(&self) -> Vec<u8> {
+        self.inner.cpu.registers.clone()
+    }
+
+    /// The flags register.
+    pub fn flags(&self) -> u8 {
+        self.inner.cpu.flags
+    }
+
+    /// Whether or not the CPU was halted.
+    pub fn halted(&self) -> bool {
+        self.inner.cpu.halted
+    }
+
+    /// The number of cycles executed.
+    pub fn cycles(&self) -> u64 {
+        self.inner.cpu.cycles
+    }
+
+    /// The entire memory image.
+    pub fn memory(&self) -> Result<Vec<u8>, EmulatorError> {
+        self.inner.memory()
+    }
 }
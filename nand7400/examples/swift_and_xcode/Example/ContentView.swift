@@ -9,16 +9,7 @@ import Nand7400
 import class Nand7400.Formatter
 import SwiftUI
 
-let assemblyConf = AssemblerConfig(
-	opcodes: [
-		Opcode(mnemonic: "nop", binary: 0x00, args: []),
-		Opcode(mnemonic: "lda", binary: 0x01, args: [OpcodeArg.immediate]),
-		Opcode(mnemonic: "ldb", binary: 0x02, args: [OpcodeArg.indirect]),
-		Opcode(mnemonic: "add", binary: 0x03, args: [OpcodeArg.immediate, OpcodeArg.immediate, OpcodeArg.immediate]),
-		Opcode(mnemonic: "jmp", binary: 0x04, args: [OpcodeArg.immediate, OpcodeArg.immediate]),
-		Opcode(mnemonic: "hlt", binary: 0xff, args: []),
-	]
-)
+let assemblyConf = nand7400Config()
 
 struct ContentView: View {
 	@State private var assemblyText = """
@@ -0,0 +1,78 @@
+use std::fmt;
+
+/// An algorithm that `.checksum` can use to work out a checksum of a range of the assembled binary. This can also be
+/// used directly on an assembled binary.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChecksumAlgorithm {
+    /// The sum of every byte, keeping only the lowest 8 bits. Written as `sum8`.
+    Sum8,
+
+    /// Every byte XORed together. Written as `xor8`.
+    Xor8,
+
+    /// CRC-8 with the polynomial `0x07` and an initial value of `0x00` (CRC-8/SMBUS). Written as `crc8`.
+    Crc8,
+
+    /// CRC-16/CCITT with the polynomial `0x1021` and an initial value of `0xFFFF` (CRC-16/CCITT-FALSE). Written as
+    /// `crc16`.
+    Crc16Ccitt,
+}
+
+impl ChecksumAlgorithm {
+    /// Every algorithm, in the order they're listed in errors.
+    pub const ALL: [ChecksumAlgorithm; 4] = [Self::Sum8, Self::Xor8, Self::Crc8, Self::Crc16Ccitt];
+
+    /// Gets an algorithm by the name it's written as in `.checksum` (e.g. `crc16`), ignoring case.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|algorithm| algorithm.to_string().eq_ignore_ascii_case(name))
+    }
+
+    /// Gets the number of bytes the checksum takes up.
+    pub fn width(&self) -> u16 {
+        match self {
+            Self::Sum8 | Self::Xor8 | Self::Crc8 => 1,
+            Self::Crc16Ccitt => 2,
+        }
+    }
+
+    /// Works out the checksum of some bytes.
+    pub fn compute(&self, bytes: impl IntoIterator<Item = u8>) -> u32 {
+        let bytes = bytes.into_iter();
+
+        match self {
+            Self::Sum8 => bytes.fold(0u8, |sum, byte| sum.wrapping_add(byte)) as u32,
+            Self::Xor8 => bytes.fold(0u8, |xor, byte| xor ^ byte) as u32,
+            Self::Crc8 => bytes.fold(0u8, |crc, byte| {
+                (0..8).fold(crc ^ byte, |crc, _| {
+                    if crc & 0x80 != 0 {
+                        (crc << 1) ^ 0x07
+                    } else {
+                        crc << 1
+                    }
+                })
+            }) as u32,
+            Self::Crc16Ccitt => bytes.fold(0xFFFFu16, |crc, byte| {
+                (0..8).fold(crc ^ ((byte as u16) << 8), |crc, _| {
+                    if crc & 0x8000 != 0 {
+                        (crc << 1) ^ 0x1021
+                    } else {
+                        crc << 1
+                    }
+                })
+            }) as u32,
+        }
+    }
+}
+
+impl fmt::Display for ChecksumAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Sum8 => write!(f, "sum8"),
+            Self::Xor8 => write!(f, "xor8"),
+            Self::Crc8 => write!(f, "crc8"),
+            Self::Crc16Ccitt => write!(f, "crc16"),
+        }
+    }
+}
@@ -0,0 +1,814 @@
+use crate::assembler::position::Position;
+use miette::Diagnostic;
+
+/// The error type for problems with an assembler configuration. Every error has the path to the value it's about in
+/// the configuration (e.g. `opcodes[3].mnemonic`), and the span of that value if the configuration was validated from
+/// its JSON source.
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error, Diagnostic)]
+pub enum ConfigError {
+    /// The configuration isn't valid JSON, or doesn't have the right structure.
+    #[error("The configuration couldn't be read: {}", message)]
+    #[diagnostic(code(nand7400::errors::config::json))]
+    Json {
+        /// The error from the JSON parser.
+        message: String,
+
+        /// The path to the value with the error. This is always empty, as the JSON parser doesn't know it.
+        path: String,
+
+        /// The span of the error in the JSON source.
+        #[label("Here")]
+        span: Option<Position>,
+    },
+
+    /// The configuration isn't valid TOML, or doesn't have the right structure.
+    #[error("The configuration couldn't be read: {}", message)]
+    #[diagnostic(code(nand7400::errors::config::toml))]
+    Toml {
+        /// The error from the TOML parser.
+        message: String,
+
+        /// The path to the value with the error. This is always empty, as the TOML parser doesn't know it.
+        path: String,
+
+        /// The span of the error in the TOML source.
+        #[label("Here")]
+        span: Option<Position>,
+    },
+
+    /// The configuration isn't valid YAML, or doesn't have the right structure.
+    #[error("The configuration couldn't be read: {}", message)]
+    #[diagnostic(code(nand7400::errors::config::yaml))]
+    Yaml {
+        /// The error from the YAML parser.
+        message: String,
+
+        /// The path to the value with the error. This is always empty, as the YAML parser doesn't know it.
+        path: String,
+
+        /// The span of the error in the YAML source.
+        #[label("Here")]
+        span: Option<Position>,
+    },
+
+    /// The format of a configuration file couldn't be worked out from its extension.
+    #[error("'{}' isn't a JSON, TOML, or YAML file.", file)]
+    #[diagnostic(
+        code(nand7400::errors::config::unknown_format),
+        help("Configuration files need to end in '.json', '.toml', '.yaml', or '.yml'.")
+    )]
+    UnknownFormat {
+        /// The configuration file.
+        file: String,
+
+        /// The path to the value with the error. This is always empty.
+        path: String,
+
+        /// The span of the error. This is always `None`.
+        span: Option<Position>,
+    },
+
+    /// A configuration file couldn't be read or written.
+    #[error("Couldn't access configuration file '{}': {}", file, message)]
+    #[diagnostic(code(nand7400::errors::config::io))]
+    Io {
+        /// The configuration file.
+        file: String,
+
+        /// The underlying error message.
+        message: String,
+
+        /// The path to the value with the error. This is always empty.
+        path: String,
+
+        /// The span of the error. This is always `None`.
+        span: Option<Position>,
+    },
+
+    /// A configuration extends a profile that doesn't exist.
+    #[error("Profile '{}' doesn't exist.", name)]
+    #[diagnostic(
+        code(nand7400::errors::config::unknown_profile),
+        help(
+            "The built-in profiles are 'nand7400' (the latest), 'nand7400_v1', and 'nand7400_v2'."
+        )
+    )]
+    UnknownProfile {
+        /// The name of the profile.
+        name: String,
+
+        /// The path to the name of the profile.
+        path: String,
+
+        /// The span of the name of the profile.
+        #[label("This profile")]
+        span: Option<Position>,
+    },
+
+    /// A configuration extends another configuration file that has a problem.
+    #[error("Configuration '{}' couldn't be extended.", file)]
+    #[diagnostic(code(nand7400::errors::config::extends))]
+    Extends {
+        /// The file being extended, as it's written in the configuration.
+        file: String,
+
+        /// The problem with the extended file.
+        #[source]
+        source: Box<ConfigError>,
+
+        /// The path to the name of the file.
+        path: String,
+
+        /// The span of the name of the file.
+        #[label("This file")]
+        span: Option<Position>,
+    },
+
+    /// A configuration extends another configuration file, but wasn't loaded from a file itself, so there's nowhere to
+    /// find it relative to.
+    #[error(
+        "Configuration '{}' can only be extended by a configuration file.",
+        file
+    )]
+    #[diagnostic(
+        code(nand7400::errors::config::extends_file),
+        help("Load the configuration with `AssemblerConfig::load`, or extend a built-in profile instead.")
+    )]
+    ExtendsFile {
+        /// The file being extended, as it's written in the configuration.
+        file: String,
+
+        /// The path to the name of the file.
+        path: String,
+
+        /// The span of the name of the file.
+        #[label("This file")]
+        span: Option<Position>,
+    },
+
+    /// A configuration extends itself, either directly or through other files.
+    #[error("Configuration '{}' extends itself.", file)]
+    #[diagnostic(
+        code(nand7400::errors::config::extends_cycle),
+        help("Make sure that none of the files this one extends extend it in turn.")
+    )]
+    ExtendsCycle {
+        /// The file that was already being extended.
+        file: String,
+
+        /// The path to the name of the file.
+        path: String,
+
+        /// The span of the name of the file.
+        #[label("This file")]
+        span: Option<Position>,
+    },
+
+    /// An overlay removes or replaces an opcode that isn't there.
+    #[error(
+        "Overlay '{}' changes opcode '{}', but there isn't one.",
+        overlay,
+        mnemonic
+    )]
+    #[diagnostic(
+        code(nand7400::errors::config::missing_opcode),
+        help("Check the spelling of the mnemonic, and whether an earlier overlay removed it already.")
+    )]
+    MissingOpcode {
+        /// The name of the overlay.
+        overlay: String,
+
+        /// The mnemonic of the opcode.
+        mnemonic: String,
+
+        /// The path to the mnemonic in the overlay.
+        path: String,
+
+        /// The span of the mnemonic in the overlay.
+        #[label("This opcode")]
+        span: Option<Position>,
+    },
+
+    /// An overlay adds an opcode that's already there.
+    #[error(
+        "Overlay '{}' adds opcode '{}', but it's already there.",
+        overlay,
+        signature
+    )]
+    #[diagnostic(
+        code(nand7400::errors::config::opcode_exists),
+        help("Use `replace` to change an opcode that's already there.")
+    )]
+    OpcodeExists {
+        /// The name of the overlay.
+        overlay: String,
+
+        /// The mnemonic and arguments of the opcode, e.g. `lda Immediate`.
+        signature: String,
+
+        /// The path to the mnemonic of the opcode in the overlay.
+        path: String,
+
+        /// The span of the mnemonic of the opcode in the overlay.
+        #[label("This opcode")]
+        span: Option<Position>,
+    },
+
+    /// Two overlays both define an opcode with the same binary.
+    #[error(
+        "Overlays '{}' and '{}' both define an opcode with binary {:#04X}.",
+        overlay,
+        other,
+        binary
+    )]
+    #[diagnostic(
+        code(nand7400::errors::config::overlay_conflict),
+        help("Overlays that are used together must give their opcodes different binaries.")
+    )]
+    OverlayConflict {
+        /// The name of the overlay.
+        overlay: String,
+
+        /// The name of the earlier overlay that defined the same binary.
+        other: String,
+
+        /// The binary of the opcode.
+        binary: u8,
+
+        /// The path to the binary of the opcode in the overlay.
+        path: String,
+
+        /// The span of the binary of the opcode in the overlay.
+        #[label("This binary")]
+        span: Option<Position>,
+    },
+
+    /// The address width isn't one that's supported.
+    #[error("Addresses can't be {} bits wide.", bits)]
+    #[diagnostic(
+        code(nand7400::errors::config::invalid_address_width),
+        help("Addresses can be 8, 12, 16, or 24 bits wide.")
+    )]
+    InvalidAddressWidth {
+        /// The address width in bits.
+        bits: u8,
+
+        /// The path to the address width.
+        path: String,
+
+        /// The span of the address width.
+        #[label("This width")]
+        span: Option<Position>,
+    },
+
+    /// The word size isn't one that's supported.
+    #[error("Words can't be {} bytes.", size)]
+    #[diagnostic(
+        code(nand7400::errors::config::invalid_word_size),
+        help("Words can be from 1 to 4 bytes.")
+    )]
+    InvalidWordSize {
+        /// The word size in bytes.
+        size: u8,
+
+        /// The path to the word size.
+        path: String,
+
+        /// The span of the word size.
+        #[label("This size")]
+        span: Option<Position>,
+    },
+
+    /// Two opcodes have the same mnemonic (or alias) and arguments, so there's no way to tell them apart.
+    #[error("Opcode '{}' is defined more than once.", signature)]
+    #[diagnostic(
+        code(nand7400::errors::config::duplicate_opcode),
+        help("Overloaded mnemonics need to take different arguments. Remove or change one of the opcodes.")
+    )]
+    DuplicateOpcode {
+        /// The signature of the opcode.
+        signature: String,
+
+        /// The path to the mnemonic of the duplicate.
+        path: String,
+
+        /// The span of the mnemonic of the duplicate.
+        #[label("This opcode")]
+        span: Option<Position>,
+    },
+
+    /// Two opcodes have binary representations that can't be told apart.
+    #[error(
+        "Opcode '{}' has the same binary as '{}' ({:#04x}).",
+        mnemonic,
+        other,
+        binary
+    )]
+    #[diagnostic(
+        code(nand7400::errors::config::overlapping_binary),
+        help(
+            "Give each opcode a different binary. Bits that are used for bit fields don't count."
+        )
+    )]
+    OverlappingBinary {
+        /// The mnemonic of the opcode.
+        mnemonic: String,
+
+        /// The mnemonic of the opcode defined earlier with the same binary.
+        other: String,
+
+        /// The binary of the opcode.
+        binary: u8,
+
+        /// The path to the binary of the opcode.
+        path: String,
+
+        /// The span of the binary of the opcode.
+        #[label("This binary")]
+        span: Option<Position>,
+    },
+
+    /// A mnemonic isn't a valid identifier, so it could never be parsed.
+    #[error("Mnemonic '{}' isn't a valid identifier.", mnemonic)]
+    #[diagnostic(
+        code(nand7400::errors::config::invalid_mnemonic),
+        help("Mnemonics can only have letters, digits, and underscores, and can't start with a digit.")
+    )]
+    InvalidMnemonic {
+        /// The mnemonic.
+        mnemonic: String,
+
+        /// The path to the mnemonic.
+        path: String,
+
+        /// The span of the mnemonic.
+        #[label("This mnemonic")]
+        span: Option<Position>,
+    },
+
+    /// A mnemonic starts with a `.`, so it would be parsed as a keyword.
+    #[error(
+        "Mnemonic '{}' starts with a '.', which is reserved for keywords.",
+        mnemonic
+    )]
+    #[diagnostic(
+        code(nand7400::errors::config::keyword_mnemonic),
+        help("Remove the '.' from the start of the mnemonic.")
+    )]
+    KeywordMnemonic {
+        /// The mnemonic.
+        mnemonic: String,
+
+        /// The path to the mnemonic.
+        path: String,
+
+        /// The span of the mnemonic.
+        #[label("This mnemonic")]
+        span: Option<Position>,
+    },
+
+    /// A bit field doesn't fit in a byte.
+    #[error(
+        "A bit field of '{}' is {} bits wide at bit {}, which doesn't fit in a byte.",
+        mnemonic,
+        width,
+        offset
+    )]
+    #[diagnostic(
+        code(nand7400::errors::config::invalid_field),
+        help("Bit fields need to be at least 1 bit wide, and their offset plus their width can't be more than 8.")
+    )]
+    InvalidField {
+        /// The mnemonic of the opcode with the field.
+        mnemonic: String,
+
+        /// The offset of the field.
+        offset: u8,
+
+        /// The width of the field.
+        width: u8,
+
+        /// The path to the field.
+        path: String,
+
+        /// The span of the field.
+        #[label("This field")]
+        span: Option<Position>,
+    },
+
+    /// Two registers have the same name.
+    #[error("Register '{}' is defined more than once.", name)]
+    #[diagnostic(
+        code(nand7400::errors::config::duplicate_register),
+        help("Remove or rename one of the registers.")
+    )]
+    DuplicateRegister {
+        /// The name of the register.
+        name: String,
+
+        /// The path to the name of the duplicate.
+        path: String,
+
+        /// The span of the name of the duplicate.
+        #[label("This register")]
+        span: Option<Position>,
+    },
+
+    /// A register name isn't a valid identifier, so it could never be parsed.
+    #[error("Register '{}' isn't a valid identifier.", name)]
+    #[diagnostic(
+        code(nand7400::errors::config::invalid_register_name),
+        help("Register names can only have letters, digits, and underscores, and can't start with a digit.")
+    )]
+    InvalidRegisterName {
+        /// The name of the register.
+        name: String,
+
+        /// The path to the name of the register.
+        path: String,
+
+        /// The span of the name of the register.
+        #[label("This register")]
+        span: Option<Position>,
+    },
+    /// Two regions have the same name.
+    #[error("Region '{}' is defined more than once.", name)]
+    #[diagnostic(
+        code(nand7400::errors::config::duplicate_region),
+        help("Remove or rename one of the regions.")
+    )]
+    DuplicateRegion {
+        /// The name of the region.
+        name: String,
+
+        /// The path to the name of the duplicate.
+        path: String,
+
+        /// The span of the name of the duplicate.
+        #[label("This region")]
+        span: Option<Position>,
+    },
+
+    /// A region is empty or doesn't fit in the address space.
+    #[error(
+        "Region '{}' of size {} can't be placed at {:#06X}.",
+        name,
+        size,
+        start
+    )]
+    #[diagnostic(
+        code(nand7400::errors::config::region_out_of_range),
+        help("Make sure the region isn't empty and fits entirely inside of the address space.")
+    )]
+    RegionOutOfRange {
+        /// The name of the region.
+        name: String,
+
+        /// The first address of the region.
+        start: u32,
+
+        /// The number of addresses in the region.
+        size: u32,
+
+        /// The path to the region.
+        path: String,
+
+        /// The span of the region.
+        #[label("This region")]
+        span: Option<Position>,
+    },
+
+    /// Two regions share some addresses.
+    #[error("Region '{}' overlaps region '{}'.", name, other)]
+    #[diagnostic(
+        code(nand7400::errors::config::region_overlap),
+        help("Move or shrink one of the regions.")
+    )]
+    RegionOverlap {
+        /// The name of the region.
+        name: String,
+
+        /// The name of the region defined before it that it overlaps.
+        other: String,
+
+        /// The path to the region.
+        path: String,
+
+        /// The span of the region.
+        #[label("This region")]
+        span: Option<Position>,
+    },
+
+    /// A pseudo-instruction has the same mnemonic as an opcode or another pseudo-instruction.
+    #[error(
+        "Pseudo-instruction '{}' has the same mnemonic as an opcode or another pseudo-instruction.",
+        mnemonic
+    )]
+    #[diagnostic(
+        code(nand7400::errors::config::duplicate_pseudo),
+        help("Rename the pseudo-instruction, or remove the opcode it has the same mnemonic as.")
+    )]
+    DuplicatePseudo {
+        /// The mnemonic of the pseudo-instruction.
+        mnemonic: String,
+
+        /// The path to the mnemonic.
+        path: String,
+
+        /// The span of the mnemonic.
+        #[label("This mnemonic")]
+        span: Option<Position>,
+    },
+
+    /// A parameter of a pseudo-instruction can't be told apart from other arguments.
+    #[error(
+        "Parameter '{}' of pseudo-instruction '{}' isn't a unique identifier.",
+        param,
+        mnemonic
+    )]
+    #[diagnostic(
+        code(nand7400::errors::config::invalid_pseudo_param),
+        help("Parameters need to be valid identifiers, and can't have the same name as each other or a register.")
+    )]
+    InvalidPseudoParam {
+        /// The mnemonic of the pseudo-instruction.
+        mnemonic: String,
+
+        /// The name of the parameter.
+        param: String,
+
+        /// The path to the parameter.
+        path: String,
+
+        /// The span of the parameter.
+        #[label("This parameter")]
+        span: Option<Position>,
+    },
+
+    /// A line in the body of a pseudo-instruction can't be expanded.
+    #[error("Pseudo-instruction '{}' can't be expanded: {}", mnemonic, message)]
+    #[diagnostic(
+        code(nand7400::errors::config::invalid_pseudo_body),
+        help("The body of a pseudo-instruction can only use opcodes and keywords, and can't define labels.")
+    )]
+    InvalidPseudoBody {
+        /// The mnemonic of the pseudo-instruction.
+        mnemonic: String,
+
+        /// What's wrong with the line.
+        message: String,
+
+        /// The path to the line.
+        path: String,
+
+        /// The span of the line.
+        #[label("This line")]
+        span: Option<Position>,
+    },
+
+    /// An argument constraint can never be met, or is for an argument that doesn't exist.
+    #[error(
+        "Opcode '{}' has an invalid argument constraint: {}",
+        mnemonic,
+        message
+    )]
+    #[diagnostic(
+        code(nand7400::errors::config::invalid_constraint),
+        help("Each constraint is for the argument at its index, `align` can't be 0, and `min` can't be over `max`.")
+    )]
+    InvalidConstraint {
+        /// The mnemonic of the opcode.
+        mnemonic: String,
+
+        /// What's wrong with the constraint.
+        message: String,
+
+        /// The path to the constraint.
+        path: String,
+
+        /// The span of the constraint.
+        #[label("This constraint")]
+        span: Option<Position>,
+    },
+    /// A vector's name isn't a valid identifier or is the name of a register, so `.vector` could never refer to it.
+    #[error("Vector '{}' isn't a valid name.", name)]
+    #[diagnostic(
+        code(nand7400::errors::config::invalid_vector_name),
+        help("Vector names need to be valid identifiers (letters, digits, and underscores), and can't be registers.")
+    )]
+    InvalidVectorName {
+        /// The name of the vector.
+        name: String,
+
+        /// The path to the name.
+        path: String,
+
+        /// The span of the name.
+        #[label("This name")]
+        span: Option<Position>,
+    },
+
+    /// Two vectors have the same name.
+    #[error("Vector '{}' is defined more than once.", name)]
+    #[diagnostic(
+        code(nand7400::errors::config::duplicate_vector),
+        help("Remove or rename one of the vectors.")
+    )]
+    DuplicateVector {
+        /// The name of the vector.
+        name: String,
+
+        /// The path to the name of the duplicate.
+        path: String,
+
+        /// The span of the name of the duplicate.
+        #[label("This vector")]
+        span: Option<Position>,
+    },
+
+    /// A vector doesn't fit in the address space.
+    #[error("Vector '{}' can't be placed at {:#06X}.", name, address)]
+    #[diagnostic(
+        code(nand7400::errors::config::vector_out_of_range),
+        help("Vectors are as big as an address, and have to fit entirely inside of the address space.")
+    )]
+    VectorOutOfRange {
+        /// The name of the vector.
+        name: String,
+
+        /// The address of the vector.
+        address: u32,
+
+        /// The path to the vector.
+        path: String,
+
+        /// The span of the vector.
+        #[label("This vector")]
+        span: Option<Position>,
+    },
+
+    /// Two vectors share some addresses.
+    #[error("Vector '{}' overlaps vector '{}'.", name, other)]
+    #[diagnostic(
+        code(nand7400::errors::config::vector_overlap),
+        help("Vectors are as big as an address, so they need to be at least that far apart.")
+    )]
+    VectorOverlap {
+        /// The name of the vector.
+        name: String,
+
+        /// The name of the vector defined before it that it overlaps.
+        other: String,
+
+        /// The path to the vector.
+        path: String,
+
+        /// The span of the vector.
+        #[label("This vector")]
+        span: Option<Position>,
+    },
+
+    /// More than one vector holds the entry point.
+    #[error(
+        "Vector '{}' holds the entry point, but so does vector '{}'.",
+        name,
+        other
+    )]
+    #[diagnostic(
+        code(nand7400::errors::config::multiple_entry_vectors),
+        help("Only one vector can have `entry` set.")
+    )]
+    MultipleEntryVectors {
+        /// The name of the vector.
+        name: String,
+
+        /// The name of the vector defined before it that also holds the entry point.
+        other: String,
+
+        /// The path to the vector.
+        path: String,
+
+        /// The span of the vector.
+        #[label("This vector")]
+        span: Option<Position>,
+    },
+}
+
+impl ConfigError {
+    /// Gets the path to the value the error is about (e.g. `opcodes[3].mnemonic`).
+    pub fn path(&self) -> &str {
+        match self {
+            Self::Json { path, .. }
+            | Self::Toml { path, .. }
+            | Self::Yaml { path, .. }
+            | Self::UnknownFormat { path, .. }
+            | Self::Io { path, .. }
+            | Self::UnknownProfile { path, .. }
+            | Self::InvalidAddressWidth { path, .. }
+            | Self::InvalidWordSize { path, .. }
+            | Self::Extends { path, .. }
+            | Self::ExtendsFile { path, .. }
+            | Self::ExtendsCycle { path, .. }
+            | Self::MissingOpcode { path, .. }
+            | Self::OpcodeExists { path, .. }
+            | Self::OverlayConflict { path, .. }
+            | Self::DuplicateOpcode { path, .. }
+            | Self::OverlappingBinary { path, .. }
+            | Self::InvalidMnemonic { path, .. }
+            | Self::KeywordMnemonic { path, .. }
+            | Self::InvalidField { path, .. }
+            | Self::DuplicateRegister { path, .. }
+            | Self::InvalidRegisterName { path, .. }
+            | Self::DuplicateRegion { path, .. }
+            | Self::RegionOutOfRange { path, .. }
+            | Self::RegionOverlap { path, .. }
+            | Self::DuplicatePseudo { path, .. }
+            | Self::InvalidPseudoParam { path, .. }
+            | Self::InvalidPseudoBody { path, .. }
+            | Self::InvalidConstraint { path, .. }
+            | Self::InvalidVectorName { path, .. }
+            | Self::DuplicateVector { path, .. }
+            | Self::VectorOutOfRange { path, .. }
+            | Self::VectorOverlap { path, .. }
+            | Self::MultipleEntryVectors { path, .. } => path,
+        }
+    }
+
+    /// Gets the span of the value the error is about in the JSON source, if there is one.
+    pub fn span(&self) -> Option<Position> {
+        match self {
+            Self::Json { span, .. }
+            | Self::Toml { span, .. }
+            | Self::Yaml { span, .. }
+            | Self::UnknownFormat { span, .. }
+            | Self::Io { span, .. }
+            | Self::UnknownProfile { span, .. }
+            | Self::InvalidAddressWidth { span, .. }
+            | Self::InvalidWordSize { span, .. }
+            | Self::Extends { span, .. }
+            | Self::ExtendsFile { span, .. }
+            | Self::ExtendsCycle { span, .. }
+            | Self::MissingOpcode { span, .. }
+            | Self::OpcodeExists { span, .. }
+            | Self::OverlayConflict { span, .. }
+            | Self::DuplicateOpcode { span, .. }
+            | Self::OverlappingBinary { span, .. }
+            | Self::InvalidMnemonic { span, .. }
+            | Self::KeywordMnemonic { span, .. }
+            | Self::InvalidField { span, .. }
+            | Self::DuplicateRegister { span, .. }
+            | Self::InvalidRegisterName { span, .. }
+            | Self::DuplicateRegion { span, .. }
+            | Self::RegionOutOfRange { span, .. }
+            | Self::RegionOverlap { span, .. }
+            | Self::DuplicatePseudo { span, .. }
+            | Self::InvalidPseudoParam { span, .. }
+            | Self::InvalidPseudoBody { span, .. }
+            | Self::InvalidConstraint { span, .. }
+            | Self::InvalidVectorName { span, .. }
+            | Self::DuplicateVector { span, .. }
+            | Self::VectorOutOfRange { span, .. }
+            | Self::VectorOverlap { span, .. }
+            | Self::MultipleEntryVectors { span, .. } => *span,
+        }
+    }
+
+    /// Gets the span of the value the error is about, so that it can be filled in once the source is known.
+    pub(super) fn span_mut(&mut self) -> &mut Option<Position> {
+        match self {
+            Self::Json { span, .. }
+            | Self::Toml { span, .. }
+            | Self::Yaml { span, .. }
+            | Self::UnknownFormat { span, .. }
+            | Self::Io { span, .. }
+            | Self::UnknownProfile { span, .. }
+            | Self::InvalidAddressWidth { span, .. }
+            | Self::InvalidWordSize { span, .. }
+            | Self::Extends { span, .. }
+            | Self::ExtendsFile { span, .. }
+            | Self::ExtendsCycle { span, .. }
+            | Self::MissingOpcode { span, .. }
+            | Self::OpcodeExists { span, .. }
+            | Self::OverlayConflict { span, .. }
+            | Self::DuplicateOpcode { span, .. }
+            | Self::OverlappingBinary { span, .. }
+            | Self::InvalidMnemonic { span, .. }
+            | Self::KeywordMnemonic { span, .. }
+            | Self::InvalidField { span, .. }
+            | Self::DuplicateRegister { span, .. }
+            | Self::InvalidRegisterName { span, .. }
+            | Self::DuplicateRegion { span, .. }
+            | Self::RegionOutOfRange { span, .. }
+            | Self::RegionOverlap { span, .. }
+            | Self::DuplicatePseudo { span, .. }
+            | Self::InvalidPseudoParam { span, .. }
+            | Self::InvalidPseudoBody { span, .. }
+            | Self::InvalidConstraint { span, .. }
+            | Self::InvalidVectorName { span, .. }
+            | Self::DuplicateVector { span, .. }
+            | Self::VectorOutOfRange { span, .. }
+            | Self::VectorOverlap { span, .. }
+            | Self::MultipleEntryVectors { span, .. } => span,
+        }
+    }
+}
@@ -0,0 +1,137 @@
+use super::{errors::ConfigError, spans::offset_of, AssemblerConfig, ConfigFile};
+use crate::assembler::position::Position;
+use serde::de::DeserializeOwned;
+use std::{fs, path::Path};
+
+/// A file format that configurations can be written in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConfigFormat {
+    /// JSON, e.g. `assembly.conf.json`.
+    Json,
+
+    /// TOML, e.g. `assembly.conf.toml`.
+    Toml,
+
+    /// YAML, e.g. `assembly.conf.yaml` or `assembly.conf.yml`.
+    Yaml,
+}
+
+impl ConfigFormat {
+    /// Works out the format of a configuration file from its extension.
+    pub fn from_path(path: impl AsRef<Path>) -> Option<Self> {
+        let extension = path.as_ref().extension()?.to_str()?.to_ascii_lowercase();
+
+        match extension.as_str() {
+            "json" => Some(Self::Json),
+            "toml" => Some(Self::Toml),
+            "yaml" | "yml" => Some(Self::Yaml),
+            _ => None,
+        }
+    }
+}
+
+/// Reading and writing configurations in different formats.
+impl AssemblerConfig {
+    /// Reads a configuration in the given format.
+    pub fn from_str_as(source: &str, format: ConfigFormat) -> Result<Self, ConfigError> {
+        parse(source, format)
+    }
+
+    /// Writes the configuration in the given format.
+    pub fn to_string_as(&self, format: ConfigFormat) -> String {
+        match format {
+            ConfigFormat::Json => {
+                serde_json::to_string_pretty(self).expect("Configs can always be serialized!")
+            }
+            ConfigFormat::Toml => {
+                toml::to_string_pretty(self).expect("Configs can always be serialized!")
+            }
+            ConfigFormat::Yaml => {
+                serde_norway::to_string(self).expect("Configs can always be serialized!")
+            }
+        }
+    }
+
+    /// Loads a configuration from a file, working out its format from its extension.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        let format = format_of(path)?;
+        let source = fs::read_to_string(path).map_err(|err| io_error(path, err))?;
+
+        // Files that are extended are found relative to this one, and can't extend it in turn.
+        let mut chain = fs::canonicalize(path).into_iter().collect();
+
+        parse::<ConfigFile>(&source, format)?
+            .resolve_in(path.parent(), &mut chain)
+            .map_err(|mut errors| errors.remove(0))
+    }
+
+    /// Saves the configuration to a file, working out its format from its extension.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ConfigError> {
+        let path = path.as_ref();
+        let format = format_of(path)?;
+
+        fs::write(path, self.to_string_as(format)).map_err(|err| io_error(path, err))
+    }
+
+    /// Gets the JSON Schema of configurations, which editors can use to check and autocomplete configuration files.
+    pub fn json_schema() -> String {
+        let schema = schemars::schema_for!(ConfigFile);
+
+        serde_json::to_string_pretty(&schema).expect("Schemas can always be serialized!")
+    }
+}
+
+/// Reads a value in the given format.
+pub(super) fn parse<T: DeserializeOwned>(
+    source: &str,
+    format: ConfigFormat,
+) -> Result<T, ConfigError> {
+    match format {
+        ConfigFormat::Json => serde_json::from_str(source).map_err(|err| json_error(source, err)),
+
+        ConfigFormat::Toml => toml::from_str(source).map_err(|err| ConfigError::Toml {
+            message: err.message().to_string(),
+            path: String::new(),
+            span: err.span().map(|span| Position::new(span.start, span.end)),
+        }),
+
+        ConfigFormat::Yaml => serde_norway::from_str(source).map_err(|err| ConfigError::Yaml {
+            span: err
+                .location()
+                .map(|location| Position::new(location.index(), location.index())),
+            message: err.to_string(),
+            path: String::new(),
+        }),
+    }
+}
+
+/// Converts a JSON error into a configuration error, pointing at where the error is in `source`.
+pub(super) fn json_error(source: &str, err: serde_json::Error) -> ConfigError {
+    let offset = offset_of(source, err.line(), err.column());
+
+    ConfigError::Json {
+        message: err.to_string(),
+        path: String::new(),
+        span: Some(Position::new(offset, offset)),
+    }
+}
+
+/// Works out the format of a configuration file, or errors if it isn't one we know.
+pub(super) fn format_of(path: &Path) -> Result<ConfigFormat, ConfigError> {
+    ConfigFormat::from_path(path).ok_or_else(|| ConfigError::UnknownFormat {
+        file: path.display().to_string(),
+        path: String::new(),
+        span: None,
+    })
+}
+
+/// Converts an IO error into a configuration error.
+pub(super) fn io_error(path: &Path, err: std::io::Error) -> ConfigError {
+    ConfigError::Io {
+        file: path.display().to_string(),
+        message: err.to_string(),
+        path: String::new(),
+        span: None,
+    }
+}
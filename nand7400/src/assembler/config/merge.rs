@@ -0,0 +1,299 @@
+use super::{
+    errors::ConfigError,
+    formats::{format_of, io_error, parse},
+    validate::binaries_overlap,
+    AssemblerConfig, ConfigFile, Opcode,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// A set of changes to a configuration's opcodes, e.g. to add some lab-specific instructions to a base ISA. Opcodes
+/// are removed first, then replaced, then added.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct Overlay {
+    /// The name of the overlay, which errors refer to it by.
+    pub name: String,
+
+    /// The mnemonics of the opcodes to remove. Every opcode with one of these mnemonics is removed. Aliases can't be
+    /// used here.
+    #[serde(default)]
+    pub remove: Vec<String>,
+
+    /// Opcodes to replace the existing ones with. Every opcode with the same mnemonic as one of these is replaced,
+    /// and the new opcodes take the place of the first one.
+    #[serde(default)]
+    pub replace: Vec<Opcode>,
+
+    /// New opcodes to add.
+    #[serde(default)]
+    pub add: Vec<Opcode>,
+}
+
+/// Combining configurations.
+impl AssemblerConfig {
+    /// Applies overlays to the configuration in order, returning the merged, flattened configuration. Every conflict
+    /// is returned: removing or replacing an opcode that isn't there, adding one that already is, or two overlays
+    /// defining opcodes with the same binary.
+    pub fn merge(&self, overlays: &[Overlay]) -> Result<Self, Vec<ConfigError>> {
+        let mut merged = self.clone();
+        let mut errors = vec![];
+
+        // The opcodes defined by the overlays so far and not removed or replaced since, along with the overlay that
+        // defined them.
+        let mut defined: Vec<(&Opcode, &str)> = vec![];
+
+        for (i, overlay) in overlays.iter().enumerate() {
+            for (j, mnemonic) in overlay.remove.iter().enumerate() {
+                if merged
+                    .opcodes
+                    .iter()
+                    .any(|opcode| &opcode.mnemonic == mnemonic)
+                {
+                    merged.opcodes.retain(|opcode| &opcode.mnemonic != mnemonic);
+                    defined.retain(|(opcode, _)| &opcode.mnemonic != mnemonic);
+                } else {
+                    errors.push(ConfigError::MissingOpcode {
+                        overlay: overlay.name.clone(),
+                        mnemonic: mnemonic.clone(),
+                        path: format!("overlays[{}].remove[{}]", i, j),
+                        span: None,
+                    });
+                }
+            }
+
+            for (j, opcode) in overlay.replace.iter().enumerate() {
+                // All of the replacements for a mnemonic are put in at once, when it's first seen.
+                if overlay.replace[..j]
+                    .iter()
+                    .any(|other| other.mnemonic == opcode.mnemonic)
+                {
+                    continue;
+                }
+
+                let Some(index) = merged
+                    .opcodes
+                    .iter()
+                    .position(|other| other.mnemonic == opcode.mnemonic)
+                else {
+                    errors.push(ConfigError::MissingOpcode {
+                        overlay: overlay.name.clone(),
+                        mnemonic: opcode.mnemonic.clone(),
+                        path: format!("overlays[{}].replace[{}].mnemonic", i, j),
+                        span: None,
+                    });
+
+                    continue;
+                };
+
+                merged
+                    .opcodes
+                    .retain(|other| other.mnemonic != opcode.mnemonic);
+                defined.retain(|(other, _)| other.mnemonic != opcode.mnemonic);
+                merged.opcodes.splice(
+                    index..index,
+                    overlay.replace[j..]
+                        .iter()
+                        .filter(|other| other.mnemonic == opcode.mnemonic)
+                        .cloned(),
+                );
+            }
+
+            for (j, opcode) in overlay.add.iter().enumerate() {
+                if merged
+                    .opcodes
+                    .iter()
+                    .any(|other| other.mnemonic == opcode.mnemonic && other.args == opcode.args)
+                {
+                    errors.push(ConfigError::OpcodeExists {
+                        overlay: overlay.name.clone(),
+                        signature: opcode.signature(),
+                        path: format!("overlays[{}].add[{}].mnemonic", i, j),
+                        span: None,
+                    });
+                } else {
+                    merged.opcodes.push(opcode.clone());
+                }
+            }
+
+            let replaced = overlay
+                .replace
+                .iter()
+                .enumerate()
+                .map(|(j, opcode)| (opcode, format!("overlays[{}].replace[{}].binary", i, j)));
+            let added = overlay
+                .add
+                .iter()
+                .enumerate()
+                .map(|(j, opcode)| (opcode, format!("overlays[{}].add[{}].binary", i, j)));
+
+            for (opcode, path) in replaced.chain(added) {
+                if let Some((_, other)) = defined
+                    .iter()
+                    .find(|(other, name)| *name != overlay.name && binaries_overlap(other, opcode))
+                {
+                    errors.push(ConfigError::OverlayConflict {
+                        overlay: overlay.name.clone(),
+                        other: other.to_string(),
+                        binary: opcode.binary,
+                        path,
+                        span: None,
+                    });
+                }
+            }
+
+            defined.extend(
+                overlay
+                    .replace
+                    .iter()
+                    .chain(&overlay.add)
+                    .map(|opcode| (opcode, overlay.name.as_str())),
+            );
+        }
+
+        if errors.is_empty() {
+            Ok(merged)
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+impl ConfigFile {
+    /// Resolves the file into a full configuration. This starts from whatever the file extends, then replaces the
+    /// opcodes, registers, regions, pseudo-instructions, and vectors with the file's own, and then applies the file's
+    /// overlays. Only built-in profiles can be extended here, as there's no file to find other configuration files
+    /// relative to; `AssemblerConfig::load` resolves those.
+    pub fn resolve(self) -> Result<AssemblerConfig, Vec<ConfigError>> {
+        self.resolve_in(None, &mut vec![])
+    }
+
+    /// Resolves the file, where other configuration files that are extended are found relative to `dir` (and can't be
+    /// extended at all without one), and `chain` is the files that are already being extended.
+    pub(super) fn resolve_in(
+        self,
+        dir: Option<&Path>,
+        chain: &mut Vec<PathBuf>,
+    ) -> Result<AssemblerConfig, Vec<ConfigError>> {
+        let mut config = match &self.extends {
+            Some(name) => match AssemblerConfig::profile(name) {
+                Some(profile) => profile,
+                None => extend_file(name, dir, chain)?,
+            },
+
+            None => AssemblerConfig::default(),
+        };
+
+        config.opcodes.retain(|opcode| {
+            !self
+                .opcodes
+                .iter()
+                .any(|other| other.mnemonic == opcode.mnemonic)
+        });
+        config.opcodes.extend(self.opcodes);
+
+        config.registers.retain(|register| {
+            !self
+                .registers
+                .iter()
+                .any(|other| other.name == register.name)
+        });
+        config.registers.extend(self.registers);
+
+        config
+            .regions
+            .retain(|region| !self.regions.iter().any(|other| other.name == region.name));
+        config.regions.extend(self.regions);
+
+        config.pseudo_instructions.retain(|pseudo| {
+            !self
+                .pseudo_instructions
+                .iter()
+                .any(|other| other.mnemonic == pseudo.mnemonic)
+        });
+        config.pseudo_instructions.extend(self.pseudo_instructions);
+
+        config
+            .vectors
+            .retain(|vector| !self.vectors.iter().any(|other| other.name == vector.name));
+        config.vectors.extend(self.vectors);
+
+        if let Some(address_bits) = self.address_bits {
+            config.address_bits = address_bits;
+        }
+
+        if let Some(word_size) = self.word_size {
+            // This is checked here rather than only when validating, as the assembler can't encode any other size.
+            if !(1..=4).contains(&word_size) {
+                return Err(vec![ConfigError::InvalidWordSize {
+                    size: word_size,
+                    path: "word_size".to_string(),
+                    span: None,
+                }]);
+            }
+
+            config.word_size = word_size;
+        }
+
+        if let Some(endianness) = self.endianness {
+            config.endianness = endianness;
+        }
+
+        config.merge(&self.overlays)
+    }
+}
+
+/// Reads and resolves a configuration file that another one extends. Names that don't look like files are taken to
+/// be profiles that don't exist.
+fn extend_file(
+    name: &str,
+    dir: Option<&Path>,
+    chain: &mut Vec<PathBuf>,
+) -> Result<AssemblerConfig, Vec<ConfigError>> {
+    let Ok(format) = format_of(Path::new(name)) else {
+        return Err(vec![ConfigError::UnknownProfile {
+            name: name.to_string(),
+            path: "extends".to_string(),
+            span: None,
+        }]);
+    };
+
+    let Some(dir) = dir else {
+        return Err(vec![ConfigError::ExtendsFile {
+            file: name.to_string(),
+            path: "extends".to_string(),
+            span: None,
+        }]);
+    };
+
+    let path = dir.join(name);
+
+    let wrap = |error: ConfigError| ConfigError::Extends {
+        file: name.to_string(),
+        source: Box::new(error),
+        path: "extends".to_string(),
+        span: None,
+    };
+
+    let canonical = fs::canonicalize(&path).map_err(|err| vec![wrap(io_error(&path, err))])?;
+
+    if chain.contains(&canonical) {
+        return Err(vec![ConfigError::ExtendsCycle {
+            file: name.to_string(),
+            path: "extends".to_string(),
+            span: None,
+        }]);
+    }
+
+    let source = fs::read_to_string(&path).map_err(|err| vec![wrap(io_error(&path, err))])?;
+    let file: ConfigFile = parse(&source, format).map_err(|err| vec![wrap(err)])?;
+
+    chain.push(canonical);
+    let config = file.resolve_in(path.parent(), chain);
+    chain.pop();
+
+    config.map_err(|errors| errors.into_iter().map(wrap).collect())
+}
@@ -0,0 +1,684 @@
+pub mod errors;
+pub mod formats;
+pub mod merge;
+pub mod profiles;
+pub mod pseudos;
+pub mod reference;
+pub mod regions;
+pub mod spans;
+pub mod vectors;
+
+mod tests;
+mod validate;
+
+use super::parser::ast::{Argument, ArgumentKind};
+use errors::ConfigError;
+use merge::Overlay;
+use pseudos::PseudoInstruction;
+use regions::Region;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use vectors::Vector;
+
+/// The main configuration type for the assembler. This is read from a `ConfigFile`, so it can extend a built-in
+/// profile, but it's always written out in full.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(try_from = "ConfigFile")]
+pub struct AssemblerConfig {
+    /// The opcodes to be used by the assembler.
+    pub opcodes: Vec<Opcode>,
+
+    /// The registers that can be given as arguments to opcodes. Labels can't have the same name as a register.
+    #[serde(default)]
+    pub registers: Vec<Register>,
+
+    /// The width of addresses in bits, which is 8, 12, 16, or 24. Addresses take up as many bytes as it takes to hold
+    /// this many bits, and nothing can be placed past the end of the address space.
+    #[serde(default = "default_address_bits")]
+    pub address_bits: u8,
+
+    /// The size of a data word in bytes, from 1 to 4. This is how much each argument to `.word` takes up.
+    #[serde(default = "default_word_size")]
+    pub word_size: u8,
+
+    /// The order that the bytes of addresses and other multi-byte values are encoded in.
+    #[serde(default)]
+    pub endianness: Endianness,
+
+    /// The memory map. If there are any regions, code and data can only be placed in `Rom` regions, and can't run
+    /// past the end of the region they start in.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub regions: Vec<Region>,
+
+    /// The pseudo-instructions, which expand to sequences of the opcodes before anything is laid out.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pseudo_instructions: Vec<PseudoInstruction>,
+
+    /// The vectors, which are filled in with the addresses given to `.entry` and `.vector`. Every one has to be set,
+    /// and none of them can share addresses with the program.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub vectors: Vec<Vector>,
+}
+
+impl Default for AssemblerConfig {
+    fn default() -> Self {
+        Self {
+            opcodes: vec![],
+            registers: vec![],
+            address_bits: default_address_bits(),
+            word_size: default_word_size(),
+            endianness: Endianness::default(),
+            regions: vec![],
+            pseudo_instructions: vec![],
+            vectors: vec![],
+        }
+    }
+}
+
+/// The default width of addresses in bits.
+fn default_address_bits() -> u8 {
+    16
+}
+
+/// The default size of a data word in bytes.
+fn default_word_size() -> u8 {
+    1
+}
+
+/// The order that the bytes of multi-byte values are encoded in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub enum Endianness {
+    /// The least significant byte comes first.
+    #[default]
+    Little,
+
+    /// The most significant byte comes first.
+    Big,
+}
+
+/// A configuration as it's written in a file, before it's combined with what it extends and its overlays (see
+/// `ConfigFile::resolve`).
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct ConfigFile {
+    /// The name of a built-in profile (e.g. `nand7400_v2`) or the path to another configuration file to start from.
+    /// Files are found relative to this one, so they can only be extended when this is loaded from a file.
+    /// The opcodes here replace all of its opcodes with the same mnemonic, and the rest are added on. Likewise for
+    /// registers, regions, pseudo-instructions, and vectors with the same name.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extends: Option<String>,
+
+    /// The opcodes to be used by the assembler.
+    #[serde(default)]
+    pub opcodes: Vec<Opcode>,
+
+    /// The registers that can be given as arguments to opcodes. Labels can't have the same name as a register.
+    #[serde(default)]
+    pub registers: Vec<Register>,
+
+    /// Changes to make to the opcodes once everything else is combined, applied in order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub overlays: Vec<Overlay>,
+
+    /// The width of addresses in bits (see `AssemblerConfig::address_bits`). This is inherited if it isn't set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub address_bits: Option<u8>,
+
+    /// The size of a data word in bytes (see `AssemblerConfig::word_size`). This is inherited if it isn't set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub word_size: Option<u8>,
+
+    /// The byte order of multi-byte values (see `AssemblerConfig::endianness`). This is inherited if it isn't set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub endianness: Option<Endianness>,
+
+    /// The memory map (see `AssemblerConfig::regions`). Regions here replace any inherited ones with the same name.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub regions: Vec<Region>,
+
+    /// The pseudo-instructions (see `AssemblerConfig::pseudo_instructions`). These replace any inherited ones with
+    /// the same mnemonic.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pseudo_instructions: Vec<PseudoInstruction>,
+
+    /// The vectors (see `AssemblerConfig::vectors`). Vectors here replace any inherited ones with the same name.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub vectors: Vec<Vector>,
+}
+
+impl TryFrom<ConfigFile> for AssemblerConfig {
+    type Error = ConfigError;
+
+    fn try_from(file: ConfigFile) -> Result<Self, Self::Error> {
+        file.resolve().map_err(|mut errors| errors.remove(0))
+    }
+}
+
+/// Public API for the assembler configuration.
+impl AssemblerConfig {
+    /// Gets an opcode by its name or one of its aliases. If the mnemonic is overloaded, this is the first opcode with
+    /// that name.
+    pub fn get_opcode(&self, mnemonic: &str) -> Option<&Opcode> {
+        self.opcodes.iter().find(|opcode| opcode.is_named(mnemonic))
+    }
+
+    /// Gets every opcode with the given name or alias. There can be more than one if the mnemonic is overloaded, in
+    /// which case the assembler picks the one whose arguments match the ones given.
+    pub fn get_opcodes(&self, mnemonic: &str) -> Vec<&Opcode> {
+        self.opcodes
+            .iter()
+            .filter(|opcode| opcode.is_named(mnemonic))
+            .collect()
+    }
+
+    /// Describes every opcode with the given name or alias in Markdown, e.g. for hover documentation in an editor.
+    /// This is `None` if there's no such opcode.
+    pub fn describe(&self, mnemonic: &str) -> Option<String> {
+        let opcodes = self.get_opcodes(mnemonic);
+
+        if opcodes.is_empty() {
+            return None;
+        }
+
+        let descriptions = opcodes
+            .iter()
+            .map(|opcode| {
+                let mut lines = vec![format!("`{}`", opcode.signature())];
+
+                if opcode.deprecated {
+                    lines.push("**Deprecated.**".to_string());
+                }
+
+                if let Some(description) = &opcode.description {
+                    lines.push(description.clone());
+                }
+
+                for (arg, operand) in opcode.args.iter().zip(&opcode.operands) {
+                    lines.push(format!("- `{:?}`: {}", arg, operand));
+                }
+
+                for (i, constraint) in opcode.constraints.iter().enumerate() {
+                    if constraint == &ArgConstraint::default() {
+                        continue;
+                    }
+
+                    lines.push(format!(
+                        "Argument {} must be {}.",
+                        i + 1,
+                        constraint.describe()
+                    ));
+                }
+
+                if let Some(cycles) = opcode.cycles {
+                    lines.push(format!("Cycles: {}", cycles));
+                }
+
+                if !opcode.flags.is_empty() {
+                    lines.push(format!("Flags affected: {}", opcode.flags.join(", ")));
+                }
+
+                if !opcode.aliases.is_empty() {
+                    lines.push(format!("Aliases: {}", opcode.aliases.join(", ")));
+                }
+
+                lines.join("\n\n")
+            })
+            .collect::<Vec<_>>();
+
+        Some(descriptions.join("\n\n---\n\n"))
+    }
+
+    /// Gets the number of bytes that an address takes up.
+    pub fn address_bytes(&self) -> u16 {
+        (self.address_bits as u16).div_ceil(8)
+    }
+
+    /// Gets the highest address there is.
+    pub fn max_address(&self) -> u32 {
+        ((1_u64 << self.address_bits.min(32)) - 1) as u32
+    }
+
+    /// Encodes the lowest `size` bytes (up to 4) of a value in the configured byte order.
+    pub fn encode_bytes(&self, value: u32, size: u16) -> Vec<u8> {
+        let mut bytes = value.to_le_bytes()[..(size as usize).min(4)].to_vec();
+
+        if self.endianness == Endianness::Big {
+            bytes.reverse();
+        }
+
+        bytes
+    }
+
+    /// Gets a register by its name.
+    pub fn get_register(&self, name: &str) -> Option<&Register> {
+        self.registers.iter().find(|register| register.name == name)
+    }
+
+    /// Gets a fingerprint of the configuration, which changes whenever something that affects how programs assemble
+    /// changes. Documentation (descriptions, cycles, flags, and so on) and the order of anything whose order doesn't
+    /// matter are left out. Unlike `Hash`, this is stable across runs and platforms, so it can be saved and checked
+    /// later (e.g. in emulator snapshots). See `FINGERPRINT_VERSION`.
+    pub fn fingerprint(&self) -> u64 {
+        let json = serde_json::to_vec(&Fingerprint::new(self))
+            .expect("Fingerprints can always be serialized!");
+
+        // 64-bit FNV-1a.
+        json.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+            (hash ^ *byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+        })
+    }
+}
+
+/// The version of what goes into `AssemblerConfig::fingerprint`. This is bumped whenever that changes, so that
+/// fingerprints made by different versions never match by accident.
+pub const FINGERPRINT_VERSION: u32 = 1;
+
+/// The parts of a configuration that are hashed by `AssemblerConfig::fingerprint`, in a canonical order.
+#[derive(Serialize)]
+struct Fingerprint<'a> {
+    version: u32,
+    opcodes: Vec<OpcodeFingerprint<'a>>,
+    registers: Vec<&'a Register>,
+    address_bits: u8,
+    word_size: u8,
+    endianness: Endianness,
+    regions: Vec<&'a Region>,
+    pseudo_instructions: Vec<&'a PseudoInstruction>,
+    vectors: Vec<&'a Vector>,
+}
+
+/// The parts of an opcode that are hashed by `AssemblerConfig::fingerprint`.
+#[derive(Serialize)]
+struct OpcodeFingerprint<'a> {
+    mnemonic: &'a str,
+    binary: u8,
+    args: &'a [OpcodeArg],
+    aliases: Vec<&'a str>,
+    constraints: &'a [ArgConstraint],
+}
+
+impl<'a> Fingerprint<'a> {
+    /// Projects a configuration onto the parts that are fingerprinted.
+    fn new(config: &'a AssemblerConfig) -> Self {
+        // Overloads of the same mnemonic are tried in order, so the sort has to be stable.
+        let mut opcodes = config
+            .opcodes
+            .iter()
+            .map(|opcode| {
+                let mut aliases = opcode
+                    .aliases
+                    .iter()
+                    .map(String::as_str)
+                    .collect::<Vec<_>>();
+                aliases.sort_unstable();
+
+                OpcodeFingerprint {
+                    mnemonic: &opcode.mnemonic,
+                    binary: opcode.binary,
+                    args: &opcode.args,
+                    aliases,
+                    constraints: &opcode.constraints,
+                }
+            })
+            .collect::<Vec<_>>();
+        opcodes.sort_by_key(|opcode| opcode.mnemonic);
+
+        let mut registers = config.registers.iter().collect::<Vec<_>>();
+        registers.sort_by_key(|register| &register.name);
+
+        let mut regions = config.regions.iter().collect::<Vec<_>>();
+        regions.sort_by_key(|region| &region.name);
+
+        let mut pseudo_instructions = config.pseudo_instructions.iter().collect::<Vec<_>>();
+        pseudo_instructions.sort_by_key(|pseudo| &pseudo.mnemonic);
+
+        let mut vectors = config.vectors.iter().collect::<Vec<_>>();
+        vectors.sort_by_key(|vector| &vector.name);
+
+        Self {
+            version: FINGERPRINT_VERSION,
+            opcodes,
+            registers,
+            address_bits: config.address_bits,
+            word_size: config.word_size,
+            endianness: config.endianness,
+            regions,
+            pseudo_instructions,
+            vectors,
+        }
+    }
+}
+
+/// An opcode to be parsed by the assembler.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct Opcode {
+    /// The name of the opcode.
+    pub mnemonic: String,
+
+    /// The binary representation of the opcode, as a byte.
+    pub binary: u8,
+
+    /// The list of arguments for the opcode. If this list is empty, then the opcode has no arguments.
+    /// Note that for the `Indirect` and `Immediate` kinds, this does not map to the literal count of arguments
+    /// (i.e. `len(args)`), but rather the length of the arguments in bytes. For example, labels are 1 argument
+    /// but map to as many bytes as an address (2 by default). The other kinds are always 1 argument each.
+    pub args: Vec<OpcodeArg>,
+
+    /// A description of what the opcode does, for documentation.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+
+    /// A description of each argument, in the same order as `args`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub operands: Vec<String>,
+
+    /// The number of clock cycles the opcode takes to run.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cycles: Option<u32>,
+
+    /// The names of the flags that the opcode changes (e.g. `Z` or `C`).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub flags: Vec<String>,
+
+    /// Whether the opcode shouldn't be used anymore. It still assembles, but with a warning.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub deprecated: bool,
+
+    /// Other mnemonics that the opcode can be written with.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
+
+    /// Constraints on the value of each argument, in the same order as `args`. These are checked once labels are
+    /// resolved, on top of the argument having to fit in its kind.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub constraints: Vec<ArgConstraint>,
+}
+
+/// Whether a flag isn't set, so that it can be left out when serializing.
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+impl Opcode {
+    /// Gets the size of the opcode in bytes, including its arguments, when addresses are `address_bytes` long. Fields
+    /// that go past the end of the other arguments make the opcode longer, with the extra bytes starting as 0.
+    pub fn size(&self, address_bytes: u16) -> u16 {
+        let args_size = 1 + self
+            .args
+            .iter()
+            .map(|arg| arg.size(address_bytes))
+            .sum::<u16>();
+
+        self.args
+            .iter()
+            .filter_map(|arg| match arg {
+                OpcodeArg::Field { byte, .. } => Some(*byte as u16 + 1),
+                _ => None,
+            })
+            .fold(args_size, u16::max)
+    }
+
+    /// Whether the opcode can be written with the given mnemonic, either its own or one of its aliases.
+    pub fn is_named(&self, mnemonic: &str) -> bool {
+        self.mnemonic == mnemonic || self.aliases.iter().any(|alias| alias == mnemonic)
+    }
+
+    /// Gets the signature of the opcode, i.e. the mnemonic followed by the argument kinds (e.g. `lda Immediate`).
+    pub fn signature(&self) -> String {
+        self.args
+            .iter()
+            .fold(self.mnemonic.clone(), |signature, arg| {
+                format!("{} {:?}", signature, arg)
+            })
+    }
+}
+
+/// A register that can be given as an argument to opcodes.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct Register {
+    /// The name of the register (e.g. `A`).
+    pub name: String,
+
+    /// The binary representation of the register, which is what gets encoded into the opcode.
+    pub binary: u8,
+
+    /// The class of the register (e.g. `general` or `index`). Opcodes take registers of a certain class, so that
+    /// e.g. only index registers can be used for indexing.
+    pub class: String,
+}
+
+/// Constraints on the value of an argument. Every one that's set has to hold.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct ArgConstraint {
+    /// The smallest value allowed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min: Option<u32>,
+
+    /// The largest value allowed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max: Option<u32>,
+
+    /// What the value has to be a multiple of, e.g. 2 for even addresses.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub align: Option<u32>,
+
+    /// The only values allowed. Any value is allowed if this is empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed: Vec<u32>,
+}
+
+impl ArgConstraint {
+    /// Checks a value against the constraint, returning a description of the first part that it breaks, e.g.
+    /// `at most 7`.
+    pub fn check(&self, value: u32) -> Result<(), String> {
+        let broken = [
+            self.min.filter(|min| value < *min).map(describe_min),
+            self.max.filter(|max| value > *max).map(describe_max),
+            self.align
+                .filter(|align| *align != 0 && !value.is_multiple_of(*align))
+                .map(describe_align),
+            (!self.allowed.is_empty() && !self.allowed.contains(&value))
+                .then(|| describe_allowed(&self.allowed)),
+        ];
+
+        match broken.into_iter().flatten().next() {
+            Some(description) => Err(description),
+            None => Ok(()),
+        }
+    }
+
+    /// Describes the constraint, e.g. `at least 1, at most 7`. This is empty if there's nothing to it.
+    pub fn describe(&self) -> String {
+        let parts = [
+            self.min.map(describe_min),
+            self.max.map(describe_max),
+            self.align.map(describe_align),
+            (!self.allowed.is_empty()).then(|| describe_allowed(&self.allowed)),
+        ];
+
+        parts.into_iter().flatten().collect::<Vec<_>>().join(", ")
+    }
+}
+
+/// Describes a minimum value.
+fn describe_min(min: u32) -> String {
+    format!("at least {}", min)
+}
+
+/// Describes a maximum value.
+fn describe_max(max: u32) -> String {
+    format!("at most {}", max)
+}
+
+/// Describes an alignment.
+fn describe_align(align: u32) -> String {
+    format!("a multiple of {}", align)
+}
+
+/// Describes a set of allowed values.
+fn describe_allowed(allowed: &[u32]) -> String {
+    let allowed = allowed
+        .iter()
+        .map(|value| value.to_string())
+        .collect::<Vec<_>>();
+
+    format!("one of {}", allowed.join(", "))
+}
+
+/// The argument kind for an opcode.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub enum OpcodeArg {
+    /// A indirect number. This is 1 byte, but a label given as one fills as many of these as an address takes up.
+    Indirect,
+
+    /// A direct/immediate number. This is 1 byte, but a label given as one fills as many of these as an address takes
+    /// up.
+    Immediate,
+
+    /// An 8-bit immediate number (e.g. `#5`).
+    Imm8,
+
+    /// A 16-bit immediate number or a label, which is used as a value rather than an address.
+    Imm16,
+
+    /// An address, either as an indirect number (e.g. `0x1234`) or a label. This is as wide as the configured address
+    /// width, which is 16 bits by default. It used to be called `Addr16`, which is still accepted in configurations.
+    #[serde(alias = "Addr16")]
+    Addr,
+
+    /// An address that is encoded as a signed 8-bit offset from the end of the opcode, for relative jumps.
+    Rel8,
+
+    /// A register of the given class. This is 1 byte, which is the register's binary representation.
+    RegisterClass {
+        /// The class of registers that can be given.
+        class: String,
+    },
+
+    /// A number or register that is packed into a bit field, rather than taking up bytes of its own. The field is
+    /// OR-ed into the opcode (if `byte` is 0) or into the `byte`th byte after it.
+    Field {
+        /// The byte the field is in, where 0 is the opcode itself.
+        byte: u8,
+
+        /// The offset of the field's lowest bit in the byte.
+        offset: u8,
+
+        /// The width of the field in bits.
+        width: u8,
+    },
+
+    /// An indirect address, written as `(addr)` or `[addr]`.
+    AddrIndirect,
+
+    /// An address indexed by a register of the given class, written as `addr,X`.
+    AddrIndexed {
+        /// The class of registers the address can be indexed by.
+        class: String,
+    },
+
+    /// An address that is indexed, and then used indirectly, written as `(addr,X)`.
+    AddrIndexedIndirect {
+        /// The class of registers the address can be indexed by.
+        class: String,
+    },
+
+    /// An address that is used indirectly, and then indexed, written as `(addr),X`.
+    AddrIndirectIndexed {
+        /// The class of registers the address can be indexed by.
+        class: String,
+    },
+}
+
+impl OpcodeArg {
+    /// Gets the size of the argument in bytes, when addresses are `address_bytes` long.
+    pub fn size(&self, address_bytes: u16) -> u16 {
+        match self {
+            Self::Indirect
+            | Self::Immediate
+            | Self::Imm8
+            | Self::Rel8
+            | Self::RegisterClass { .. } => 1,
+            Self::Imm16 => 2,
+            Self::Addr
+            | Self::AddrIndirect
+            | Self::AddrIndexed { .. }
+            | Self::AddrIndexedIndirect { .. }
+            | Self::AddrIndirectIndexed { .. } => address_bytes,
+            Self::Field { .. } => 0,
+        }
+    }
+
+    /// Whether the argument is one of the 1-byte kinds that a label fills several of.
+    pub fn is_single_byte_slot(&self) -> bool {
+        matches!(self, Self::Indirect | Self::Immediate)
+    }
+
+    /// Whether an argument can be given in place of this kind.
+    pub fn accepts<T>(&self, arg: &Argument<T>) -> bool {
+        if let (Self::RegisterClass { class }, ArgumentKind::Register(register)) = (self, &arg.kind)
+        {
+            return register.class == *class;
+        }
+
+        // Addressing modes have to be written the same way, and have an address at the bottom of them.
+        if matches!(
+            self,
+            Self::AddrIndirect
+                | Self::AddrIndexed { .. }
+                | Self::AddrIndexedIndirect { .. }
+                | Self::AddrIndirectIndexed { .. }
+        ) {
+            return OpcodeArg::from(arg) == *self
+                && matches!(
+                    arg.base().kind,
+                    ArgumentKind::IndirectNumber(_) | ArgumentKind::Label(_)
+                );
+        }
+
+        matches!(
+            (self, &arg.kind),
+            (Self::Indirect, ArgumentKind::IndirectNumber(_))
+                | (
+                    Self::Immediate | Self::Imm16,
+                    ArgumentKind::ImmediateNumber(_) | ArgumentKind::Label(_)
+                )
+                | (Self::Imm8, ArgumentKind::ImmediateNumber(_))
+                | (
+                    Self::Addr | Self::Rel8,
+                    ArgumentKind::IndirectNumber(_) | ArgumentKind::Label(_)
+                )
+                | (
+                    Self::Field { .. },
+                    ArgumentKind::ImmediateNumber(_)
+                        | ArgumentKind::IndirectNumber(_)
+                        | ArgumentKind::Register(_)
+                )
+        )
+    }
+}
+
+impl<T> From<&Argument<T>> for OpcodeArg {
+    fn from(arg: &Argument<T>) -> Self {
+        match &arg.kind {
+            ArgumentKind::IndirectNumber(_) => Self::Indirect,
+            ArgumentKind::ImmediateNumber(_) | ArgumentKind::Label(_) => Self::Immediate,
+            ArgumentKind::Register(register) => Self::RegisterClass {
+                class: register.class.clone(),
+            },
+            ArgumentKind::Indirect(inner) => match &inner.kind {
+                ArgumentKind::Indexed { index, .. } => Self::AddrIndexedIndirect {
+                    class: index.class.clone(),
+                },
+                _ => Self::AddrIndirect,
+            },
+            ArgumentKind::Indexed { base, index } => match &base.kind {
+                ArgumentKind::Indirect(_) => Self::AddrIndirectIndexed {
+                    class: index.class.clone(),
+                },
+                _ => Self::AddrIndexed {
+                    class: index.class.clone(),
+                },
+            },
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use super::{AssemblerConfig, Endianness, Opcode, OpcodeArg};
+
+/// The names of the built-in profiles, oldest first. `nand7400` is also accepted as a name for the latest one.
+pub const PROFILE_NAMES: &[&str] = &["nand7400_v1", "nand7400_v2"];
+
+/// Built-in configurations for the nand7400 computer, so that the opcode table doesn't have to be copied around.
+impl AssemblerConfig {
+    /// Gets the configuration for the latest revision of the nand7400 computer.
+    pub fn nand7400() -> Self {
+        Self::nand7400_v2()
+    }
+
+    /// Gets the configuration for the 1st revision of the nand7400 computer. This is the original opcode table, where
+    /// every argument is a single `Immediate` or `Indirect` byte.
+    pub fn nand7400_v1() -> Self {
+        Self {
+            opcodes: vec![
+                opcode("nop", 0x00, vec![]),
+                opcode("lda", 0x01, vec![OpcodeArg::Immediate]),
+                opcode("ldb", 0x02, vec![OpcodeArg::Indirect]),
+                opcode(
+                    "add",
+                    0x03,
+                    vec![
+                        OpcodeArg::Immediate,
+                        OpcodeArg::Immediate,
+                        OpcodeArg::Immediate,
+                    ],
+                ),
+                opcode(
+                    "jmp",
+                    0x04,
+                    vec![OpcodeArg::Immediate, OpcodeArg::Immediate],
+                ),
+                opcode("hlt", 0xFF, vec![]),
+            ],
+            registers: vec![],
+            address_bits: 16,
+            word_size: 1,
+            endianness: Endianness::Little,
+            regions: vec![],
+            pseudo_instructions: vec![],
+            vectors: vec![],
+        }
+    }
+
+    /// Gets the configuration for the 2nd revision of the nand7400 computer. This has the same encoding as the 1st
+    /// revision, but the arguments are sized, so e.g. `jmp` takes a 16-bit address rather than two separate bytes.
+    pub fn nand7400_v2() -> Self {
+        Self {
+            opcodes: vec![
+                opcode("nop", 0x00, vec![]),
+                opcode("lda", 0x01, vec![OpcodeArg::Imm8]),
+                opcode("ldb", 0x02, vec![OpcodeArg::Indirect]),
+                opcode(
+                    "add",
+                    0x03,
+                    vec![OpcodeArg::Imm8, OpcodeArg::Imm8, OpcodeArg::Imm8],
+                ),
+                opcode("jmp", 0x04, vec![OpcodeArg::Addr]),
+                opcode("hlt", 0xFF, vec![]),
+            ],
+            registers: vec![],
+            address_bits: 16,
+            word_size: 1,
+            endianness: Endianness::Little,
+            regions: vec![],
+            pseudo_instructions: vec![],
+            vectors: vec![],
+        }
+    }
+
+    /// Gets a built-in profile by name (see `PROFILE_NAMES`).
+    pub fn profile(name: &str) -> Option<Self> {
+        match name {
+            "nand7400" => Some(Self::nand7400()),
+            "nand7400_v1" => Some(Self::nand7400_v1()),
+            "nand7400_v2" => Some(Self::nand7400_v2()),
+            _ => None,
+        }
+    }
+}
+
+/// Shorthand for making an opcode.
+fn opcode(mnemonic: &str, binary: u8, args: Vec<OpcodeArg>) -> Opcode {
+    Opcode {
+        mnemonic: mnemonic.to_string(),
+        binary,
+        args,
+        ..Default::default()
+    }
+}
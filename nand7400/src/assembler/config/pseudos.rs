@@ -0,0 +1,30 @@
+use super::AssemblerConfig;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// A pseudo-instruction, which is a template over real opcodes that's expanded wherever it's used. For example,
+/// `inc` with the parameter `reg` and the body `["add reg #1"]` turns `inc A` into `add A #1`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct PseudoInstruction {
+    /// The name of the pseudo-instruction.
+    pub mnemonic: String,
+
+    /// The names of the parameters. Wherever one of these is used as an argument in the body, the argument given in
+    /// its place is substituted in, e.g. `#5`, `(addr),X`, or a register.
+    #[serde(default)]
+    pub params: Vec<String>,
+
+    /// The lines of assembly that the pseudo-instruction expands to. These can use opcodes and keywords, but can't
+    /// define labels or use other pseudo-instructions.
+    pub body: Vec<String>,
+}
+
+/// Public API for pseudo-instructions.
+impl AssemblerConfig {
+    /// Gets a pseudo-instruction by its name.
+    pub fn get_pseudo(&self, mnemonic: &str) -> Option<&PseudoInstruction> {
+        self.pseudo_instructions
+            .iter()
+            .find(|pseudo| pseudo.mnemonic == mnemonic)
+    }
+}
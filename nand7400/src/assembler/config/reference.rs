@@ -0,0 +1,446 @@
+use super::{validate::fixed_bits, AssemblerConfig, Endianness, Opcode, OpcodeArg};
+
+/// A piece of a reference document, which can be rendered as either Markdown or HTML. Text can have `code` and
+/// **bold** spans in it, written the Markdown way.
+enum Block {
+    /// A heading of the given level, from 1 to 3.
+    Heading(u8, String),
+
+    /// A paragraph of text.
+    Paragraph(String),
+
+    /// A table with a header row.
+    Table {
+        /// The header of each column.
+        header: Vec<String>,
+
+        /// The cells of each row.
+        rows: Vec<Vec<String>>,
+    },
+}
+
+/// Generating instruction set references.
+impl AssemblerConfig {
+    /// Renders an instruction set reference for the configuration in Markdown. This has an opcode matrix, the unused
+    /// opcodes, and the encoding and operands of every instruction, along with any descriptions from the
+    /// configuration.
+    pub fn reference_markdown(&self) -> String {
+        self.reference()
+            .iter()
+            .map(|block| match block {
+                Block::Heading(level, text) => {
+                    format!("{} {}", "#".repeat(*level as usize), text)
+                }
+                Block::Paragraph(text) => text.clone(),
+                Block::Table { header, rows } => {
+                    let row = |cells: &[String]| {
+                        let cells = cells
+                            .iter()
+                            .map(|cell| cell.replace('|', "\\|"))
+                            .collect::<Vec<_>>();
+
+                        format!("| {} |", cells.join(" | "))
+                    };
+
+                    let mut lines = vec![row(header), row(&vec!["---".to_string(); header.len()])];
+                    lines.extend(rows.iter().map(|cells| row(cells)));
+                    lines.join("\n")
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n\n")
+            + "\n"
+    }
+
+    /// Renders the same reference as `reference_markdown`, but as a standalone HTML page.
+    pub fn reference_html(&self) -> String {
+        let body = self
+            .reference()
+            .iter()
+            .map(|block| match block {
+                Block::Heading(level, text) => {
+                    format!("<h{0}>{1}</h{0}>", level, inline_html(text))
+                }
+                Block::Paragraph(text) => format!("<p>{}</p>", inline_html(text)),
+                Block::Table { header, rows } => {
+                    let row = |tag: &str, cells: &[String]| {
+                        let cells = cells
+                            .iter()
+                            .map(|cell| format!("<{0}>{1}</{0}>", tag, inline_html(cell)))
+                            .collect::<String>();
+
+                        format!("<tr>{}</tr>", cells)
+                    };
+
+                    let rows = rows
+                        .iter()
+                        .map(|cells| row("td", cells))
+                        .collect::<Vec<_>>()
+                        .join("\n");
+
+                    format!(
+                        "<table>\n<thead>{}</thead>\n<tbody>\n{}\n</tbody>\n</table>",
+                        row("th", header),
+                        rows
+                    )
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        format!(
+            "<!DOCTYPE html>\n\
+             <html>\n\
+             <head>\n\
+             <meta charset=\"utf-8\">\n\
+             <title>Instruction set reference</title>\n\
+             <style>\n\
+             body {{ font-family: sans-serif; max-width: 60em; margin: auto; }}\n\
+             table {{ border-collapse: collapse; margin-bottom: 1em; }}\n\
+             th, td {{ border: 1px solid #999; padding: 0.2em 0.5em; text-align: center; }}\n\
+             </style>\n\
+             </head>\n\
+             <body>\n\
+             {}\n\
+             </body>\n\
+             </html>\n",
+            body
+        )
+    }
+
+    /// Gets the opcode bytes that no opcode uses, once the bit fields of the opcodes are taken into account. These
+    /// are free for new instructions.
+    pub fn unused_binaries(&self) -> Vec<u8> {
+        (0..=u8::MAX)
+            .filter(|binary| self.opcodes_at(*binary).is_empty())
+            .collect()
+    }
+
+    /// Gets the opcodes whose first byte can be `binary`, once their bit fields are filled in.
+    fn opcodes_at(&self, binary: u8) -> Vec<&Opcode> {
+        self.opcodes
+            .iter()
+            .filter(|opcode| {
+                let fixed = fixed_bits(opcode);
+
+                binary & fixed == opcode.binary & fixed
+            })
+            .collect()
+    }
+
+    /// Builds up the blocks of the reference.
+    fn reference(&self) -> Vec<Block> {
+        let endianness = match self.endianness {
+            Endianness::Little => "little-endian",
+            Endianness::Big => "big-endian",
+        };
+        let mut blocks = vec![
+            Block::Heading(1, "Instruction set reference".to_string()),
+            Block::Paragraph(format!(
+                "Addresses are {} bits wide. Multi-byte values are {}. Words are {} byte(s).",
+                self.address_bits, endianness, self.word_size
+            )),
+            Block::Heading(2, "Opcode matrix".to_string()),
+            Block::Paragraph(
+                "Rows are the high nibble of the opcode byte, and columns are the low nibble."
+                    .to_string(),
+            ),
+            self.matrix(),
+            Block::Heading(2, "Unused opcodes".to_string()),
+            Block::Paragraph(self.unused_ranges()),
+        ];
+
+        if !self.registers.is_empty() {
+            blocks.push(Block::Heading(2, "Registers".to_string()));
+            blocks.push(Block::Table {
+                header: vec!["Name".into(), "Binary".into(), "Class".into()],
+                rows: self
+                    .registers
+                    .iter()
+                    .map(|register| {
+                        vec![
+                            format!("`{}`", register.name),
+                            format!("`{:#04X}`", register.binary),
+                            register.class.clone(),
+                        ]
+                    })
+                    .collect(),
+            });
+        }
+
+        blocks.push(Block::Heading(2, "Instructions".to_string()));
+
+        for opcode in &self.opcodes {
+            blocks.extend(self.describe_blocks(opcode));
+        }
+
+        if !self.pseudo_instructions.is_empty() {
+            blocks.push(Block::Heading(2, "Pseudo-instructions".to_string()));
+            blocks.push(Block::Table {
+                header: vec!["Usage".into(), "Expands to".into()],
+                rows: self
+                    .pseudo_instructions
+                    .iter()
+                    .map(|pseudo| {
+                        let usage = pseudo
+                            .params
+                            .iter()
+                            .fold(pseudo.mnemonic.clone(), |usage, param| {
+                                format!("{} {}", usage, param)
+                            });
+                        let body = pseudo
+                            .body
+                            .iter()
+                            .map(|line| format!("`{}`", line))
+                            .collect::<Vec<_>>()
+                            .join("; ");
+
+                        vec![format!("`{}`", usage), body]
+                    })
+                    .collect(),
+            });
+        }
+
+        blocks
+    }
+
+    /// Builds the 16×16 grid of which opcode each byte is.
+    fn matrix(&self) -> Block {
+        let mut header = vec![String::new()];
+        header.extend((0..16).map(|low| format!("x{:X}", low)));
+
+        let rows = (0..16_u8)
+            .map(|high| {
+                let mut cells = vec![format!("{:X}x", high)];
+
+                cells.extend((0..16_u8).map(|low| {
+                    self.opcodes_at(high << 4 | low)
+                        .iter()
+                        .map(|opcode| opcode.mnemonic.clone())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                }));
+
+                cells
+            })
+            .collect();
+
+        Block::Table { header, rows }
+    }
+
+    /// Lists the unused opcode bytes, with runs of them collapsed into ranges.
+    fn unused_ranges(&self) -> String {
+        let mut ranges: Vec<(u8, u8)> = vec![];
+
+        for binary in self.unused_binaries() {
+            match ranges.last_mut() {
+                Some((_, end)) if *end as u16 + 1 == binary as u16 => *end = binary,
+                _ => ranges.push((binary, binary)),
+            }
+        }
+
+        if ranges.is_empty() {
+            return "Every opcode is used.".to_string();
+        }
+
+        ranges
+            .iter()
+            .map(|(start, end)| {
+                if start == end {
+                    format!("`{:#04X}`", start)
+                } else {
+                    format!("`{:#04X}`–`{:#04X}`", start, end)
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// Builds the section for a single opcode.
+    fn describe_blocks(&self, opcode: &Opcode) -> Vec<Block> {
+        let mut blocks = vec![Block::Heading(3, format!("`{}`", opcode.signature()))];
+
+        if opcode.deprecated {
+            blocks.push(Block::Paragraph("**Deprecated.**".to_string()));
+        }
+
+        if let Some(description) = &opcode.description {
+            blocks.push(Block::Paragraph(description.clone()));
+        }
+
+        let encoding = self.encoding(opcode);
+        blocks.push(Block::Table {
+            header: (0..encoding.len())
+                .map(|byte| format!("Byte {}", byte))
+                .collect(),
+            rows: vec![encoding],
+        });
+
+        if !opcode.args.is_empty() {
+            let address_bytes = self.address_bytes();
+
+            blocks.push(Block::Table {
+                header: vec![
+                    "Operand".into(),
+                    "Kind".into(),
+                    "Size".into(),
+                    "Description".into(),
+                ],
+                rows: opcode
+                    .args
+                    .iter()
+                    .enumerate()
+                    .map(|(i, arg)| {
+                        let size = match arg {
+                            OpcodeArg::Field { width, .. } => format!("{} bit(s)", width),
+                            _ => format!("{} byte(s)", arg.size(address_bytes)),
+                        };
+
+                        vec![
+                            format!("${}", i + 1),
+                            format!("`{:?}`", arg),
+                            size,
+                            opcode.operands.get(i).cloned().unwrap_or_default(),
+                        ]
+                    })
+                    .collect(),
+            });
+        }
+
+        let mut details = vec![];
+
+        if let Some(cycles) = opcode.cycles {
+            details.push(format!("Cycles: {}.", cycles));
+        }
+
+        if !opcode.flags.is_empty() {
+            details.push(format!("Flags affected: {}.", opcode.flags.join(", ")));
+        }
+
+        if !opcode.aliases.is_empty() {
+            let aliases = opcode
+                .aliases
+                .iter()
+                .map(|alias| format!("`{}`", alias))
+                .collect::<Vec<_>>();
+
+            details.push(format!("Aliases: {}.", aliases.join(", ")));
+        }
+
+        for (i, constraint) in opcode.constraints.iter().enumerate() {
+            let constraint = constraint.describe();
+
+            if !constraint.is_empty() {
+                details.push(format!("${} must be {}.", i + 1, constraint));
+            }
+        }
+
+        if !details.is_empty() {
+            blocks.push(Block::Paragraph(details.join(" ")));
+        }
+
+        blocks
+    }
+
+    /// Works out what goes in each byte of an opcode. Operands are numbered from `$1`, bit fields are shown as the
+    /// bits they take up, and the bytes of multi-byte operands are shown as the bits of the value they hold.
+    fn encoding(&self, opcode: &Opcode) -> Vec<String> {
+        let address_bytes = self.address_bytes();
+        let mut parts = vec![vec![]; opcode.size(address_bytes) as usize];
+        let mut bits = vec![None; parts.len()]; // The bit pattern of each byte with fixed bits or fields in it.
+        let mut next = 1;
+
+        bits[0] = Some(
+            (0..8)
+                .rev()
+                .map(|bit| char::from(b'0' + (opcode.binary >> bit & 1)))
+                .collect::<Vec<_>>(),
+        );
+
+        for (i, arg) in opcode.args.iter().enumerate() {
+            let operand = format!("${}", i + 1);
+
+            match *arg {
+                OpcodeArg::Field {
+                    byte,
+                    offset,
+                    width,
+                } => {
+                    let pattern = bits[byte as usize].get_or_insert_with(|| vec!['0'; 8]);
+
+                    for bit in offset..(offset + width).min(8) {
+                        pattern[7 - bit as usize] = char::from(b'a' + i as u8);
+                    }
+
+                    parts[byte as usize].push(format!(
+                        "{} = {}",
+                        char::from(b'a' + i as u8),
+                        operand
+                    ));
+                }
+
+                _ => {
+                    let size = arg.size(address_bytes) as usize;
+
+                    for k in 0..size {
+                        // The `k`th byte holds bits `low..low + 8` of the value.
+                        let low = match self.endianness {
+                            Endianness::Little => k * 8,
+                            Endianness::Big => (size - 1 - k) * 8,
+                        };
+
+                        parts[next + k].push(match size {
+                            1 => operand.clone(),
+                            _ => format!("{}[{}:{}]", operand, low + 7, low),
+                        });
+                    }
+
+                    next += size;
+                }
+            }
+        }
+
+        parts
+            .into_iter()
+            .zip(bits)
+            .map(|(parts, bits)| {
+                let pattern = bits.map(|bits| {
+                    let bits = bits.into_iter().collect::<String>();
+                    format!("`{} {}`", &bits[..4], &bits[4..])
+                });
+
+                pattern
+                    .into_iter()
+                    .chain(parts)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            })
+            .collect()
+    }
+}
+
+/// Converts text with Markdown `code` and **bold** spans into escaped HTML.
+fn inline_html(text: &str) -> String {
+    let escaped = text
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;");
+
+    let code = escaped
+        .split('`')
+        .enumerate()
+        .map(|(i, part)| match i % 2 {
+            1 => format!("<code>{}</code>", part),
+            _ => part.to_string(),
+        })
+        .collect::<String>();
+
+    code.split("**")
+        .enumerate()
+        .map(|(i, part)| match i % 2 {
+            1 => format!("<strong>{}</strong>", part),
+            _ => part.to_string(),
+        })
+        .collect()
+}
@@ -0,0 +1,109 @@
+use super::AssemblerConfig;
+use crate::assembler::source_map::SourceMap;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// A named range of the address space, e.g. the EEPROM that programs are burned into.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct Region {
+    /// The name of the region, which errors and usage statistics refer to it by.
+    pub name: String,
+
+    /// The first address of the region.
+    pub start: u32,
+
+    /// The number of addresses in the region.
+    pub size: u32,
+
+    /// What's in the region, which decides what can be placed there.
+    pub kind: RegionKind,
+}
+
+/// What's in a region of memory.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub enum RegionKind {
+    /// Read-only memory that the program is burned into. Code and data can only be placed here.
+    Rom,
+
+    /// Memory that the program can read and write while it runs.
+    Ram,
+
+    /// Addresses that devices are mapped to.
+    Io,
+
+    /// Addresses that nothing should use.
+    Reserved,
+}
+
+/// How much of a region an assembled program takes up.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RegionUsage {
+    /// The name of the region.
+    pub name: String,
+
+    /// What's in the region.
+    pub kind: RegionKind,
+
+    /// The first address of the region.
+    pub start: u32,
+
+    /// The number of addresses in the region.
+    pub size: u32,
+
+    /// The number of bytes placed in the region.
+    pub used: u32,
+}
+
+impl RegionUsage {
+    /// Gets the number of bytes left in the region.
+    pub fn free(&self) -> u32 {
+        self.size.saturating_sub(self.used)
+    }
+}
+
+impl Region {
+    /// Gets the address one past the end of the region.
+    pub fn end(&self) -> u64 {
+        self.start as u64 + self.size as u64
+    }
+
+    /// Whether an address is in the region.
+    pub fn contains(&self, address: u32) -> bool {
+        self.start <= address && (address as u64) < self.end()
+    }
+
+    /// Whether two regions share any addresses.
+    pub fn overlaps(&self, other: &Region) -> bool {
+        (self.start as u64) < other.end() && (other.start as u64) < self.end()
+    }
+}
+
+/// Looking up the memory map.
+impl AssemblerConfig {
+    /// Gets the region that an address is in, if any.
+    pub fn region_at(&self, address: u32) -> Option<&Region> {
+        self.regions.iter().find(|region| region.contains(address))
+    }
+
+    /// Works out how many bytes of each region an assembled program takes up, from its source map.
+    pub fn region_usage(&self, source_map: &SourceMap) -> Vec<RegionUsage> {
+        self.regions
+            .iter()
+            .map(|region| RegionUsage {
+                name: region.name.clone(),
+                kind: region.kind,
+                start: region.start,
+                size: region.size,
+                used: source_map
+                    .entries()
+                    .map(|entry| {
+                        let start = (entry.address as u64).max(region.start as u64);
+                        let end = (entry.address as u64 + entry.len as u64).min(region.end());
+
+                        end.saturating_sub(start) as u32
+                    })
+                    .sum(),
+            })
+            .collect()
+    }
+}
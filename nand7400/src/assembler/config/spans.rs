@@ -0,0 +1,185 @@
+use crate::assembler::position::Position;
+use std::collections::HashMap;
+
+/// Finds the span of every value in a JSON document, keyed by its path. Object members are joined with a `.` and
+/// array elements are indexed with `[i]`, so e.g. the mnemonic of the 4th opcode is at `opcodes[3].mnemonic`. The
+/// root value is at the empty path. Spans are byte-wise. If the JSON is malformed, then only the spans found before
+/// the error are returned.
+pub fn json_spans(source: &str) -> HashMap<String, Position> {
+    let mut scanner = SpanScanner {
+        source,
+        pos: 0,
+        spans: HashMap::new(),
+    };
+
+    scanner.value(String::new());
+
+    scanner.spans
+}
+
+/// Gets the line and column (both starting at 1) of a byte offset in the source.
+pub fn line_column(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset.min(source.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before
+        .rsplit('\n')
+        .next()
+        .map_or(0, |line| line.chars().count())
+        + 1;
+
+    (line, column)
+}
+
+/// Gets the byte offset of a line and column (both starting at 1) in the source.
+pub fn offset_of(source: &str, line: usize, column: usize) -> usize {
+    let line_start = source
+        .split_inclusive('\n')
+        .take(line.saturating_sub(1))
+        .map(str::len)
+        .sum::<usize>();
+
+    source[line_start..]
+        .char_indices()
+        .nth(column.saturating_sub(1))
+        .map_or(source.len(), |(offset, _)| line_start + offset)
+}
+
+/// Walks a JSON document, recording the span of every value it passes.
+struct SpanScanner<'a> {
+    /// The JSON source.
+    source: &'a str,
+
+    /// The current byte offset in the source.
+    pos: usize,
+
+    /// The spans found so far.
+    spans: HashMap<String, Position>,
+}
+
+impl SpanScanner<'_> {
+    /// Scans a value at the given path. Returns `None` if the JSON is malformed.
+    fn value(&mut self, path: String) -> Option<()> {
+        self.skip_whitespace();
+
+        let start = self.pos;
+
+        match self.peek()? {
+            b'{' => {
+                self.pos += 1;
+
+                loop {
+                    self.skip_whitespace();
+
+                    if self.eat(b'}') {
+                        break;
+                    }
+
+                    let key = self.string()?;
+
+                    self.skip_whitespace();
+
+                    if !self.eat(b':') {
+                        return None;
+                    }
+
+                    let member = if path.is_empty() {
+                        key
+                    } else {
+                        format!("{}.{}", path, key)
+                    };
+
+                    self.value(member)?;
+                    self.skip_whitespace();
+
+                    if self.eat(b'}') {
+                        break;
+                    } else if !self.eat(b',') {
+                        return None;
+                    }
+                }
+            }
+
+            b'[' => {
+                self.pos += 1;
+
+                for i in 0.. {
+                    self.skip_whitespace();
+
+                    if i == 0 && self.eat(b']') {
+                        break;
+                    }
+
+                    self.value(format!("{}[{}]", path, i))?;
+                    self.skip_whitespace();
+
+                    if self.eat(b']') {
+                        break;
+                    } else if !self.eat(b',') {
+                        return None;
+                    }
+                }
+            }
+
+            b'"' => {
+                self.string()?;
+            }
+
+            // Numbers, booleans, and null all end at the next delimiter.
+            _ => {
+                while self.peek().is_some_and(|byte| {
+                    !matches!(byte, b',' | b'}' | b']') && !byte.is_ascii_whitespace()
+                }) {
+                    self.pos += 1;
+                }
+            }
+        }
+
+        self.spans.insert(path, Position::new(start, self.pos));
+
+        Some(())
+    }
+
+    /// Scans a string, returning its contents with any escapes decoded.
+    fn string(&mut self) -> Option<String> {
+        let start = self.pos;
+
+        if !self.eat(b'"') {
+            return None;
+        }
+
+        loop {
+            match self.peek()? {
+                b'\\' => self.pos += 2,
+                b'"' => break,
+                _ => self.pos += 1,
+            }
+        }
+
+        self.pos += 1;
+
+        serde_json::from_str(self.source.get(start..self.pos)?).ok()
+    }
+
+    /// Skips any whitespace.
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(|byte| byte.is_ascii_whitespace()) {
+            self.pos += 1;
+        }
+    }
+
+    /// Consumes the given byte if it's next.
+    fn eat(&mut self, byte: u8) -> bool {
+        let matched = self.peek() == Some(byte);
+
+        if matched {
+            self.pos += 1;
+        }
+
+        matched
+    }
+
+    /// Gets the next byte without consuming it.
+    fn peek(&self) -> Option<u8> {
+        self.source.as_bytes().get(self.pos).copied()
+    }
+}
@@ -0,0 +1,1117 @@
+#![cfg(test)]
+
+use super::{errors::ConfigError, formats::ConfigFormat, merge::Overlay, spans::json_spans, *};
+use crate::assembler::{position::Position, Assembler};
+
+/// A configuration with a mistake of every kind.
+const INVALID_CONFIG: &str = r#"{
+    "opcodes": [
+        { "mnemonic": "lda", "binary": 1, "args": ["Immediate"] },
+        { "mnemonic": "lda", "binary": 2, "args": ["Immediate"] },
+        { "mnemonic": "2nd", "binary": 3, "args": [] },
+        { "mnemonic": ".org", "binary": 4, "args": [] },
+        { "mnemonic": "mov", "binary": 64, "args": [{ "Field": { "byte": 0, "offset": 0, "width": 6 } }] },
+        { "mnemonic": "nop", "binary": 65, "args": [] },
+        { "mnemonic": "out", "binary": 128, "args": [{ "Field": { "byte": 1, "offset": 6, "width": 3 } }] }
+    ],
+    "registers": [
+        { "name": "A", "binary": 0, "class": "general" },
+        { "name": "A", "binary": 1, "class": "general" },
+        { "name": "A-", "binary": 2, "class": "general" }
+    ]
+}"#;
+
+/// Test that a good configuration is valid, both as a value and as JSON.
+#[test]
+fn validate_valid_config() {
+    let source = r#"{
+        "opcodes": [
+            { "mnemonic": "lda", "binary": 1, "args": ["Immediate"] },
+            { "mnemonic": "lda", "binary": 2, "args": ["Indirect"] },
+            { "mnemonic": "mov", "binary": 64, "args": [{ "Field": { "byte": 0, "offset": 0, "width": 6 } }] },
+            { "mnemonic": "hlt", "binary": 255, "args": [] }
+        ]
+    }"#;
+
+    let config = AssemblerConfig::validate_json(source).unwrap();
+
+    assert_eq!(config.validate(), Ok(()));
+    assert!(Assembler::try_new(config).is_ok());
+}
+
+/// Test that every kind of mistake is found, with spans pointing at the mistake in the JSON.
+#[test]
+fn validate_invalid_config() {
+    let at = |needle: &str, nth: usize| {
+        let start = INVALID_CONFIG
+            .match_indices(needle)
+            .nth(nth)
+            .expect("The needle should be in the config!")
+            .0;
+
+        Some(Position::new(start, start + needle.len()))
+    };
+
+    assert_eq!(
+        AssemblerConfig::validate_json(INVALID_CONFIG),
+        Err(vec![
+            ConfigError::DuplicateOpcode {
+                signature: "lda Immediate".to_string(),
+                path: "opcodes[1].mnemonic".to_string(),
+                span: at("\"lda\"", 1),
+            },
+            ConfigError::InvalidMnemonic {
+                mnemonic: "2nd".to_string(),
+                path: "opcodes[2].mnemonic".to_string(),
+                span: at("\"2nd\"", 0),
+            },
+            ConfigError::KeywordMnemonic {
+                mnemonic: ".org".to_string(),
+                path: "opcodes[3].mnemonic".to_string(),
+                span: at("\".org\"", 0),
+            },
+            ConfigError::OverlappingBinary {
+                mnemonic: "nop".to_string(),
+                other: "mov".to_string(),
+                binary: 65,
+                path: "opcodes[5].binary".to_string(),
+                span: at("65", 0),
+            },
+            ConfigError::InvalidField {
+                mnemonic: "out".to_string(),
+                offset: 6,
+                width: 3,
+                path: "opcodes[6].args[0]".to_string(),
+                span: at(r#"{ "Field": { "byte": 1, "offset": 6, "width": 3 } }"#, 0),
+            },
+            ConfigError::DuplicateRegister {
+                name: "A".to_string(),
+                path: "registers[1].name".to_string(),
+                span: at("\"A\"", 1),
+            },
+            ConfigError::InvalidRegisterName {
+                name: "A-".to_string(),
+                path: "registers[2].name".to_string(),
+                span: at("\"A-\"", 0),
+            },
+        ])
+    );
+}
+
+/// Test that validating without JSON finds the same mistakes, just without spans.
+#[test]
+fn validate_without_spans() {
+    let config: AssemblerConfig = serde_json::from_str(INVALID_CONFIG).unwrap();
+    let errors = config.clone().validate().unwrap_err();
+
+    assert_eq!(errors.len(), 7);
+    assert!(errors.iter().all(|error| error.span().is_none()));
+    assert_eq!(errors[0].path(), "opcodes[1].mnemonic");
+
+    assert!(Assembler::try_new(config.clone()).is_err());
+
+    let mut assembler = Assembler::new(AssemblerConfig {
+        opcodes: vec![],
+        registers: vec![],
+        ..Default::default()
+    });
+
+    assert!(assembler.try_set_config(config).is_err());
+}
+
+/// Test that JSON that can't be read is reported where the problem is.
+#[test]
+fn validate_malformed_json() {
+    let source = "{\n    \"opcodes\": [,]\n}";
+
+    let errors = AssemblerConfig::validate_json(source).unwrap_err();
+
+    assert!(matches!(
+        errors.as_slice(),
+        [ConfigError::Json {
+            span: Some(Position { start: 18, end: 18 }),
+            ..
+        }]
+    ));
+}
+
+/// Test the spans found for each path in some JSON.
+#[test]
+fn json_value_spans() {
+    let spans = json_spans(r#"{"a": [1, "two", {"b": null}], "c\"d": true}"#);
+
+    assert_eq!(spans[""], Position::new(0, 44));
+    assert_eq!(spans["a"], Position::new(6, 29));
+    assert_eq!(spans["a[0]"], Position::new(7, 8));
+    assert_eq!(spans["a[1]"], Position::new(10, 15));
+    assert_eq!(spans["a[2].b"], Position::new(23, 27));
+    assert_eq!(spans["c\"d"], Position::new(39, 43));
+}
+
+/// The same configuration in every format, for checking that they all read the same. This one uses the old name of
+/// `Addr`, to check that it still works.
+const JSON_CONFIG: &str = r#"{
+    "opcodes": [
+        { "mnemonic": "lda", "binary": 1, "args": ["Imm8"] },
+        { "mnemonic": "ld", "binary": 2, "args": [{ "RegisterClass": { "class": "general" } }, "Addr16"] },
+        { "mnemonic": "mov", "binary": 64, "args": [{ "Field": { "byte": 0, "offset": 0, "width": 3 } }] },
+        { "mnemonic": "hlt", "binary": 255, "args": [] }
+    ],
+    "registers": [
+        { "name": "A", "binary": 0, "class": "general" }
+    ]
+}"#;
+
+/// The TOML version of `JSON_CONFIG`.
+const TOML_CONFIG: &str = r#"
+[[opcodes]]
+mnemonic = "lda"
+binary = 1
+args = ["Imm8"]
+
+[[opcodes]]
+mnemonic = "ld"
+binary = 2
+args = [{ RegisterClass = { class = "general" } }, "Addr"]
+
+[[opcodes]]
+mnemonic = "mov"
+binary = 0x40
+args = [{ Field = { byte = 0, offset = 0, width = 3 } }]
+
+[[opcodes]]
+mnemonic = "hlt"
+binary = 0xFF
+args = []
+
+[[registers]]
+name = "A"
+binary = 0
+class = "general"
+"#;
+
+/// The YAML version of `JSON_CONFIG`.
+const YAML_CONFIG: &str = r#"
+opcodes:
+  - mnemonic: lda
+    binary: 1
+    args: [Imm8]
+  - mnemonic: ld
+    binary: 2
+    args:
+      - !RegisterClass { class: general }
+      - Addr
+  - mnemonic: mov
+    binary: 0x40
+    args:
+      - !Field { byte: 0, offset: 0, width: 3 }
+  - mnemonic: hlt
+    binary: 0xFF
+    args: []
+registers:
+  - name: A
+    binary: 0
+    class: general
+"#;
+
+/// Test that the same configuration reads the same in every format, and that each format can be written and read
+/// back without changing anything.
+#[test]
+fn config_format_round_trips() {
+    let json = AssemblerConfig::from_str_as(JSON_CONFIG, ConfigFormat::Json).unwrap();
+    let toml = AssemblerConfig::from_str_as(TOML_CONFIG, ConfigFormat::Toml).unwrap();
+    let yaml = AssemblerConfig::from_str_as(YAML_CONFIG, ConfigFormat::Yaml).unwrap();
+
+    assert_eq!(json, toml);
+    assert_eq!(json, yaml);
+
+    for format in [ConfigFormat::Json, ConfigFormat::Toml, ConfigFormat::Yaml] {
+        let written = json.to_string_as(format);
+
+        assert_eq!(
+            AssemblerConfig::from_str_as(&written, format),
+            Ok(json.clone()),
+            "{:?} didn't round-trip:\n{}",
+            format,
+            written
+        );
+    }
+}
+
+/// Test loading and saving configurations, with the format worked out from the file extension.
+#[test]
+fn config_files() {
+    let config = AssemblerConfig::from_str_as(JSON_CONFIG, ConfigFormat::Json).unwrap();
+
+    for extension in ["json", "toml", "yaml", "yml"] {
+        let path = std::env::temp_dir().join(format!(
+            "nand7400-config-{}.{}",
+            std::process::id(),
+            extension
+        ));
+
+        config.save(&path).unwrap();
+        let loaded = AssemblerConfig::load(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded, Ok(config.clone()));
+    }
+
+    assert_eq!(
+        ConfigFormat::from_path("isa/Assembly.Conf.YML"),
+        Some(ConfigFormat::Yaml)
+    );
+    assert!(matches!(
+        AssemblerConfig::load("assembly.conf.ini"),
+        Err(ConfigError::UnknownFormat { .. })
+    ));
+    assert!(matches!(
+        AssemblerConfig::load("this/does/not/exist.toml"),
+        Err(ConfigError::Io { .. })
+    ));
+}
+
+/// Test that errors in TOML and YAML point at where the problem is.
+#[test]
+fn config_format_errors() {
+    let toml = AssemblerConfig::from_str_as("opcodes = 5", ConfigFormat::Toml);
+    let yaml = AssemblerConfig::from_str_as("opcodes:\n  - mnemonic: [", ConfigFormat::Yaml);
+
+    assert!(matches!(
+        toml,
+        Err(ConfigError::Toml {
+            span: Some(Position { start: 10, end: 11 }),
+            ..
+        })
+    ));
+    assert!(matches!(yaml, Err(ConfigError::Yaml { span: Some(_), .. })));
+}
+
+/// Test that the JSON Schema in the repository is up to date with the configuration types.
+#[test]
+fn json_schema_is_up_to_date() {
+    let schema = include_str!("../../../schemas/assembler-config.schema.json");
+
+    assert_eq!(schema.trim_end(), AssemblerConfig::json_schema());
+}
+
+/// The example program, which assembles the same way with every revision of the nand7400 computer.
+const PROFILE_PROGRAM: &str =
+    "jmp LABEL\nnop\nnop\nLABEL:\nadd #0x01 #0x02 #0x03\nlda #-0x01\nldb +0x01\nhlt";
+
+/// Test that the built-in profiles are valid, can be found by name, and assemble the example program the same.
+#[test]
+fn builtin_profiles() {
+    for name in profiles::PROFILE_NAMES {
+        let config = AssemblerConfig::profile(name).unwrap();
+
+        assert_eq!(config.validate(), Ok(()), "{} isn't valid", name);
+        assert_eq!(
+            Assembler::new(config).assemble(PROFILE_PROGRAM).unwrap(),
+            vec![
+                0x04, 0x05, 0x00, 0x00, 0x00, 0x03, 0x01, 0x02, 0x03, 0x01, 0xFF, 0x02, 0x01, 0xFF
+            ],
+            "{} assembled differently",
+            name
+        );
+    }
+
+    assert_eq!(
+        AssemblerConfig::profile("nand7400"),
+        Some(AssemblerConfig::nand7400_v2())
+    );
+    assert_eq!(AssemblerConfig::profile("nand7400_v3"), None);
+}
+
+/// Test that the revisions differ where expected: v1 takes two bytes for a jump, but v2 takes a 16-bit address.
+#[test]
+fn profile_revisions() {
+    let mut v1 = Assembler::new(AssemblerConfig::nand7400_v1());
+    let mut v2 = Assembler::new(AssemblerConfig::nand7400_v2());
+
+    assert_eq!(
+        v1.assemble("jmp #0x34 #0x12").unwrap(),
+        vec![0x04, 0x34, 0x12]
+    );
+    assert!(v2.assemble("jmp #0x34 #0x12").is_err());
+    assert_eq!(v2.assemble("jmp 0x1234").unwrap(), vec![0x04, 0x34, 0x12]);
+}
+
+/// Test that a configuration can extend a profile, replacing opcodes by mnemonic and adding new ones.
+#[test]
+fn extend_profile() {
+    let json = r#"{
+        "extends": "nand7400_v2",
+        "opcodes": [
+            { "mnemonic": "hlt", "binary": 254, "args": [] },
+            { "mnemonic": "out", "binary": 5, "args": ["Imm8"] }
+        ],
+        "registers": [
+            { "name": "A", "binary": 0, "class": "general" }
+        ]
+    }"#;
+    let toml = r#"
+        extends = "nand7400_v2"
+
+        [[opcodes]]
+        mnemonic = "hlt"
+        binary = 0xFE
+        args = []
+
+        [[opcodes]]
+        mnemonic = "out"
+        binary = 5
+        args = ["Imm8"]
+
+        [[registers]]
+        name = "A"
+        binary = 0
+        class = "general"
+    "#;
+
+    let config = AssemblerConfig::validate_json(json).unwrap();
+    let mut expected = AssemblerConfig::nand7400_v2();
+
+    expected.opcodes.retain(|opcode| opcode.mnemonic != "hlt");
+    expected.opcodes.push(Opcode {
+        mnemonic: "hlt".to_string(),
+        binary: 0xFE,
+        args: vec![],
+        ..Default::default()
+    });
+    expected.opcodes.push(Opcode {
+        mnemonic: "out".to_string(),
+        binary: 5,
+        args: vec![OpcodeArg::Imm8],
+        ..Default::default()
+    });
+    expected.registers.push(Register {
+        name: "A".to_string(),
+        binary: 0,
+        class: "general".to_string(),
+    });
+
+    assert_eq!(config, expected);
+    assert_eq!(
+        AssemblerConfig::from_str_as(toml, ConfigFormat::Toml),
+        Ok(expected.clone())
+    );
+
+    // Written out configurations are flattened, so they read back the same without the profile.
+    let written = config.to_string_as(ConfigFormat::Json);
+
+    assert!(!written.contains("extends"));
+    assert_eq!(AssemblerConfig::validate_json(&written), Ok(expected));
+}
+
+/// Test that mistakes in a configuration extending a profile are reported where they are in the file, and that
+/// unknown profiles and files that can't be extended are reported.
+#[test]
+fn extend_profile_errors() {
+    let source = r#"{
+        "extends": "nand7400_v2",
+        "opcodes": [
+            { "mnemonic": "out", "binary": 4, "args": [] }
+        ]
+    }"#;
+    let start = source.find("4,").unwrap();
+
+    assert_eq!(
+        AssemblerConfig::validate_json(source),
+        Err(vec![ConfigError::OverlappingBinary {
+            mnemonic: "out".to_string(),
+            other: "jmp".to_string(),
+            binary: 4,
+            path: "opcodes[6].binary".to_string(),
+            span: Some(Position::new(start, start + 1)),
+        }])
+    );
+
+    let source = r#"{ "extends": "nand7400_v9" }"#;
+    let start = source.find("\"nand7400_v9\"").unwrap();
+
+    assert_eq!(
+        AssemblerConfig::validate_json(source),
+        Err(vec![ConfigError::UnknownProfile {
+            name: "nand7400_v9".to_string(),
+            path: "extends".to_string(),
+            span: Some(Position::new(start, start + 13)),
+        }])
+    );
+    assert!(serde_json::from_str::<AssemblerConfig>(source).is_err());
+
+    // Other files can only be extended when loading a file, so plain deserializing never touches the file system.
+    let source = r#"{ "extends": "base.json" }"#;
+    let start = source.find("\"base.json\"").unwrap();
+
+    assert_eq!(
+        AssemblerConfig::validate_json(source),
+        Err(vec![ConfigError::ExtendsFile {
+            file: "base.json".to_string(),
+            path: "extends".to_string(),
+            span: Some(Position::new(start, start + 11)),
+        }])
+    );
+    assert!(serde_json::from_str::<AssemblerConfig>(source).is_err());
+}
+
+/// Shorthand for making an overlay.
+fn overlay(name: &str, remove: &[&str], replace: Vec<Opcode>, add: Vec<Opcode>) -> Overlay {
+    Overlay {
+        name: name.to_string(),
+        remove: remove.iter().map(|mnemonic| mnemonic.to_string()).collect(),
+        replace,
+        add,
+    }
+}
+
+/// Shorthand for making an opcode that takes a byte.
+fn byte_opcode(mnemonic: &str, binary: u8) -> Opcode {
+    Opcode {
+        mnemonic: mnemonic.to_string(),
+        binary,
+        args: vec![OpcodeArg::Imm8],
+        ..Default::default()
+    }
+}
+
+/// Test that overlays remove, replace, and add opcodes in order.
+#[test]
+fn merge_overlays() {
+    let base = AssemblerConfig::nand7400_v2();
+    let merged = base
+        .merge(&[
+            overlay("io", &[], vec![], vec![byte_opcode("out", 0x10)]),
+            overlay(
+                "debug",
+                &["nop"],
+                vec![
+                    byte_opcode("lda", 0x11),
+                    Opcode {
+                        mnemonic: "lda".to_string(),
+                        binary: 0x12,
+                        args: vec![OpcodeArg::Indirect],
+                        ..Default::default()
+                    },
+                ],
+                vec![Opcode {
+                    mnemonic: "brk".to_string(),
+                    binary: 0xFE,
+                    args: vec![],
+                    ..Default::default()
+                }],
+            ),
+        ])
+        .unwrap();
+
+    let mnemonics = merged
+        .opcodes
+        .iter()
+        .map(|opcode| (opcode.mnemonic.as_str(), opcode.binary))
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        mnemonics,
+        vec![
+            ("lda", 0x11),
+            ("lda", 0x12),
+            ("ldb", 0x02),
+            ("add", 0x03),
+            ("jmp", 0x04),
+            ("hlt", 0xFF),
+            ("out", 0x10),
+            ("brk", 0xFE),
+        ]
+    );
+    assert_eq!(merged.validate(), Ok(()));
+    assert_eq!(base.merge(&[]), Ok(base));
+}
+
+/// Test that every conflict between overlays and what they change is found.
+#[test]
+fn merge_overlay_conflicts() {
+    let errors = AssemblerConfig::nand7400_v2()
+        .merge(&[
+            overlay("io", &["brk"], vec![], vec![byte_opcode("out", 0x10)]),
+            overlay(
+                "debug",
+                &[],
+                vec![byte_opcode("xor", 0x20)],
+                vec![byte_opcode("lda", 0x21), byte_opcode("brk", 0x10)],
+            ),
+        ])
+        .unwrap_err();
+
+    assert_eq!(
+        errors,
+        vec![
+            ConfigError::MissingOpcode {
+                overlay: "io".to_string(),
+                mnemonic: "brk".to_string(),
+                path: "overlays[0].remove[0]".to_string(),
+                span: None,
+            },
+            ConfigError::MissingOpcode {
+                overlay: "debug".to_string(),
+                mnemonic: "xor".to_string(),
+                path: "overlays[1].replace[0].mnemonic".to_string(),
+                span: None,
+            },
+            ConfigError::OpcodeExists {
+                overlay: "debug".to_string(),
+                signature: "lda Imm8".to_string(),
+                path: "overlays[1].add[0].mnemonic".to_string(),
+                span: None,
+            },
+            ConfigError::OverlayConflict {
+                overlay: "debug".to_string(),
+                other: "io".to_string(),
+                binary: 0x10,
+                path: "overlays[1].add[1].binary".to_string(),
+                span: None,
+            },
+        ]
+    );
+
+    // Replacing an opcode from an earlier overlay with the same binary isn't a conflict.
+    assert!(AssemblerConfig::nand7400_v2()
+        .merge(&[
+            overlay("io", &[], vec![], vec![byte_opcode("out", 0x10)]),
+            overlay("io_v2", &[], vec![byte_opcode("out", 0x10)], vec![]),
+        ])
+        .is_ok());
+
+    // Opcodes can only be removed by their own mnemonic, not an alias.
+    let base = AssemblerConfig {
+        opcodes: vec![Opcode {
+            aliases: vec!["load".to_string()],
+            ..byte_opcode("lda", 0x01)
+        }],
+        ..Default::default()
+    };
+
+    assert_eq!(
+        base.merge(&[overlay("io", &["load"], vec![], vec![])]),
+        Err(vec![ConfigError::MissingOpcode {
+            overlay: "io".to_string(),
+            mnemonic: "load".to_string(),
+            path: "overlays[0].remove[0]".to_string(),
+            span: None,
+        }])
+    );
+}
+
+/// Test that configuration files can extend each other and have overlays, relative to where they are.
+#[test]
+fn extend_config_files() {
+    let dir = std::env::temp_dir().join(format!("nand7400-extends-{}", std::process::id()));
+    let isa = dir.join("isa");
+
+    std::fs::create_dir_all(&isa).unwrap();
+    std::fs::write(
+        isa.join("base.toml"),
+        r#"
+            extends = "nand7400_v2"
+
+            [[overlays]]
+            name = "io"
+            add = [{ mnemonic = "out", binary = 0x10, args = ["Imm8"] }]
+        "#,
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("lab.json"),
+        r#"{
+            "extends": "isa/base.toml",
+            "overlays": [{ "name": "debug", "add": [{ "mnemonic": "brk", "binary": 254, "args": [] }] }]
+        }"#,
+    )
+    .unwrap();
+    std::fs::write(dir.join("a.yaml"), "extends: b.yaml").unwrap();
+    std::fs::write(dir.join("b.yaml"), "extends: a.yaml").unwrap();
+
+    let lab = AssemblerConfig::load(dir.join("lab.json"));
+    let cycle = AssemblerConfig::load(dir.join("a.yaml"));
+    let missing = AssemblerConfig::load(isa.join("base.toml").with_file_name("lab.json"));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    let lab = lab.unwrap();
+
+    assert_eq!(lab.opcodes.len(), 8);
+    assert_eq!(lab.get_opcode("out").unwrap().binary, 0x10);
+    assert_eq!(lab.get_opcode("brk").unwrap().binary, 0xFE);
+    assert!(lab
+        .to_string_as(ConfigFormat::Json)
+        .find("overlays")
+        .is_none());
+
+    assert!(matches!(
+        cycle,
+        Err(ConfigError::Extends { source, .. })
+            if matches!(*source, ConfigError::ExtendsCycle { ref file, .. } if file == "a.yaml")
+    ));
+    assert!(matches!(missing, Err(ConfigError::Io { .. })));
+}
+
+/// Test that mistakes in overlays are reported where they are in the JSON, including ones found once the overlays
+/// are merged.
+#[test]
+fn overlay_error_spans() {
+    let source = r#"{
+        "extends": "nand7400_v2",
+        "overlays": [
+            { "name": "io", "remove": ["out"], "add": [{ "mnemonic": "o-t", "binary": 16, "args": [] }] }
+        ]
+    }"#;
+    let at = |needle: &str| {
+        let start = source.find(needle).unwrap();
+
+        Some(Position::new(start, start + needle.len()))
+    };
+
+    assert_eq!(
+        AssemblerConfig::validate_json(source),
+        Err(vec![ConfigError::MissingOpcode {
+            overlay: "io".to_string(),
+            mnemonic: "out".to_string(),
+            path: "overlays[0].remove[0]".to_string(),
+            span: at("\"out\""),
+        }])
+    );
+
+    let source = source.replace(r#""remove": ["out"], "#, "");
+
+    assert_eq!(
+        AssemblerConfig::validate_json(&source),
+        Err(vec![ConfigError::InvalidMnemonic {
+            mnemonic: "o-t".to_string(),
+            path: "opcodes[6].mnemonic".to_string(),
+            span: {
+                let start = source.find("\"o-t\"").unwrap();
+                Some(Position::new(start, start + 5))
+            },
+        }])
+    );
+}
+
+/// Test that only the supported address widths and word sizes are valid.
+#[test]
+fn validate_address_width_and_word_size() {
+    let source = r#"{ "extends": "nand7400_v2", "address_bits": 20, "word_size": 2 }"#;
+    let at = |needle: &str| {
+        let start = source.find(needle).unwrap();
+
+        Some(Position::new(start, start + needle.len()))
+    };
+
+    assert_eq!(
+        AssemblerConfig::validate_json(source),
+        Err(vec![ConfigError::InvalidAddressWidth {
+            bits: 20,
+            path: "address_bits".to_string(),
+            span: at("20"),
+        }])
+    );
+
+    // Word sizes are rejected when loading, as the assembler can't encode them.
+    for size in [0, 5] {
+        let source = format!(r#"{{ "extends": "nand7400_v2", "word_size": {size} }}"#);
+        let start = source.rfind(&size.to_string()).unwrap();
+
+        assert_eq!(
+            AssemblerConfig::validate_json(&source),
+            Err(vec![ConfigError::InvalidWordSize {
+                size,
+                path: "word_size".to_string(),
+                span: Some(Position::new(start, start + 1)),
+            }])
+        );
+        assert!(serde_json::from_str::<AssemblerConfig>(&source).is_err());
+    }
+
+    let config =
+        AssemblerConfig::validate_json(r#"{ "address_bits": 24, "endianness": "Big" }"#).unwrap();
+
+    assert_eq!(config.address_bytes(), 3);
+    assert_eq!(config.max_address(), 0xFF_FFFF);
+    assert_eq!(config.word_size, 1);
+    assert_eq!(config.endianness, Endianness::Big);
+}
+
+/// Test that regions have to be unique, non-empty, inside of the address space, and not overlap.
+#[test]
+fn validate_regions() {
+    let source = r#"{
+    "address_bits": 12,
+    "regions": [
+        { "name": "rom", "start": 0, "size": 2048, "kind": "Rom" },
+        { "name": "ram", "start": 2048, "size": 2048, "kind": "Ram" },
+        { "name": "io", "start": 4000, "size": 0, "kind": "Io" },
+        { "name": "rom", "start": 2000, "size": 96, "kind": "Reserved" },
+        { "name": "high", "start": 4095, "size": 2, "kind": "Reserved" }
+    ]
+}"#;
+    let spans = json_spans(source);
+    let span_of = |path: &str| spans.get(path).copied();
+
+    assert_eq!(
+        AssemblerConfig::validate_json(source),
+        Err(vec![
+            ConfigError::RegionOutOfRange {
+                name: "io".to_string(),
+                start: 4000,
+                size: 0,
+                path: "regions[2]".to_string(),
+                span: span_of("regions[2]"),
+            },
+            ConfigError::DuplicateRegion {
+                name: "rom".to_string(),
+                path: "regions[3].name".to_string(),
+                span: span_of("regions[3].name"),
+            },
+            ConfigError::RegionOverlap {
+                name: "rom".to_string(),
+                other: "rom".to_string(),
+                path: "regions[3]".to_string(),
+                span: span_of("regions[3]"),
+            },
+            ConfigError::RegionOutOfRange {
+                name: "high".to_string(),
+                start: 4095,
+                size: 2,
+                path: "regions[4]".to_string(),
+                span: span_of("regions[4]"),
+            },
+        ])
+    );
+
+    // Regions replace inherited ones with the same name.
+    let config = AssemblerConfig::validate_json(
+        r#"{ "extends": "nand7400", "regions": [{ "name": "rom", "start": 0, "size": 32768, "kind": "Rom" }] }"#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        config.region_at(0x7FFF).map(|region| region.kind),
+        Some(regions::RegionKind::Rom)
+    );
+    assert_eq!(config.region_at(0x8000), None);
+}
+
+/// Test that pseudo-instructions need unique mnemonics and parameters, and bodies that only use opcodes.
+#[test]
+fn validate_pseudo_instructions() {
+    let source = r#"{
+    "extends": "nand7400",
+    "registers": [{ "name": "A", "binary": 0, "class": "general" }],
+    "pseudo_instructions": [
+        { "mnemonic": "jmp", "params": [], "body": ["nop"] },
+        { "mnemonic": "inc", "params": ["A", "x", "x"], "body": ["add x #1 #0"] },
+        { "mnemonic": "clr", "params": [], "body": ["here: nop", "inc", "lda @"] }
+    ]
+}"#;
+    let spans = json_spans(source);
+    let span_of = |path: &str| spans.get(path).copied();
+
+    assert_eq!(
+        AssemblerConfig::validate_json(source),
+        Err(vec![
+            ConfigError::DuplicatePseudo {
+                mnemonic: "jmp".to_string(),
+                path: "pseudo_instructions[0].mnemonic".to_string(),
+                span: span_of("pseudo_instructions[0].mnemonic"),
+            },
+            ConfigError::InvalidPseudoParam {
+                mnemonic: "inc".to_string(),
+                param: "A".to_string(),
+                path: "pseudo_instructions[1].params[0]".to_string(),
+                span: span_of("pseudo_instructions[1].params[0]"),
+            },
+            ConfigError::InvalidPseudoParam {
+                mnemonic: "inc".to_string(),
+                param: "x".to_string(),
+                path: "pseudo_instructions[1].params[2]".to_string(),
+                span: span_of("pseudo_instructions[1].params[2]"),
+            },
+            ConfigError::InvalidPseudoBody {
+                mnemonic: "clr".to_string(),
+                message: "label 'here' can't be defined here.".to_string(),
+                path: "pseudo_instructions[2].body[0]".to_string(),
+                span: span_of("pseudo_instructions[2].body[0]"),
+            },
+            ConfigError::InvalidPseudoBody {
+                mnemonic: "clr".to_string(),
+                message: "'inc' isn't an opcode.".to_string(),
+                path: "pseudo_instructions[2].body[1]".to_string(),
+                span: span_of("pseudo_instructions[2].body[1]"),
+            },
+            ConfigError::InvalidPseudoBody {
+                mnemonic: "clr".to_string(),
+                message: "Unknown character '@'".to_string(),
+                path: "pseudo_instructions[2].body[2]".to_string(),
+                span: span_of("pseudo_instructions[2].body[2]"),
+            },
+        ])
+    );
+}
+
+/// Test that opcode metadata is read from the configuration and described in Markdown.
+#[test]
+fn describe_opcodes() {
+    let config = AssemblerConfig::validate_json(
+        r#"{
+    "opcodes": [
+        {
+            "mnemonic": "lda",
+            "binary": 1,
+            "args": ["Imm8"],
+            "description": "Loads a value into A.",
+            "operands": ["The value to load."],
+            "cycles": 3,
+            "flags": ["Z", "N"],
+            "aliases": ["ld"]
+        },
+        { "mnemonic": "ldx", "binary": 2, "args": ["Indirect"], "deprecated": true, "aliases": ["ld"] }
+    ]
+}"#,
+    )
+    .unwrap();
+
+    assert_eq!(config.opcodes[0].cycles, Some(3));
+    assert!(config.opcodes[1].deprecated);
+    assert_eq!(
+        config.describe("lda").unwrap(),
+        "`lda Imm8`\n\n\
+         Loads a value into A.\n\n\
+         - `Imm8`: The value to load.\n\n\
+         Cycles: 3\n\n\
+         Flags affected: Z, N\n\n\
+         Aliases: ld"
+    );
+    assert_eq!(
+        config.describe("ld").unwrap().split("\n\n---\n\n").nth(1),
+        Some("`ldx Indirect`\n\n**Deprecated.**\n\nAliases: ld")
+    );
+    assert_eq!(config.describe("nop"), None);
+
+    // Metadata isn't written out unless it's there.
+    assert!(!AssemblerConfig::nand7400()
+        .to_string_as(ConfigFormat::Json)
+        .contains("deprecated"));
+}
+
+/// Test that aliases are checked like mnemonics.
+#[test]
+fn validate_aliases() {
+    let source = r#"{
+    "opcodes": [
+        { "mnemonic": "lda", "binary": 1, "args": ["Imm8"], "aliases": ["load", "2nd"] },
+        { "mnemonic": "load", "binary": 2, "args": ["Imm8"] }
+    ]
+}"#;
+    let spans = json_spans(source);
+    let span_of = |path: &str| spans.get(path).copied();
+
+    assert_eq!(
+        AssemblerConfig::validate_json(source),
+        Err(vec![
+            ConfigError::InvalidMnemonic {
+                mnemonic: "2nd".to_string(),
+                path: "opcodes[0].aliases[1]".to_string(),
+                span: span_of("opcodes[0].aliases[1]"),
+            },
+            ConfigError::DuplicateOpcode {
+                signature: "load Imm8".to_string(),
+                path: "opcodes[1].mnemonic".to_string(),
+                span: span_of("opcodes[1].mnemonic"),
+            },
+        ])
+    );
+}
+
+/// Test that vectors have valid names, fit in the address space, and don't overlap.
+#[test]
+fn validate_vectors() {
+    let source = r#"{
+    "address_bits": 8,
+    "registers": [{ "name": "A", "binary": 0, "class": "general" }],
+    "vectors": [
+        { "name": "reset", "address": 252, "entry": true },
+        { "name": "A", "address": 250 },
+        { "name": "reset", "address": 248 },
+        { "name": "irq", "address": 252, "entry": true },
+        { "name": "nmi", "address": 256 }
+    ]
+}"#;
+    let spans = json_spans(source);
+    let span_of = |path: &str| spans.get(path).copied();
+
+    assert_eq!(
+        AssemblerConfig::validate_json(source),
+        Err(vec![
+            ConfigError::InvalidVectorName {
+                name: "A".to_string(),
+                path: "vectors[1].name".to_string(),
+                span: span_of("vectors[1].name"),
+            },
+            ConfigError::DuplicateVector {
+                name: "reset".to_string(),
+                path: "vectors[2].name".to_string(),
+                span: span_of("vectors[2].name"),
+            },
+            ConfigError::VectorOverlap {
+                name: "irq".to_string(),
+                other: "reset".to_string(),
+                path: "vectors[3]".to_string(),
+                span: span_of("vectors[3]"),
+            },
+            ConfigError::MultipleEntryVectors {
+                name: "irq".to_string(),
+                other: "reset".to_string(),
+                path: "vectors[3]".to_string(),
+                span: span_of("vectors[3]"),
+            },
+            ConfigError::VectorOutOfRange {
+                name: "nmi".to_string(),
+                address: 256,
+                path: "vectors[4]".to_string(),
+                span: span_of("vectors[4]"),
+            },
+        ])
+    );
+
+    // Vectors replace inherited ones with the same name.
+    let config = AssemblerConfig::validate_json(
+        r#"{ "extends": "nand7400", "vectors": [{ "name": "reset", "address": 65534, "entry": true }] }"#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        config.entry_vector().map(|vector| vector.address),
+        Some(0xFFFE)
+    );
+}
+
+/// Test that argument constraints that can never be met are caught.
+#[test]
+fn validate_constraints() {
+    let source = r#"{
+    "opcodes": [
+        { "mnemonic": "lda", "binary": 1, "args": ["Imm8"], "constraints": [{ "min": 4, "max": 2 }] },
+        { "mnemonic": "jmp", "binary": 2, "args": ["Addr"], "constraints": [{ "align": 0 }] },
+        { "mnemonic": "nop", "binary": 3, "args": [], "constraints": [{ "max": 1 }] },
+        { "mnemonic": "out", "binary": 4, "args": ["Imm8"], "constraints": [{ "min": 1, "allowed": [1, 2] }] }
+    ]
+}"#;
+    let spans = json_spans(source);
+    let span_of = |path: &str| spans.get(path).copied();
+    let invalid = |mnemonic: &str, message: &str, path: &str| ConfigError::InvalidConstraint {
+        mnemonic: mnemonic.to_string(),
+        message: message.to_string(),
+        path: path.to_string(),
+        span: span_of(path),
+    };
+
+    assert_eq!(
+        AssemblerConfig::validate_json(source),
+        Err(vec![
+            invalid(
+                "lda",
+                "`min` is more than `max`",
+                "opcodes[0].constraints[0]"
+            ),
+            invalid("jmp", "`align` is 0", "opcodes[1].constraints[0]"),
+            invalid(
+                "nop",
+                "argument 1 doesn't exist",
+                "opcodes[2].constraints[0]"
+            ),
+        ])
+    );
+
+    let config = AssemblerConfig::validate_json(
+        r#"{ "opcodes": [{ "mnemonic": "out", "binary": 4, "args": ["Imm8"], "constraints": [{ "min": 1, "allowed": [1, 2] }] }] }"#,
+    )
+    .unwrap();
+
+    assert!(config
+        .describe("out")
+        .unwrap()
+        .contains("Argument 1 must be at least 1, one of 1, 2."));
+}
+
+/// Test the parts of the instruction set reference.
+#[test]
+fn reference_documents() {
+    let mut config = AssemblerConfig::nand7400();
+
+    config.opcodes.push(Opcode {
+        mnemonic: "mov".to_string(),
+        binary: 0x40,
+        args: vec![
+            OpcodeArg::Field {
+                byte: 0,
+                offset: 0,
+                width: 3,
+            },
+            OpcodeArg::Addr,
+        ],
+        description: Some("Moves <things> & stuff.".to_string()),
+        operands: vec!["The register.".to_string()],
+        cycles: Some(4),
+        constraints: vec![ArgConstraint {
+            max: Some(5),
+            ..Default::default()
+        }],
+        ..Default::default()
+    });
+
+    assert_eq!(
+        config.unused_binaries().len(),
+        256 - 6 - 8,
+        "Fields should take up every opcode they can make."
+    );
+
+    let markdown = config.reference_markdown();
+
+    assert!(markdown.contains("| 0x | nop | lda | ldb | add | jmp |  |"));
+    assert!(markdown.contains("| 4x | mov | mov | mov | mov | mov | mov | mov | mov |  |"));
+    assert!(markdown.contains("`0x05`–`0x3F`, `0x48`–`0xFE`"));
+    assert!(markdown.contains("| `0000 0100` | $1[7:0] | $1[15:8] |"));
+    assert!(markdown.contains("| `0100 0aaa`, a = $1 | $2[7:0] | $2[15:8] |"));
+    assert!(markdown
+        .contains("| $1 | `Field { byte: 0, offset: 0, width: 3 }` | 3 bit(s) | The register. |"));
+    assert!(markdown.contains("Cycles: 4. $1 must be at most 5."));
+
+    config.endianness = Endianness::Big;
+
+    let html = config.reference_html();
+
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(html.contains("<p>Moves &lt;things&gt; &amp; stuff.</p>"));
+    assert!(html.contains("<td><code>0000 0100</code></td><td>$1[15:8]</td><td>$1[7:0]</td>"));
+}
+
+/// Test that fingerprints only change when something that affects assembling changes.
+#[test]
+fn config_fingerprints() {
+    let config = AssemblerConfig::nand7400();
+    let mut documented = config.clone();
+
+    documented.opcodes[0].description = Some("Does nothing.".to_string());
+    documented.opcodes[0].cycles = Some(1);
+    documented.opcodes.reverse();
+
+    assert_eq!(config.fingerprint(), documented.fingerprint());
+
+    let mut changed = config.clone();
+    changed.opcodes[0].binary = 0x80;
+
+    assert_ne!(config.fingerprint(), changed.fingerprint());
+
+    let mut changed = config.clone();
+    changed.endianness = Endianness::Big;
+
+    assert_ne!(config.fingerprint(), changed.fingerprint());
+}
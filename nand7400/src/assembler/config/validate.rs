@@ -0,0 +1,536 @@
+use super::{
+    errors::ConfigError, formats::json_error, pseudos::PseudoInstruction, spans::json_spans,
+    AssemblerConfig, ConfigFile, Opcode, OpcodeArg,
+};
+use crate::assembler::{
+    parser::{ast::InstructionKind, Parser},
+    position::Position,
+};
+use std::collections::HashMap;
+
+/// Validation of assembler configurations.
+impl AssemblerConfig {
+    /// Checks the configuration for mistakes that would otherwise only show up as confusing errors when assembling,
+    /// returning every mistake found. The errors don't have spans, as there's no source to point into; use
+    /// `validate_json` for that.
+    pub fn validate(&self) -> Result<(), Vec<ConfigError>> {
+        let errors = self.validate_with_spans(&HashMap::new());
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Reads a configuration from JSON and validates it, like `validate`. The errors have spans pointing into the
+    /// JSON, so that they can be shown to whoever wrote it.
+    pub fn validate_json(source: &str) -> Result<Self, Vec<ConfigError>> {
+        let file: ConfigFile =
+            serde_json::from_str(source).map_err(|err| vec![json_error(source, err)])?;
+        let spans = json_spans(source);
+
+        let config = file.clone().resolve().map_err(|mut errors| {
+            for error in &mut errors {
+                *error.span_mut() = spans.get(error.path()).copied();
+            }
+
+            errors
+        })?;
+
+        let spans = merged_spans(&spans, &file, &config);
+        let errors = config.validate_with_spans(&spans);
+
+        if errors.is_empty() {
+            Ok(config)
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+impl AssemblerConfig {
+    /// Validates the configuration, getting spans for the errors from `spans` (see `json_spans`).
+    fn validate_with_spans(&self, spans: &HashMap<String, Position>) -> Vec<ConfigError> {
+        let span_of = |path: &str| spans.get(path).copied();
+        let mut errors = vec![];
+
+        if ![8, 12, 16, 24].contains(&self.address_bits) {
+            errors.push(ConfigError::InvalidAddressWidth {
+                bits: self.address_bits,
+                path: "address_bits".to_string(),
+                span: span_of("address_bits"),
+            });
+        }
+
+        if !(1..=4).contains(&self.word_size) {
+            errors.push(ConfigError::InvalidWordSize {
+                size: self.word_size,
+                path: "word_size".to_string(),
+                span: span_of("word_size"),
+            });
+        }
+
+        for (i, opcode) in self.opcodes.iter().enumerate() {
+            let path = format!("opcodes[{}].mnemonic", i);
+
+            if opcode.mnemonic.starts_with('.') {
+                errors.push(ConfigError::KeywordMnemonic {
+                    mnemonic: opcode.mnemonic.clone(),
+                    span: span_of(&path),
+                    path,
+                });
+            } else if !is_identifier(&opcode.mnemonic) {
+                errors.push(ConfigError::InvalidMnemonic {
+                    mnemonic: opcode.mnemonic.clone(),
+                    span: span_of(&path),
+                    path,
+                });
+            } else if self.opcodes[..i].iter().any(|other| {
+                other.args == opcode.args
+                    && (other.is_named(&opcode.mnemonic)
+                        || opcode.aliases.iter().any(|alias| other.is_named(alias)))
+            }) {
+                errors.push(ConfigError::DuplicateOpcode {
+                    signature: opcode.signature(),
+                    span: span_of(&path),
+                    path,
+                });
+            }
+
+            if let Some(other) = self.opcodes[..i]
+                .iter()
+                .find(|other| binaries_overlap(other, opcode))
+            {
+                let path = format!("opcodes[{}].binary", i);
+
+                errors.push(ConfigError::OverlappingBinary {
+                    mnemonic: opcode.mnemonic.clone(),
+                    other: other.mnemonic.clone(),
+                    binary: opcode.binary,
+                    span: span_of(&path),
+                    path,
+                });
+            }
+
+            for (j, alias) in opcode.aliases.iter().enumerate() {
+                if !is_identifier(alias) {
+                    let path = format!("opcodes[{}].aliases[{}]", i, j);
+
+                    errors.push(ConfigError::InvalidMnemonic {
+                        mnemonic: alias.clone(),
+                        span: span_of(&path),
+                        path,
+                    });
+                }
+            }
+
+            for (j, constraint) in opcode.constraints.iter().enumerate() {
+                let message = if j >= opcode.args.len() {
+                    Some(format!("argument {} doesn't exist", j + 1))
+                } else if constraint.align == Some(0) {
+                    Some("`align` is 0".to_string())
+                } else if matches!((constraint.min, constraint.max), (Some(min), Some(max)) if min > max)
+                {
+                    Some("`min` is more than `max`".to_string())
+                } else {
+                    None
+                };
+
+                if let Some(message) = message {
+                    let path = format!("opcodes[{}].constraints[{}]", i, j);
+
+                    errors.push(ConfigError::InvalidConstraint {
+                        mnemonic: opcode.mnemonic.clone(),
+                        message,
+                        span: span_of(&path),
+                        path,
+                    });
+                }
+            }
+
+            for (j, arg) in opcode.args.iter().enumerate() {
+                if let OpcodeArg::Field { offset, width, .. } = *arg {
+                    if width == 0 || offset as u16 + width as u16 > 8 {
+                        let path = format!("opcodes[{}].args[{}]", i, j);
+
+                        errors.push(ConfigError::InvalidField {
+                            mnemonic: opcode.mnemonic.clone(),
+                            offset,
+                            width,
+                            span: span_of(&path),
+                            path,
+                        });
+                    }
+                }
+            }
+        }
+
+        for (i, register) in self.registers.iter().enumerate() {
+            let path = format!("registers[{}].name", i);
+
+            if !is_identifier(&register.name) {
+                errors.push(ConfigError::InvalidRegisterName {
+                    name: register.name.clone(),
+                    span: span_of(&path),
+                    path,
+                });
+            } else if self.registers[..i]
+                .iter()
+                .any(|other| other.name == register.name)
+            {
+                errors.push(ConfigError::DuplicateRegister {
+                    name: register.name.clone(),
+                    span: span_of(&path),
+                    path,
+                });
+            }
+        }
+
+        for (i, region) in self.regions.iter().enumerate() {
+            if self.regions[..i]
+                .iter()
+                .any(|other| other.name == region.name)
+            {
+                let path = format!("regions[{}].name", i);
+
+                errors.push(ConfigError::DuplicateRegion {
+                    name: region.name.clone(),
+                    span: span_of(&path),
+                    path,
+                });
+            }
+
+            let path = format!("regions[{}]", i);
+
+            if region.size == 0 || region.end() > self.max_address() as u64 + 1 {
+                errors.push(ConfigError::RegionOutOfRange {
+                    name: region.name.clone(),
+                    start: region.start,
+                    size: region.size,
+                    span: span_of(&path),
+                    path,
+                });
+            } else if let Some(other) = self.regions[..i]
+                .iter()
+                .find(|other| other.overlaps(region))
+            {
+                errors.push(ConfigError::RegionOverlap {
+                    name: region.name.clone(),
+                    other: other.name.clone(),
+                    span: span_of(&path),
+                    path,
+                });
+            }
+        }
+
+        for (i, pseudo) in self.pseudo_instructions.iter().enumerate() {
+            errors.extend(self.validate_pseudo(i, pseudo, &span_of));
+        }
+
+        let address_bytes = self.address_bytes();
+
+        for (i, vector) in self.vectors.iter().enumerate() {
+            let path = format!("vectors[{}].name", i);
+
+            if !is_identifier(&vector.name)
+                || self
+                    .registers
+                    .iter()
+                    .any(|register| register.name == vector.name)
+            {
+                errors.push(ConfigError::InvalidVectorName {
+                    name: vector.name.clone(),
+                    span: span_of(&path),
+                    path,
+                });
+            } else if self.vectors[..i]
+                .iter()
+                .any(|other| other.name == vector.name)
+            {
+                errors.push(ConfigError::DuplicateVector {
+                    name: vector.name.clone(),
+                    span: span_of(&path),
+                    path,
+                });
+            }
+
+            let path = format!("vectors[{}]", i);
+
+            if vector.end(address_bytes) > self.max_address() as u64 + 1 {
+                errors.push(ConfigError::VectorOutOfRange {
+                    name: vector.name.clone(),
+                    address: vector.address,
+                    span: span_of(&path),
+                    path: path.clone(),
+                });
+            } else if let Some(other) = self.vectors[..i].iter().find(|other| {
+                (other.address as u64) < vector.end(address_bytes)
+                    && (vector.address as u64) < other.end(address_bytes)
+            }) {
+                errors.push(ConfigError::VectorOverlap {
+                    name: vector.name.clone(),
+                    other: other.name.clone(),
+                    span: span_of(&path),
+                    path: path.clone(),
+                });
+            }
+
+            if let Some(other) = self.vectors[..i]
+                .iter()
+                .find(|other| vector.entry && other.entry)
+            {
+                errors.push(ConfigError::MultipleEntryVectors {
+                    name: vector.name.clone(),
+                    other: other.name.clone(),
+                    span: span_of(&path),
+                    path,
+                });
+            }
+        }
+
+        errors
+    }
+
+    /// Validates a pseudo-instruction, where `i` is its index.
+    fn validate_pseudo(
+        &self,
+        i: usize,
+        pseudo: &PseudoInstruction,
+        span_of: &impl Fn(&str) -> Option<Position>,
+    ) -> Vec<ConfigError> {
+        let mut errors = vec![];
+        let path = format!("pseudo_instructions[{}].mnemonic", i);
+
+        if pseudo.mnemonic.starts_with('.') {
+            errors.push(ConfigError::KeywordMnemonic {
+                mnemonic: pseudo.mnemonic.clone(),
+                span: span_of(&path),
+                path,
+            });
+        } else if !is_identifier(&pseudo.mnemonic) {
+            errors.push(ConfigError::InvalidMnemonic {
+                mnemonic: pseudo.mnemonic.clone(),
+                span: span_of(&path),
+                path,
+            });
+        } else if self.get_opcode(&pseudo.mnemonic).is_some()
+            || self.pseudo_instructions[..i]
+                .iter()
+                .any(|other| other.mnemonic == pseudo.mnemonic)
+        {
+            errors.push(ConfigError::DuplicatePseudo {
+                mnemonic: pseudo.mnemonic.clone(),
+                span: span_of(&path),
+                path,
+            });
+        }
+
+        for (j, param) in pseudo.params.iter().enumerate() {
+            if !is_identifier(param)
+                || pseudo.params[..j].contains(param)
+                || self
+                    .registers
+                    .iter()
+                    .any(|register| &register.name == param)
+            {
+                let path = format!("pseudo_instructions[{}].params[{}]", i, j);
+
+                errors.push(ConfigError::InvalidPseudoParam {
+                    mnemonic: pseudo.mnemonic.clone(),
+                    param: param.clone(),
+                    span: span_of(&path),
+                    path,
+                });
+            }
+        }
+
+        for (j, line) in pseudo.body.iter().enumerate() {
+            let message = match Parser::with_registers(line, &self.registers)
+                .and_then(|parser| parser.parse())
+            {
+                Err(err) => Some(err.to_string()),
+
+                Ok(ast) => {
+                    ast.instructions
+                        .iter()
+                        .find_map(|instruction| match &instruction.kind {
+                            InstructionKind::Label(label) => {
+                                Some(format!("label '{}' can't be defined here.", label))
+                            }
+                            InstructionKind::Opcode { mnemonic, .. }
+                                if self.get_opcode(mnemonic).is_none() =>
+                            {
+                                Some(format!("'{}' isn't an opcode.", mnemonic))
+                            }
+                            _ => None,
+                        })
+                }
+            };
+
+            if let Some(message) = message {
+                let path = format!("pseudo_instructions[{}].body[{}]", i, j);
+
+                errors.push(ConfigError::InvalidPseudoBody {
+                    mnemonic: pseudo.mnemonic.clone(),
+                    message,
+                    span: span_of(&path),
+                    path,
+                });
+            }
+        }
+
+        errors
+    }
+}
+
+/// Works out the spans of the opcodes, registers, regions, pseudo-instructions, and vectors in a merged configuration,
+/// from the spans of where they were written in the file. Anything that came from a profile or another file doesn't
+/// have a span.
+fn merged_spans(
+    spans: &HashMap<String, Position>,
+    file: &ConfigFile,
+    config: &AssemblerConfig,
+) -> HashMap<String, Position> {
+    let written_opcodes =
+        file.opcodes
+            .iter()
+            .enumerate()
+            .map(|(i, opcode)| (opcode, format!("opcodes[{}]", i)))
+            .chain(file.overlays.iter().enumerate().flat_map(|(i, overlay)| {
+                let replaced =
+                    overlay.replace.iter().enumerate().map(move |(j, opcode)| {
+                        (opcode, format!("overlays[{}].replace[{}]", i, j))
+                    });
+                let added = overlay
+                    .add
+                    .iter()
+                    .enumerate()
+                    .map(move |(j, opcode)| (opcode, format!("overlays[{}].add[{}]", i, j)));
+
+                replaced.chain(added)
+            }))
+            .collect::<Vec<_>>();
+
+    // Everything outside of the opcodes, registers, regions, pseudo-instructions, and vectors is where it was written.
+    let mut merged = spans
+        .iter()
+        .filter(|(path, _)| {
+            !path.starts_with("opcodes[")
+                && !path.starts_with("registers[")
+                && !path.starts_with("regions[")
+                && !path.starts_with("pseudo_instructions[")
+                && !path.starts_with("vectors[")
+        })
+        .map(|(path, span)| (path.clone(), *span))
+        .collect::<HashMap<_, _>>();
+
+    for (i, opcode) in config.opcodes.iter().enumerate() {
+        // The last place an opcode was written is the one that it came from.
+        if let Some((_, from)) = written_opcodes
+            .iter()
+            .rev()
+            .find(|(other, _)| *other == opcode)
+        {
+            copy_spans(spans, from, &format!("opcodes[{}]", i), &mut merged);
+        }
+    }
+
+    for (i, register) in config.registers.iter().enumerate() {
+        if let Some(j) = file.registers.iter().position(|other| other == register) {
+            copy_spans(
+                spans,
+                &format!("registers[{}]", j),
+                &format!("registers[{}]", i),
+                &mut merged,
+            );
+        }
+    }
+
+    for (i, region) in config.regions.iter().enumerate() {
+        if let Some(j) = file.regions.iter().position(|other| other == region) {
+            copy_spans(
+                spans,
+                &format!("regions[{}]", j),
+                &format!("regions[{}]", i),
+                &mut merged,
+            );
+        }
+    }
+
+    for (i, pseudo) in config.pseudo_instructions.iter().enumerate() {
+        if let Some(j) = file
+            .pseudo_instructions
+            .iter()
+            .position(|other| other == pseudo)
+        {
+            copy_spans(
+                spans,
+                &format!("pseudo_instructions[{}]", j),
+                &format!("pseudo_instructions[{}]", i),
+                &mut merged,
+            );
+        }
+    }
+
+    for (i, vector) in config.vectors.iter().enumerate() {
+        if let Some(j) = file.vectors.iter().position(|other| other == vector) {
+            copy_spans(
+                spans,
+                &format!("vectors[{}]", j),
+                &format!("vectors[{}]", i),
+                &mut merged,
+            );
+        }
+    }
+
+    merged
+}
+
+/// Copies the spans of the value at path `from` and everything in it to path `to`.
+fn copy_spans(
+    spans: &HashMap<String, Position>,
+    from: &str,
+    to: &str,
+    into: &mut HashMap<String, Position>,
+) {
+    for (path, span) in spans {
+        if let Some(rest) = path.strip_prefix(from) {
+            if rest.is_empty() || rest.starts_with(['.', '[']) {
+                into.insert(format!("{}{}", to, rest), *span);
+            }
+        }
+    }
+}
+
+/// Whether a name can be lexed as a single identifier.
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+
+    chars
+        .next()
+        .is_some_and(|first| first.is_alphabetic() || first == '_')
+        && chars.all(|c| c.is_alphanumeric() || c == '_')
+}
+
+/// Whether two opcodes can have the same first byte, once their bit fields are filled in.
+pub(super) fn binaries_overlap(a: &Opcode, b: &Opcode) -> bool {
+    let fixed = fixed_bits(a) & fixed_bits(b);
+
+    a.binary & fixed == b.binary & fixed
+}
+
+/// Gets the bits of an opcode's first byte that aren't part of a bit field.
+pub(super) fn fixed_bits(opcode: &Opcode) -> u8 {
+    opcode.args.iter().fold(0xFF, |fixed, arg| match *arg {
+        OpcodeArg::Field {
+            byte: 0,
+            offset,
+            width,
+        } => {
+            let mask = (1_u16 << width.min(8)).wrapping_sub(1) as u8;
+            fixed & !mask.checked_shl(offset as u32).unwrap_or(0)
+        }
+        _ => fixed,
+    })
+}
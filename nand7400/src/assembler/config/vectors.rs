@@ -0,0 +1,39 @@
+use super::{is_false, AssemblerConfig};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// A vector, which is a fixed address that holds the address of somewhere in the program, e.g. where to start running
+/// from on reset or where to jump to on an interrupt. Each one takes up as many bytes as an address.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct Vector {
+    /// The name of the vector, which `.vector` refers to it by (e.g. `irq`).
+    pub name: String,
+
+    /// The address that the vector is stored at.
+    pub address: u32,
+
+    /// Whether this is the reset vector, which holds the entry point of the program set with `.entry` rather than
+    /// being set with `.vector`.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub entry: bool,
+}
+
+impl Vector {
+    /// Gets the address one past the end of the vector, when addresses are `address_bytes` long.
+    pub fn end(&self, address_bytes: u16) -> u64 {
+        self.address as u64 + address_bytes as u64
+    }
+}
+
+/// Looking up vectors.
+impl AssemblerConfig {
+    /// Gets a vector by its name.
+    pub fn get_vector(&self, name: &str) -> Option<&Vector> {
+        self.vectors.iter().find(|vector| vector.name == name)
+    }
+
+    /// Gets the vector that holds the entry point of the program, if there is one.
+    pub fn entry_vector(&self) -> Option<&Vector> {
+        self.vectors.iter().find(|vector| vector.entry)
+    }
+}
@@ -1,5 +1,6 @@
 use crate::assembler::{parser::errors::ParsingError, position::Position};
 use miette::Diagnostic;
+use std::borrow::Borrow;
 
 use super::config::OpcodeArg;
 
@@ -75,6 +76,303 @@ pub enum AssemblerError {
         arg_span: Position,
     },
 
+    /// A number given to an opcode doesn't fit in the argument it was given as.
+    #[error(
+        "'{}' expects an argument of type {:?}, but {:#06x} doesn't fit in it.",
+        mnemonic,
+        expected,
+        value
+    )]
+    #[diagnostic(
+        code(nand7400::errors::arg_out_of_range),
+        help("Use a smaller number, or check the type of argument the opcode expects.")
+    )]
+    ArgOutOfRange {
+        /// The opcode that was given the argument.
+        mnemonic: String,
+
+        /// The type of argument that the opcode expects.
+        expected: OpcodeArg,
+
+        /// The value of the argument.
+        value: u32,
+
+        /// The span of the opcode in the source code.
+        #[label("This mnemonic")]
+        mnemonic_span: Position,
+
+        /// The span of the argument in the source code.
+        #[label("This argument")]
+        arg_span: Position,
+    },
+
+    /// An argument given to an opcode breaks one of the constraints on it from the configuration.
+    #[error(
+        "'{}' needs this argument to be {}, but it's {:#06x}.",
+        mnemonic,
+        constraint,
+        value
+    )]
+    #[diagnostic(
+        code(nand7400::errors::constraint_violated),
+        help("Check the description of the opcode for which values it takes.")
+    )]
+    ConstraintViolated {
+        /// The opcode that was given the argument.
+        mnemonic: String,
+
+        /// The value of the argument, once labels are resolved.
+        value: u32,
+
+        /// A description of the constraint that was broken, e.g. `at most 7`.
+        constraint: String,
+
+        /// The span of the opcode in the source code.
+        #[label("This mnemonic")]
+        mnemonic_span: Position,
+
+        /// The span of the argument in the source code.
+        #[label("This argument")]
+        arg_span: Position,
+    },
+
+    /// A number given to an opcode doesn't fit in the bit field it's packed into.
+    #[error(
+        "'{}' packs this argument into {} bits, but {:#06x} doesn't fit in it.",
+        mnemonic,
+        width,
+        value
+    )]
+    #[diagnostic(
+        code(nand7400::errors::field_out_of_range),
+        help("Use a number less than 2 to the power of the width of the field.")
+    )]
+    FieldOutOfRange {
+        /// The opcode that was given the argument.
+        mnemonic: String,
+
+        /// The width of the field in bits.
+        width: u8,
+
+        /// The value of the argument.
+        value: u32,
+
+        /// The span of the opcode in the source code.
+        #[label("This mnemonic")]
+        mnemonic_span: Position,
+
+        /// The span of the argument in the source code.
+        #[label("This argument")]
+        arg_span: Position,
+    },
+
+    /// The target of a relative argument is too far away from the opcode.
+    #[error(
+        "'{}' can only jump 128 bytes backwards or 127 bytes forwards, but {:#06x} is {} bytes away.",
+        mnemonic,
+        target,
+        offset
+    )]
+    #[diagnostic(
+        code(nand7400::errors::relative_out_of_range),
+        help("Move the target closer, or use an opcode that takes an absolute address.")
+    )]
+    RelativeOutOfRange {
+        /// The opcode that was given the argument.
+        mnemonic: String,
+
+        /// The address being jumped to.
+        target: u32,
+
+        /// The offset from the end of the opcode to the target.
+        offset: i64,
+
+        /// The span of the opcode in the source code.
+        #[label("This mnemonic")]
+        mnemonic_span: Position,
+
+        /// The span of the argument in the source code.
+        #[label("This target")]
+        arg_span: Position,
+    },
+
+    /// An address doesn't fit in the configured address width.
+    #[error(
+        "Addresses are {} bits wide, but {:#06x} doesn't fit in that.",
+        bits,
+        value
+    )]
+    #[diagnostic(
+        code(nand7400::errors::address_out_of_range),
+        help("Use a smaller address, or check the address width in the configuration.")
+    )]
+    AddressOutOfRange {
+        /// The address.
+        value: u32,
+
+        /// The width of addresses in bits.
+        bits: u8,
+
+        /// The span of the address in the source code.
+        #[label("This address")]
+        span: Position,
+    },
+
+    /// The program goes past the end of the address space.
+    #[error(
+        "This goes past the end of the address space, which ends at {:#06x}.",
+        max
+    )]
+    #[diagnostic(
+        code(nand7400::errors::address_space_overflow),
+        help("Make the program smaller, move it to a lower address with `.org`, or check the address width in the configuration.")
+    )]
+    AddressSpaceOverflow {
+        /// The highest address there is.
+        max: u32,
+
+        /// The span of the instruction that goes past the end.
+        #[label("This instruction")]
+        span: Position,
+    },
+
+    /// A number given to `.word` doesn't fit in a word.
+    #[error("Words are {} byte(s), but {:#06x} doesn't fit in that.", size, value)]
+    #[diagnostic(
+        code(nand7400::errors::word_out_of_range),
+        help("Use a smaller number, or check the word size in the configuration.")
+    )]
+    WordOutOfRange {
+        /// The value of the argument.
+        value: u32,
+
+        /// The size of a word in bytes.
+        size: u8,
+
+        /// The span of the argument in the source code.
+        #[label("This argument")]
+        span: Position,
+    },
+
+    /// The configuration has a word size that `.word` can't encode, e.g. because it was built without being loaded or
+    /// validated.
+    #[error("Words can't be {} bytes.", size)]
+    #[diagnostic(
+        code(nand7400::errors::invalid_word_size),
+        help("Set the word size in the configuration to something from 1 to 4 bytes.")
+    )]
+    InvalidWordSize {
+        /// The word size in bytes.
+        size: u8,
+    },
+
+    /// Code or data is placed at an address that isn't in any region of the memory map.
+    #[error("{:#06x} isn't in any region of the memory map.", address)]
+    #[diagnostic(
+        code(nand7400::errors::unmapped_address),
+        help("Move the code or data into a ROM region with `.org`, or add a region for it in the configuration.")
+    )]
+    UnmappedAddress {
+        /// The address the instruction is placed at.
+        address: u32,
+
+        /// The span of the instruction.
+        #[label("This instruction")]
+        span: Position,
+    },
+
+    /// Code or data is placed in a region that isn't ROM, so it would never be burned into the chip.
+    #[error("{:#06x} is in region '{}', which isn't ROM.", address, region)]
+    #[diagnostic(
+        code(nand7400::errors::outside_rom),
+        help("Move the code or data into a ROM region with `.org`.")
+    )]
+    OutsideRom {
+        /// The address the instruction is placed at.
+        address: u32,
+
+        /// The name of the region the address is in.
+        region: String,
+
+        /// The span of the instruction.
+        #[label("This instruction")]
+        span: Position,
+    },
+
+    /// Code or data runs past the end of the region it starts in.
+    #[error(
+        "This goes past the end of region '{}', which ends at {:#06x}.",
+        region,
+        max
+    )]
+    #[diagnostic(
+        code(nand7400::errors::region_overflow),
+        help("Make the program smaller, or split it up between regions with `.org`.")
+    )]
+    RegionOverflow {
+        /// The name of the region.
+        region: String,
+
+        /// The highest address in the region.
+        max: u32,
+
+        /// The span of the instruction that goes past the end.
+        #[label("This instruction")]
+        span: Position,
+    },
+
+    /// None of the opcodes for an overloaded mnemonic take the arguments given.
+    #[error(
+        "No variant of '{}' takes these arguments. The variants are: {}.",
+        mnemonic,
+        candidates.join(", ")
+    )]
+    #[diagnostic(
+        code(nand7400::errors::no_matching_opcode),
+        help("Change the arguments to match one of the variants.")
+    )]
+    NoMatchingOpcode {
+        /// The overloaded mnemonic.
+        mnemonic: String,
+
+        /// The signatures of every variant of the mnemonic.
+        candidates: Vec<String>,
+
+        /// The span of the opcode in the source code.
+        #[label("This mnemonic")]
+        mnemonic_span: Position,
+
+        /// The span of the arguments in the source code.
+        #[label("These arguments")]
+        args_span: Position,
+    },
+
+    /// More than one of the opcodes for an overloaded mnemonic take the arguments given.
+    #[error(
+        "More than one variant of '{}' takes these arguments: {}.",
+        mnemonic,
+        candidates.join(", ")
+    )]
+    #[diagnostic(
+        code(nand7400::errors::ambiguous_opcode),
+        help("Each variant of an overloaded mnemonic needs to take different arguments. Check the configuration.")
+    )]
+    AmbiguousOpcode {
+        /// The overloaded mnemonic.
+        mnemonic: String,
+
+        /// The signatures of the variants that match.
+        candidates: Vec<String>,
+
+        /// The span of the opcode in the source code.
+        #[label("This mnemonic")]
+        mnemonic_span: Position,
+
+        /// The span of the arguments in the source code.
+        #[label("These arguments")]
+        args_span: Position,
+    },
+
     /// An opcode does not exist.
     #[error("Opcode '{}' does not exist.", mnemonic)]
     #[diagnostic(
@@ -104,6 +402,262 @@ pub enum AssemblerError {
         #[label("Here")]
         span: Position,
     },
+
+    /// A line in the body of a pseudo-instruction couldn't be parsed.
+    #[error("Pseudo-instruction '{}' can't be expanded: {}", mnemonic, message)]
+    #[diagnostic(
+        code(nand7400::errors::invalid_pseudo_body),
+        help("Fix the pseudo-instruction in the configuration. `AssemblerConfig::validate` can find the problem.")
+    )]
+    InvalidPseudoBody {
+        /// The mnemonic of the pseudo-instruction.
+        mnemonic: String,
+
+        /// The error from parsing the line.
+        message: String,
+
+        /// The span of the pseudo-instruction in the source code.
+        #[label("Used here")]
+        span: Position,
+    },
+
+    /// `.vector` is given the name of a vector that isn't in the configuration.
+    #[error("Vector '{}' does not exist.", name)]
+    #[diagnostic(
+        code(nand7400::errors::vector_dne),
+        help("Vectors have to be declared in the configuration before they can be set.")
+    )]
+    VectorDNE {
+        /// The name that was given.
+        name: String,
+
+        /// The span of the name in the source code.
+        #[label("Here")]
+        span: Position,
+    },
+
+    /// A vector or the entry point is set more than once.
+    #[error("Vector '{}' is set more than once.", name)]
+    #[diagnostic(
+        code(nand7400::errors::vector_set_twice),
+        help("Remove all but one of the `.vector` or `.entry` keywords for it.")
+    )]
+    VectorSetTwice {
+        /// The name of the vector, or `.entry` for the entry point.
+        name: String,
+
+        /// The span of the keyword that set it again.
+        #[label("Set again here")]
+        span: Position,
+    },
+
+    /// A vector in the configuration is never set.
+    #[error("Vector '{}' at {:#06X} is never set.", name, address)]
+    #[diagnostic(
+        code(nand7400::errors::missing_vector),
+        help("Set it with `.vector`, or with `.entry` if it holds the entry point.")
+    )]
+    MissingVector {
+        /// The name of the vector.
+        name: String,
+
+        /// The address of the vector.
+        address: u32,
+    },
+
+    /// Code or data is placed where a vector goes.
+    #[error("Vector '{}' at {:#06X} overlaps the program.", name, address)]
+    #[diagnostic(
+        code(nand7400::errors::vector_overlaps_code),
+        help("Move the code or data away from the vector table with `.org`.")
+    )]
+    VectorOverlapsCode {
+        /// The name of the vector.
+        name: String,
+
+        /// The address of the vector.
+        address: u32,
+
+        /// The span of the instruction placed where the vector goes.
+        #[label("This is in the way")]
+        span: Position,
+    },
+
+    /// An expression divides by zero, or takes the remainder of dividing by zero.
+    #[error("Division by zero.")]
+    #[diagnostic(code(nand7400::errors::division_by_zero))]
+    DivisionByZero {
+        /// The span of the zero in the source code.
+        #[label("This is zero")]
+        span: Position,
+    },
+
+    /// The condition of an `.assert` keyword is 0.
+    #[error("Assertion failed: {}", message)]
+    #[diagnostic(code(nand7400::errors::assertion_failed))]
+    AssertionFailed {
+        /// The message given with the assertion.
+        message: String,
+
+        /// The span of the assertion in the source code.
+        #[label("This assertion")]
+        span: Position,
+    },
+
+    /// An `.error` keyword is assembled.
+    #[error("{}", message)]
+    #[diagnostic(code(nand7400::errors::user_error))]
+    UserError {
+        /// The message given with the keyword.
+        message: String,
+
+        /// The span of the keyword in the source code.
+        #[label("Here")]
+        span: Position,
+    },
+
+    /// `.checksum` is given an algorithm that doesn't exist.
+    #[error("Checksum algorithm '{}' does not exist.", name)]
+    #[diagnostic(
+        code(nand7400::errors::checksum_algorithm_dne),
+        help("The checksum algorithms are 'sum8', 'xor8', 'crc8', and 'crc16'.")
+    )]
+    ChecksumAlgorithmDNE {
+        /// The algorithm that was given.
+        name: String,
+
+        /// The span of the algorithm in the source code.
+        #[label("Here")]
+        span: Position,
+    },
+
+    /// The range `.checksum` is given ends before it starts.
+    #[error("Checksum range {:#06X} to {:#06X} ends before it starts.", start, end)]
+    #[diagnostic(
+        code(nand7400::errors::invalid_checksum_range),
+        help("The range includes the start address and not the end address, so the end can't be before the start.")
+    )]
+    InvalidChecksumRange {
+        /// The start of the range.
+        start: u32,
+
+        /// The end of the range.
+        end: u32,
+
+        /// The span of the range in the source code.
+        #[label("This range")]
+        span: Position,
+    },
+
+    /// A checksum is placed where code, data, a vector, or another checksum already is.
+    #[error("Checksum at {:#06X} overlaps the program.", address)]
+    #[diagnostic(
+        code(nand7400::errors::checksum_overlap),
+        help("Put the checksum somewhere the program doesn't use.")
+    )]
+    ChecksumOverlap {
+        /// The address of the checksum.
+        address: u32,
+
+        /// The span of the `.checksum` keyword in the source code.
+        #[label("The checksum")]
+        checksum_span: Position,
+
+        /// The span of the instruction placed where the checksum goes.
+        #[label("This is in the way")]
+        span: Position,
+    },
+
+    /// Checksums cover each other's locations, so there's no order they can be filled in.
+    #[error(
+        "Checksum at {:#06X} covers the checksum at {:#06X}, which depends on it.",
+        address,
+        other
+    )]
+    #[diagnostic(
+        code(nand7400::errors::checksum_cycle),
+        help("A checksum can cover another checksum, as long as that checksum doesn't cover it back.")
+    )]
+    ChecksumCycle {
+        /// The address of the checksum.
+        address: u32,
+
+        /// The address of the checksum it covers.
+        other: u32,
+
+        /// The span of the `.checksum` keyword in the source code.
+        #[label("This checksum")]
+        checksum_span: Position,
+
+        /// The span of the `.checksum` keyword of the checksum it covers.
+        #[label("covers this one")]
+        span: Position,
+    },
+
+    /// There was an error in an instruction that came from one of the iterations of a `.rept` or `.for` block.
+    #[error("In iteration {} of the block: {}", iteration, source)]
+    #[diagnostic(code(nand7400::errors::in_iteration))]
+    InIteration {
+        /// The iteration the instruction came from, starting at 0.
+        iteration: u32,
+
+        /// The error itself.
+        #[diagnostic_source]
+        source: Box<AssemblerError>,
+
+        /// The span of the instruction in the source code.
+        #[label("Here")]
+        span: Position,
+    },
+}
+
+/// The type used to report problems that don't stop the code from being assembled.
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error, Diagnostic)]
+pub enum AssemblerWarning {
+    /// An opcode that's marked as deprecated in the configuration is used.
+    #[error("Opcode '{}' is deprecated.", mnemonic)]
+    #[diagnostic(
+        code(nand7400::warnings::deprecated_opcode),
+        severity(Warning),
+        help("Check the description of the opcode for what to use instead.")
+    )]
+    DeprecatedOpcode {
+        /// The mnemonic the opcode was written with.
+        mnemonic: String,
+
+        /// The span of the mnemonic in the source code.
+        #[label("Here")]
+        span: Position,
+    },
+
+    /// A `.warning` keyword is assembled.
+    #[error("{}", message)]
+    #[diagnostic(code(nand7400::warnings::user_warning), severity(Warning))]
+    UserWarning {
+        /// The message given with the keyword.
+        message: String,
+
+        /// The span of the keyword in the source code.
+        #[label("Here")]
+        span: Position,
+    },
+}
+
+impl AssemblerWarning {
+    /// Gets the span of the warning in the source code.
+    pub fn span(&self) -> Position {
+        match self {
+            Self::DeprecatedOpcode { span, .. } | Self::UserWarning { span, .. } => *span,
+        }
+    }
+}
+
+/// Lets the error wrapped by `AssemblerError::InIteration` be shown as the diagnostic it is, with its own code and
+/// labels.
+impl Borrow<dyn Diagnostic> for Box<AssemblerError> {
+    fn borrow(&self) -> &(dyn Diagnostic + 'static) {
+        self.as_ref()
+    }
 }
 
 /// Public API for AssemblerError.
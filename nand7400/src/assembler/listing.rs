@@ -0,0 +1,97 @@
+use super::position::{Position, SourceIndex};
+use core::fmt;
+
+/// A listing of an assembled program, which shows each instruction next to its address and the bytes it assembled
+/// into. Pseudo-instructions are shown with what they expanded to underneath.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Listing {
+    /// The lines of the listing, in the order of the source code.
+    lines: Vec<ListingLine>,
+}
+
+/// A single line of a listing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListingLine {
+    /// The address the instruction was placed at.
+    pub address: u32,
+
+    /// The bytes the instruction assembled into. This is empty for labels, keywords that don't write anything, and
+    /// pseudo-instructions (the instructions they expanded to have the bytes instead).
+    pub bytes: Vec<u8>,
+
+    /// The line the instruction is on in the source code, starting at 1.
+    pub line: usize,
+
+    /// The text of the instruction.
+    pub text: String,
+
+    /// Whether this is one of the instructions that a pseudo-instruction expanded to.
+    pub expanded: bool,
+}
+
+/// Public API for listings.
+impl Listing {
+    /// Creates a new, empty listing.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a line to the listing for the instruction at `span` in `source`. If `expansion` is given, the line is
+    /// one of the instructions a pseudo-instruction expanded to, and that's its text.
+    pub fn push(
+        &mut self,
+        source: &SourceIndex,
+        address: u32,
+        bytes: Vec<u8>,
+        span: Position,
+        expansion: Option<&str>,
+    ) {
+        self.lines.push(ListingLine {
+            address,
+            bytes,
+            line: source.line(span),
+            text: expansion.unwrap_or(source.text(span)).to_string(),
+            expanded: expansion.is_some(),
+        });
+    }
+
+    /// Iterates over the lines of the listing.
+    pub fn lines(&self) -> impl Iterator<Item = &ListingLine> {
+        self.lines.iter()
+    }
+}
+
+impl fmt::Display for Listing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Line the text up after the longest run of bytes.
+        let bytes_width = self
+            .lines
+            .iter()
+            .map(|line| line.bytes.len() * 3)
+            .max()
+            .unwrap_or(0);
+
+        for line in &self.lines {
+            let bytes = line
+                .bytes
+                .iter()
+                .map(|byte| format!("{:02X}", byte))
+                .collect::<Vec<_>>()
+                .join(" ");
+            let indent = if line.expanded { "    " } else { "" };
+
+            writeln!(
+                f,
+                "{:>5}  {:04X}  {:<bytes_width$}{}{}",
+                line.line,
+                line.address,
+                bytes,
+                indent,
+                line.text,
+                bytes_width = bytes_width,
+            )?;
+        }
+
+        Ok(())
+    }
+}
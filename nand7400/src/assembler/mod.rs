@@ -1,33 +1,62 @@
+pub mod checksum;
 pub mod config;
 pub mod errors;
+pub mod listing;
 pub mod parser;
 pub mod position;
+pub mod source_map;
 
 mod tests;
 
-use std::collections::HashMap;
+use std::{collections::HashMap, ops::Range};
 
-use crate::assembler::config::OpcodeArg;
+use crate::assembler::config::{Opcode, OpcodeArg};
+
+use checksum::ChecksumAlgorithm;
 
 use self::parser::{
-    ast::{Argument, ArgumentKind, Ast, InstructionKind, Keyword, Label},
+    ast::{
+        Argument, ArgumentKind, Ast, EvaluationError, Expression, Instruction, InstructionKind,
+        Keyword, Label, MessageKind,
+    },
     Parser,
 };
-use config::AssemblerConfig;
-use errors::AssemblerError;
+use config::{errors::ConfigError, regions::RegionKind, AssemblerConfig, Endianness};
+use errors::{AssemblerError, AssemblerWarning};
+use listing::Listing;
 use num_traits::{FromPrimitive, Num, ToBytes, ToPrimitive};
+use position::{Position, SourceIndex};
+use source_map::{SourceMap, SourceMapEntryKind};
 
 /// The main assember structure to be used.
 pub struct Assembler {
     /// The configuration for the assembler.
     config: AssemblerConfig,
+
+    /// The warnings from the last time something was assembled.
+    warnings: Vec<AssemblerWarning>,
+
+    /// The entry point from the last time something was assembled.
+    entry_point: Option<u32>,
 }
 
 /// Public API for the assembler.
 impl Assembler {
     /// Create a new assembler with the given configuration.
     pub fn new(config: AssemblerConfig) -> Self {
-        Self { config }
+        Self {
+            config,
+            warnings: vec![],
+            entry_point: None,
+        }
+    }
+
+    /// Create a new assembler with the given configuration, if the configuration is valid (see
+    /// `AssemblerConfig::validate`).
+    pub fn try_new(config: AssemblerConfig) -> Result<Self, Vec<ConfigError>> {
+        config.validate()?;
+
+        Ok(Self::new(config))
     }
 
     /// Replaces the configuration of the assembler with the given one.
@@ -35,6 +64,27 @@ impl Assembler {
         self.config = config;
     }
 
+    /// Replaces the configuration of the assembler with the given one, if the configuration is valid (see
+    /// `AssemblerConfig::validate`). Otherwise, the configuration is left as-is.
+    pub fn try_set_config(&mut self, config: AssemblerConfig) -> Result<(), Vec<ConfigError>> {
+        config.validate()?;
+        self.set_config(config);
+
+        Ok(())
+    }
+
+    /// Gets the warnings from the last time something was assembled successfully, e.g. for using deprecated opcodes.
+    /// These are cleared whenever something new is assembled.
+    pub fn warnings(&self) -> &[AssemblerWarning] {
+        &self.warnings
+    }
+
+    /// Gets the entry point set with `.entry` the last time something was assembled successfully, if there was one.
+    /// If the configuration has a reset vector, it holds this address.
+    pub fn entry_point(&self) -> Option<u32> {
+        self.entry_point
+    }
+
     /// Assembles the given assembly code into binary.
     pub fn assemble(&mut self, source: &str) -> Result<Vec<u8>, AssemblerError> {
         Ok(self.assemble_with_ast(source)?.0)
@@ -42,126 +92,1173 @@ impl Assembler {
 
     /// Assembles the given assembly code into binary and associated AST.
     pub fn assemble_with_ast(&mut self, source: &str) -> Result<(Vec<u8>, Ast), AssemblerError> {
-        let ast = Parser::new(source)?.parse()?; // Parse the source into an AST.
+        let (binary, ast, _) = self.assemble_with_source_map(source)?;
+
+        Ok((binary, ast))
+    }
+
+    /// Assembles the given assembly code into binary, the associated AST, and a source map from addresses in the
+    /// binary back to the instructions that produced them.
+    pub fn assemble_with_source_map(
+        &mut self,
+        source: &str,
+    ) -> Result<(Vec<u8>, Ast, SourceMap), AssemblerError> {
+        let (binary, ast, source_map, _) = self.assemble_all(source)?;
+
+        Ok((binary, ast, source_map))
+    }
+
+    /// Assembles the given assembly code into binary and a listing of where each instruction ended up.
+    pub fn assemble_with_listing(
+        &mut self,
+        source: &str,
+    ) -> Result<(Vec<u8>, Listing), AssemblerError> {
+        let (binary, _, _, listing) = self.assemble_all(source)?;
+
+        Ok((binary, listing))
+    }
+}
+
+impl Assembler {
+    /// Assembles the given assembly code into binary, the associated AST, a source map, and a listing.
+    fn assemble_all(
+        &mut self,
+        source: &str,
+    ) -> Result<(Vec<u8>, Ast, SourceMap, Listing), AssemblerError> {
+        self.warnings.clear();
+        self.entry_point = None;
+
+        // Words of any other size can't be encoded, and would throw off the layout.
+        if !(1..=4).contains(&self.config.word_size) {
+            return Err(AssemblerError::InvalidWordSize {
+                size: self.config.word_size,
+            });
+        }
+
+        let mut warnings = vec![]; // The warnings, which are only kept if the code assembles.
+        let mut ast = Parser::with_registers(source, &self.config.registers)?.parse()?; // Parse the source into an AST.
+        let index = SourceIndex::new(source); // Where each line and character starts, for slicing out text.
+
+        // Pseudo-instructions are expanded first, so that what they expand to is laid out like everything else.
+        ast.instructions = self.expand_pseudos(&index, ast.instructions)?;
+
+        // The parser doesn't know how big each opcode is, so the symbol table is re-done here with the configuration.
+        ast.symbols = self.layout(&ast)?;
+
         let mut next_mem_location = 0; // The next memory location to write to.
         let mut binary = vec![]; // The binary to write to.
+        let mut source_map = SourceMap::new(); // The map from addresses back to instructions.
+        let mut listing = Listing::new(); // The listing of where each instruction ended up.
+        let mut last_pseudo = None; // The span of the last pseudo-instruction listed.
+        let mut vectors = HashMap::new(); // The address and span that each vector is set to.
+        let mut checksums = vec![]; // The checksums to fill in, in the order they're written.
 
-        for instruction in &ast.instructions {
-            match &instruction.kind {
-                // Skip labels, as they;ve already been loaded into the symbol table.
-                InstructionKind::Label(_) => continue,
+        let mut current = None; // The instruction being assembled, so errors can say which iteration it's from.
+        let mut assemble_instructions = || -> Result<(), AssemblerError> {
+            for instruction in &ast.instructions {
+                current = Some(instruction);
 
-                // Execute the keywords as they come in.
-                InstructionKind::Keyword { keyword, arguments } => match keyword {
-                    // Set the memory location to the 1st argument.
-                    Keyword::Org => {
-                        // Make sure there's only 1 argument.
-                        if arguments.len() != 1 {
-                            return Err(AssemblerError::WrongNumArgs {
-                                mnemonic: keyword.to_string(),
-                                expected: 1,
-                                given: arguments.len() as u16,
-                                mnemonic_span: instruction.token_span,
-                                args_span: arguments
-                                    .iter()
-                                    .map(|arg| arg.span)
-                                    .fold(instruction.token_span, |acc, span| acc.join(&span)),
-                            });
+                // Pseudo-instructions are listed before the first instruction they expand to.
+                if instruction.expansion.is_some()
+                    && last_pseudo != Some(instruction.instruction_span)
+                {
+                    listing.push(
+                        &index,
+                        next_mem_location as u32,
+                        vec![],
+                        instruction.instruction_span,
+                        None,
+                    );
+                    last_pseudo = Some(instruction.instruction_span);
+                }
+
+                let (bytes, kind) = match &instruction.kind {
+                    // Skip labels, as they've already been loaded into the symbol table.
+                    InstructionKind::Label(_) => {
+                        listing.push(
+                            &index,
+                            next_mem_location as u32,
+                            vec![],
+                            instruction.instruction_span,
+                            instruction.expansion.as_deref(),
+                        );
+                        continue;
+                    }
+
+                    // Execute the keywords as they come in.
+                    InstructionKind::Keyword { keyword, arguments } => match keyword {
+                        // Set the memory location to the 1st argument.
+                        Keyword::Org => {
+                            next_mem_location =
+                                org_address(&self.config, &ast.symbols, instruction, arguments)?
+                                    as usize;
+                            listing.push(
+                                &index,
+                                next_mem_location as u32,
+                                vec![],
+                                instruction.instruction_span,
+                                instruction.expansion.as_deref(),
+                            );
+                            continue;
                         }
 
-                        let arg = &arguments[0]; // This is safe because we already checked the length.
+                        // Set the next byte(s) to the arguments.
+                        Keyword::Byte => {
+                            let mut bytes = vec![];
 
-                        // Now adjust the memory location based on the argument.
-                        next_mem_location = decode_arg_u16(&ast.symbols, arg)? as usize;
-                    }
+                            for arg in arguments {
+                                bytes.extend(decode_arg_bytes(&self.config, &ast.symbols, arg)?);
+                            }
 
-                    // Set the next byte(s) to the arguments.
-                    Keyword::Byte => {
-                        let mut bytes = vec![];
+                            (bytes, SourceMapEntryKind::Data)
+                        }
+
+                        // Set the next word(s) to the arguments.
+                        Keyword::Word => {
+                            let mut bytes = vec![];
 
-                        for arg in arguments {
-                            bytes.extend(decode_arg_bytes(&ast.symbols, arg)?);
+                            for arg in arguments {
+                                bytes.extend(encode_word(&self.config, &ast.symbols, arg)?);
+                            }
+
+                            (bytes, SourceMapEntryKind::Data)
                         }
 
-                        for (i, byte) in bytes.iter().enumerate() {
-                            binary.insert(next_mem_location + i, *byte);
+                        // Remember what the vector is set to, so that the vector table can be filled in at the end.
+                        Keyword::Entry | Keyword::Vector => {
+                            let (name, value) = vector_keyword(
+                                &self.config,
+                                &ast.symbols,
+                                instruction,
+                                *keyword,
+                                arguments,
+                            )?;
+
+                            if vectors.contains_key(&name) {
+                                return Err(AssemblerError::VectorSetTwice {
+                                    name,
+                                    span: instruction.instruction_span,
+                                });
+                            }
+
+                            vectors.insert(name, (value, instruction));
+                            continue;
                         }
 
-                        next_mem_location += bytes.len();
+                        // Remember the checksum, so that it can be worked out once everything else is in place.
+                        Keyword::Checksum => {
+                            let (address, algorithm, range) = checksum_keyword(
+                                &self.config,
+                                &ast.symbols,
+                                instruction,
+                                arguments,
+                            )?;
+
+                            checksums.push((address, algorithm, range, instruction));
+                            continue;
+                        }
+                    },
+
+                    // Check assertions and give messages now that every label has an address.
+                    InstructionKind::Message { kind, message } => {
+                        let span = instruction.instruction_span;
+
+                        match kind {
+                            MessageKind::Assert(condition) => {
+                                if evaluate_expression(&ast.symbols, condition)? == 0 {
+                                    return Err(AssemblerError::AssertionFailed {
+                                        message: message.clone(),
+                                        span,
+                                    });
+                                }
+                            }
+
+                            MessageKind::Error => {
+                                return Err(AssemblerError::UserError {
+                                    message: message.clone(),
+                                    span,
+                                })
+                            }
+
+                            MessageKind::Warning => warnings.push(AssemblerWarning::UserWarning {
+                                message: message.clone(),
+                                span,
+                            }),
+                        }
+
+                        listing.push(
+                            &index,
+                            next_mem_location as u32,
+                            vec![],
+                            span,
+                            instruction.expansion.as_deref(),
+                        );
+                        continue;
+                    }
+
+                    InstructionKind::Opcode {
+                        mnemonic,
+                        arguments,
+                    } => {
+                        // Get the opcode from the configuration, picking the right one if the mnemonic is overloaded.
+                        let opcode = self.select_opcode(mnemonic, instruction, arguments)?;
+
+                        if opcode.deprecated {
+                            warnings.push(AssemblerWarning::DeprecatedOpcode {
+                                mnemonic: mnemonic.clone(),
+                                span: instruction.token_span,
+                            });
+                        }
+
+                        // Now encode the opcode and its arguments into bytes.
+                        let bytes = encode_opcode(
+                            &self.config,
+                            opcode,
+                            instruction,
+                            arguments,
+                            &ast.symbols,
+                            next_mem_location as u32,
+                        )?;
+
+                        (bytes, SourceMapEntryKind::Code)
+                    }
+                };
+
+                check_region(
+                    &self.config,
+                    next_mem_location as u32,
+                    bytes.len() as u32,
+                    instruction.instruction_span,
+                )?;
+
+                // Adjust the binary buffer if the bytes go out-of-range, and then write them in.
+                if next_mem_location + bytes.len() > binary.len() {
+                    binary.resize(next_mem_location + bytes.len(), 0);
+                }
+
+                binary[next_mem_location..next_mem_location + bytes.len()].copy_from_slice(&bytes);
+
+                source_map.insert(
+                    &index,
+                    next_mem_location as u32,
+                    bytes.len() as u32,
+                    instruction.instruction_span,
+                    kind,
+                );
+                listing.push(
+                    &index,
+                    next_mem_location as u32,
+                    bytes.clone(),
+                    instruction.instruction_span,
+                    instruction.expansion.as_deref(),
+                );
+
+                // Adjust the memory location.
+                next_mem_location += bytes.len();
+
+                // Keep the binary as long as the furthest location written to (or skipped over).
+                if next_mem_location > binary.len() {
+                    binary.resize(next_mem_location, 0);
+                }
+            }
+
+            Ok(())
+        };
+
+        assemble_instructions().map_err(|err| in_iteration(err, current))?;
+
+        self.fill_vectors(&index, &vectors, &mut binary, &mut source_map, &mut listing)?;
+        self.fill_checksums(
+            &index,
+            &checksums,
+            &mut binary,
+            &mut source_map,
+            &mut listing,
+        )?;
+
+        let entry_name = self
+            .config
+            .entry_vector()
+            .map_or(Keyword::Entry.to_string(), |vector| vector.name.clone());
+
+        self.warnings = warnings;
+        self.entry_point = vectors.get(&entry_name).map(|(value, _)| *value);
+
+        Ok((binary, ast, source_map, listing))
+    }
+
+    /// Works out the address of every label, by going through the program and adding up the size of everything
+    /// before each label. This has to be done before anything is encoded, so that labels can be used before they're
+    /// defined. This is also where anything going past the end of the address space is caught.
+    fn layout(&self, ast: &Ast) -> Result<HashMap<Label, u32>, AssemblerError> {
+        let address_bytes = self.config.address_bytes();
+        let mut symbols = HashMap::new();
+        let mut next_mem_location = 0;
+
+        for instruction in &ast.instructions {
+            let in_iteration = |err| in_iteration(err, Some(instruction));
+
+            match &instruction.kind {
+                InstructionKind::Label(label) => {
+                    symbols.insert(label.clone(), next_mem_location as u32);
+                }
+
+                InstructionKind::Keyword { keyword, arguments } => match keyword {
+                    Keyword::Org => {
+                        next_mem_location =
+                            org_address(&self.config, &symbols, instruction, arguments)
+                                .map_err(in_iteration)? as usize;
                     }
+
+                    Keyword::Byte => {
+                        next_mem_location += arguments
+                            .iter()
+                            .map(|arg| match &arg.base().kind {
+                                ArgumentKind::Label(_) => address_bytes as usize,
+                                _ => decode_arg_bytes(&self.config, &symbols, arg)
+                                    .map_or(0, |bytes| bytes.len()),
+                            })
+                            .sum::<usize>();
+                    }
+
+                    Keyword::Word => {
+                        next_mem_location += arguments.len() * self.config.word_size as usize;
+                    }
+
+                    Keyword::Entry | Keyword::Vector | Keyword::Checksum => {}
                 },
 
+                InstructionKind::Message { .. } => {}
+
                 InstructionKind::Opcode {
                     mnemonic,
                     arguments,
                 } => {
-                    // Get the opcode from the configuration.
-                    let opcode = self.config.get_opcode(mnemonic).ok_or_else(|| {
-                        AssemblerError::OpcodeDNE {
-                            mnemonic: mnemonic.clone(),
-                            span: instruction.token_span,
-                        }
+                    next_mem_location += self
+                        .select_opcode(mnemonic, instruction, arguments)
+                        .map_err(in_iteration)?
+                        .size(address_bytes) as usize;
+                }
+            }
+
+            // The location after the last byte can be one past the highest address, but no further.
+            if next_mem_location > self.config.max_address() as usize + 1 {
+                return Err(in_iteration(AssemblerError::AddressSpaceOverflow {
+                    max: self.config.max_address(),
+                    span: instruction.instruction_span,
+                }));
+            }
+        }
+
+        Ok(symbols)
+    }
+
+    /// Writes the address each vector in the configuration is set to into the vector table, given the address and
+    /// the `.entry` or `.vector` keyword that each one was set with. Every vector has to be set, and none of them can
+    /// be where the program already put something.
+    fn fill_vectors(
+        &self,
+        source: &SourceIndex,
+        vectors: &HashMap<String, (u32, &Instruction)>,
+        binary: &mut Vec<u8>,
+        source_map: &mut SourceMap,
+        listing: &mut Listing,
+    ) -> Result<(), AssemblerError> {
+        let address_bytes = self.config.address_bytes();
+
+        for vector in &self.config.vectors {
+            let (value, instruction) =
+                vectors
+                    .get(&vector.name)
+                    .ok_or_else(|| AssemblerError::MissingVector {
+                        name: vector.name.clone(),
+                        address: vector.address,
                     })?;
 
-                    // Make sure the number of arguments is correct.
-                    if opcode.args.len() != instruction.args_len_bytes() {
-                        return Err(AssemblerError::WrongNumArgs {
-                            mnemonic: mnemonic.clone(),
-                            expected: opcode.args.len() as u16,
-                            given: arguments.len() as u16,
-                            mnemonic_span: instruction.token_span,
-                            args_span: arguments
-                                .iter()
-                                .map(|arg| arg.span)
-                                .fold(instruction.token_span, |acc, span| acc.join(&span)),
-                        });
-                    }
+            if let Some(entry) = source_map.entries().find(|entry| {
+                (entry.address as u64) < vector.end(address_bytes)
+                    && (vector.address as u64) < entry.address as u64 + entry.len as u64
+            }) {
+                return Err(AssemblerError::VectorOverlapsCode {
+                    name: vector.name.clone(),
+                    address: vector.address,
+                    span: entry.span,
+                });
+            }
 
-                    // Now decode the arguments into bytes.
-                    let mut bytes = vec![opcode.binary];
+            let bytes = self.config.encode_bytes(*value, address_bytes);
+            let start = vector.address as usize;
 
-                    for (arg, arg_type) in arguments.iter().zip(opcode.args.iter()) {
-                        let given_arg_type = OpcodeArg::from(arg);
+            check_region(
+                &self.config,
+                vector.address,
+                bytes.len() as u32,
+                instruction.instruction_span,
+            )?;
 
-                        // Check the argument type against the opcode.
-                        if given_arg_type != *arg_type {
-                            return Err(AssemblerError::WrongArgType {
-                                mnemonic: mnemonic.clone(),
-                                expected: *arg_type,
-                                given: given_arg_type,
-                                mnemonic_span: instruction.token_span,
-                                arg_span: arg.span,
-                            });
+            if start + bytes.len() > binary.len() {
+                binary.resize(start + bytes.len(), 0);
+            }
+
+            binary[start..start + bytes.len()].copy_from_slice(&bytes);
+
+            source_map.insert(
+                source,
+                vector.address,
+                bytes.len() as u32,
+                instruction.instruction_span,
+                SourceMapEntryKind::Data,
+            );
+            listing.push(
+                source,
+                vector.address,
+                bytes,
+                instruction.instruction_span,
+                instruction.expansion.as_deref(),
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Writes each checksum into the binary at its address, given the algorithm and range of addresses (which includes
+    /// the start and not the end) of each one. This is done last, so that the checksums are of the finished binary,
+    /// where anything past the end counts as 0. A checksum's own bytes are left out of its range, and it can't be
+    /// where anything else already is, including other checksums. A checksum that covers other checksums is filled
+    /// in after them, so checksums can't cover each other.
+    fn fill_checksums(
+        &self,
+        source: &SourceIndex,
+        checksums: &[(u32, ChecksumAlgorithm, Range<u32>, &Instruction)],
+        binary: &mut Vec<u8>,
+        source_map: &mut SourceMap,
+        listing: &mut Listing,
+    ) -> Result<(), AssemblerError> {
+        let location = |(address, algorithm, ..): &(u32, ChecksumAlgorithm, _, _)| {
+            *address..address + algorithm.width() as u32
+        };
+        let mut remaining = (0..checksums.len()).collect::<Vec<_>>(); // The checksums that aren't filled in yet.
+
+        while !remaining.is_empty() {
+            // The first checksum left that covers the checksum at index `i`, other than itself.
+            let covered = |i: usize| {
+                remaining.iter().copied().find(|&j| {
+                    let (range, other) = (&checksums[i].2, location(&checksums[j]));
+
+                    i != j && range.start < other.end && other.start < range.end
+                })
+            };
+
+            // Fill in a checksum that doesn't cover any that are left. If every one does, they cover each other.
+            let Some(next) = remaining.iter().position(|&i| covered(i).is_none()) else {
+                let (i, j) = (remaining[0], covered(remaining[0]).unwrap_or(remaining[0]));
+                let err = AssemblerError::ChecksumCycle {
+                    address: checksums[i].0,
+                    other: checksums[j].0,
+                    checksum_span: checksums[i].3.instruction_span,
+                    span: checksums[j].3.instruction_span,
+                };
+
+                return Err(in_iteration(err, Some(checksums[i].3)));
+            };
+
+            let checksum @ (address, algorithm, range, instruction) =
+                &checksums[remaining.remove(next)];
+            let location = location(checksum);
+
+            if let Some(entry) = source_map.entries().find(|entry| {
+                entry.address < location.end && location.start < entry.address + entry.len
+            }) {
+                let err = AssemblerError::ChecksumOverlap {
+                    address: *address,
+                    checksum_span: instruction.instruction_span,
+                    span: entry.span,
+                };
+
+                return Err(in_iteration(err, Some(instruction)));
+            }
+
+            check_region(
+                &self.config,
+                *address,
+                algorithm.width() as u32,
+                instruction.instruction_span,
+            )
+            .map_err(|err| in_iteration(err, Some(instruction)))?;
+
+            let value = algorithm.compute(
+                range
+                    .clone()
+                    .filter(|address| !location.contains(address))
+                    .map(|address| binary.get(address as usize).copied().unwrap_or(0)),
+            );
+            let bytes = self.config.encode_bytes(value, algorithm.width());
+            let start = *address as usize;
+
+            if start + bytes.len() > binary.len() {
+                binary.resize(start + bytes.len(), 0);
+            }
+
+            binary[start..start + bytes.len()].copy_from_slice(&bytes);
+
+            source_map.insert(
+                source,
+                *address,
+                bytes.len() as u32,
+                instruction.instruction_span,
+                SourceMapEntryKind::Data,
+            );
+            listing.push(
+                source,
+                *address,
+                bytes,
+                instruction.instruction_span,
+                instruction.expansion.as_deref(),
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Replaces every use of a pseudo-instruction with the instructions it expands to. Everything from the body of the
+    /// pseudo-instruction gets the spans of where it was used, except for the arguments substituted in.
+    fn expand_pseudos(
+        &self,
+        source: &SourceIndex,
+        instructions: Vec<Instruction>,
+    ) -> Result<Vec<Instruction>, AssemblerError> {
+        let mut expanded = vec![];
+
+        for instruction in instructions {
+            let InstructionKind::Opcode {
+                mnemonic,
+                arguments,
+            } = &instruction.kind
+            else {
+                expanded.push(instruction);
+                continue;
+            };
+
+            let Some(pseudo) = self.config.get_pseudo(mnemonic) else {
+                expanded.push(instruction);
+                continue;
+            };
+
+            if arguments.len() != pseudo.params.len() {
+                let err = AssemblerError::WrongNumArgs {
+                    mnemonic: mnemonic.clone(),
+                    expected: pseudo.params.len() as u16,
+                    given: arguments.len() as u16,
+                    mnemonic_span: instruction.token_span,
+                    args_span: args_span(&instruction, arguments),
+                };
+
+                return Err(in_iteration(err, Some(&instruction)));
+            }
+
+            let params = pseudo
+                .params
+                .iter()
+                .map(String::as_str)
+                .zip(arguments)
+                .collect::<HashMap<_, _>>();
+
+            for line in &pseudo.body {
+                let body = SourceIndex::new(line);
+                let ast = Parser::with_registers(line, &self.config.registers)
+                    .and_then(|parser| parser.parse())
+                    .map_err(|err| AssemblerError::InvalidPseudoBody {
+                        mnemonic: mnemonic.clone(),
+                        message: err.to_string(),
+                        span: instruction.instruction_span,
+                    })
+                    .map_err(|err| in_iteration(err, Some(&instruction)))?;
+
+                for body_instruction in ast.instructions {
+                    let mut substituted = vec![]; // The spans in the line that had arguments substituted in.
+                    let substitute = |args: Vec<Argument<u32>>, substituted: &mut Vec<_>| {
+                        args.into_iter()
+                            .map(|arg| substitute_arg(arg, &params, &instruction, substituted))
+                            .collect()
+                    };
+
+                    let kind = match body_instruction.kind {
+                        InstructionKind::Opcode {
+                            mnemonic,
+                            arguments,
+                        } => InstructionKind::Opcode {
+                            mnemonic,
+                            arguments: substitute(arguments, &mut substituted),
+                        },
+                        InstructionKind::Keyword { keyword, arguments } => {
+                            InstructionKind::Keyword {
+                                keyword,
+                                arguments: substitute(arguments, &mut substituted),
+                            }
                         }
+                        label => label,
+                    };
 
-                        // Now decode the argument into bytes.
-                        bytes.extend(decode_arg_bytes(&ast.symbols, arg)?);
-                    }
+                    // The text is the line with the source of each substituted argument put in.
+                    let span = body_instruction.instruction_span;
+                    let mut text = String::new();
+                    let mut cursor = span.start as usize;
+
+                    substituted.sort_by_key(|(from, _): &(Position, Position)| from.start);
 
-                    // Now insert the bytes into the binary.
-                    for (i, byte) in bytes.iter().enumerate() {
-                        binary.insert(next_mem_location + i, *byte);
+                    for (from, to) in substituted {
+                        text.push_str(body.text(Position::new(cursor, from.start as usize)));
+                        text.push_str(source.text(to));
+                        cursor = from.end as usize;
                     }
 
-                    // Adjust the memory location.
-                    next_mem_location += bytes.len();
+                    text.push_str(body.text(Position::new(cursor, span.end as usize)));
+
+                    expanded.push(Instruction {
+                        kind,
+                        expansion: Some(text),
+                        ..instruction.clone()
+                    });
                 }
             }
+        }
+
+        Ok(expanded)
+    }
+
+    /// Picks the opcode to use for an instruction. If there's only one opcode with the mnemonic, then the arguments
+    /// are checked against it directly, so that the errors are as specific as possible. Otherwise, the mnemonic is
+    /// overloaded, and the one opcode whose arguments match is used.
+    fn select_opcode(
+        &self,
+        mnemonic: &str,
+        instruction: &Instruction,
+        arguments: &[Argument<u32>],
+    ) -> Result<&Opcode, AssemblerError> {
+        let candidates = self.config.get_opcodes(mnemonic);
+
+        match candidates.as_slice() {
+            [] => Err(AssemblerError::OpcodeDNE {
+                mnemonic: mnemonic.to_string(),
+                span: instruction.token_span,
+            }),
+
+            [opcode] => {
+                pair_args(opcode, instruction, arguments, self.config.address_bytes())?;
+                Ok(opcode)
+            }
+
+            _ => {
+                let matching = candidates
+                    .iter()
+                    .filter(|opcode| {
+                        pair_args(opcode, instruction, arguments, self.config.address_bytes())
+                            .is_ok()
+                    })
+                    .collect::<Vec<_>>();
 
-            // Adjust the binary buffer if the next location is out-of-range.
-            if next_mem_location > binary.len() {
-                binary.resize(next_mem_location, 0);
+                match matching.as_slice() {
+                    [opcode] => Ok(opcode),
+
+                    // If nothing matches, list all the candidates. If too much matches, list only the ones that do.
+                    [] => Err(AssemblerError::NoMatchingOpcode {
+                        mnemonic: mnemonic.to_string(),
+                        candidates: candidates.iter().map(|opcode| opcode.signature()).collect(),
+                        mnemonic_span: instruction.token_span,
+                        args_span: args_span(instruction, arguments),
+                    }),
+
+                    _ => Err(AssemblerError::AmbiguousOpcode {
+                        mnemonic: mnemonic.to_string(),
+                        candidates: matching.iter().map(|opcode| opcode.signature()).collect(),
+                        mnemonic_span: instruction.token_span,
+                        args_span: args_span(instruction, arguments),
+                    }),
+                }
             }
         }
+    }
+}
 
-        Ok((binary, ast))
+/// Substitutes the arguments a pseudo-instruction was given for its parameters in an argument from its body, adding
+/// the span of each parameter and the argument put in its place to `substituted`. Anything else gets the span of the
+/// pseudo-instruction.
+fn substitute_arg(
+    arg: Argument<u32>,
+    params: &HashMap<&str, &Argument<u32>>,
+    pseudo: &Instruction,
+    substituted: &mut Vec<(Position, Position)>,
+) -> Argument<u32> {
+    let kind = match arg.kind {
+        ArgumentKind::Label(label) => match params.get(label.as_str()) {
+            Some(param) => {
+                substituted.push((arg.span, param.span));
+                return (*param).clone();
+            }
+            None => ArgumentKind::Label(label),
+        },
+
+        ArgumentKind::Indirect(inner) => ArgumentKind::Indirect(Box::new(substitute_arg(
+            *inner,
+            params,
+            pseudo,
+            substituted,
+        ))),
+
+        ArgumentKind::Indexed { base, index } => ArgumentKind::Indexed {
+            base: Box::new(substitute_arg(*base, params, pseudo, substituted)),
+            index,
+        },
+
+        kind => kind,
+    };
+
+    Argument::new(kind, pseudo.instruction_span)
+}
+
+/// An argument given to an instruction, paired with the kind of argument the opcode expects in its place and the
+/// index of that kind in the opcode's arguments.
+type ArgPair<'a, 'b, T> = (&'a Argument<T>, &'b OpcodeArg, usize);
+
+/// Pairs each argument given to an instruction with the kind of argument the opcode expects in its place and the
+/// index of that kind in the opcode's arguments, checking that there are the right number of arguments and that
+/// they're the right kinds. `address_bytes` is the number of 1-byte slots that a label fills.
+fn pair_args<'a, 'b, T>(
+    opcode: &'b Opcode,
+    instruction: &Instruction,
+    arguments: &'a [Argument<T>],
+    address_bytes: u16,
+) -> Result<Vec<ArgPair<'a, 'b, T>>, AssemblerError> {
+    let mut expected = opcode.args.iter().enumerate();
+    let mut pairs = vec![];
+    let mut filled = 0; // The number of argument kinds filled by the given arguments.
+
+    for arg in arguments {
+        let arg_type = expected.next();
+
+        filled += 1;
+
+        if let Some((index, arg_type)) = arg_type {
+            pairs.push((arg, arg_type, index));
+
+            // Labels given to the 1-byte `Indirect`/`Immediate` arguments are as long as an address, so they fill
+            // that many of them.
+            if arg_type.is_single_byte_slot() && matches!(arg.kind, ArgumentKind::Label(_)) {
+                for _ in 1..address_bytes {
+                    expected.next();
+                    filled += 1;
+                }
+            }
+        }
+    }
+
+    // Make sure the number of arguments is correct.
+    if filled != opcode.args.len() {
+        return Err(AssemblerError::WrongNumArgs {
+            mnemonic: opcode.mnemonic.clone(),
+            expected: opcode.args.len() as u16,
+            given: arguments.len() as u16,
+            mnemonic_span: instruction.token_span,
+            args_span: args_span(instruction, arguments),
+        });
+    }
+
+    // Check the argument types against the opcode.
+    for (arg, arg_type, _) in &pairs {
+        if !arg_type.accepts(arg) {
+            return Err(AssemblerError::WrongArgType {
+                mnemonic: opcode.mnemonic.clone(),
+                expected: (*arg_type).clone(),
+                given: OpcodeArg::from(*arg),
+                mnemonic_span: instruction.token_span,
+                arg_span: arg.span,
+            });
+        }
+    }
+
+    Ok(pairs)
+}
+
+/// Encodes an opcode and its arguments into bytes. `address` is where the opcode is being placed, which is needed for
+/// relative arguments.
+fn encode_opcode(
+    config: &AssemblerConfig,
+    opcode: &Opcode,
+    instruction: &Instruction,
+    arguments: &[Argument<u32>],
+    symbols: &HashMap<Label, u32>,
+    address: u32,
+) -> Result<Vec<u8>, AssemblerError> {
+    let address_bytes = config.address_bytes();
+    let mut bytes = vec![opcode.binary];
+    let mut fields = vec![]; // The fields to OR in, once all the bytes are there.
+
+    let out_of_range = |arg: &Argument<u32>, arg_type: &OpcodeArg, value| {
+        Err(AssemblerError::ArgOutOfRange {
+            mnemonic: opcode.mnemonic.clone(),
+            expected: arg_type.clone(),
+            value,
+            mnemonic_span: instruction.token_span,
+            arg_span: arg.span,
+        })
+    };
+
+    for (arg, arg_type, index) in pair_args(opcode, instruction, arguments, address_bytes)? {
+        let value = decode_arg_u32(symbols, arg)?;
+
+        if let Some(constraint) = opcode.constraints.get(index) {
+            constraint
+                .check(value)
+                .map_err(|constraint| AssemblerError::ConstraintViolated {
+                    mnemonic: opcode.mnemonic.clone(),
+                    value,
+                    constraint,
+                    mnemonic_span: instruction.token_span,
+                    arg_span: arg.span,
+                })?;
+        }
+
+        match arg_type {
+            // Labels are always as long as an address.
+            OpcodeArg::Indirect | OpcodeArg::Immediate
+                if matches!(arg.kind, ArgumentKind::Label(_)) =>
+            {
+                bytes.extend(config.encode_bytes(value, address_bytes))
+            }
+
+            OpcodeArg::Indirect | OpcodeArg::Immediate | OpcodeArg::Imm8 => {
+                // Negative numbers are sign-extended to 32 bits, so they still fit in a byte.
+                if value > u8::MAX as u32 && value < 0xFFFF_FF80 {
+                    return out_of_range(arg, arg_type, value);
+                }
+
+                bytes.push(value as u8);
+            }
+
+            OpcodeArg::Imm16 => {
+                if value > u16::MAX as u32 && value < 0xFFFF_8000 {
+                    return out_of_range(arg, arg_type, value);
+                }
+
+                bytes.extend(config.encode_bytes(value, 2));
+            }
+
+            OpcodeArg::Addr
+            | OpcodeArg::AddrIndirect
+            | OpcodeArg::AddrIndexed { .. }
+            | OpcodeArg::AddrIndexedIndirect { .. }
+            | OpcodeArg::AddrIndirectIndexed { .. } => bytes.extend(
+                config.encode_bytes(check_address(config, value, arg.span)?, address_bytes),
+            ),
+
+            OpcodeArg::RegisterClass { .. } => bytes.push(value as u8),
+
+            OpcodeArg::Rel8 => {
+                let next_address = address as i64 + opcode.size(address_bytes) as i64;
+                let offset = value as i64 - next_address;
+
+                if !(i8::MIN as i64..=i8::MAX as i64).contains(&offset) {
+                    return Err(AssemblerError::RelativeOutOfRange {
+                        mnemonic: opcode.mnemonic.clone(),
+                        target: value,
+                        offset,
+                        mnemonic_span: instruction.token_span,
+                        arg_span: arg.span,
+                    });
+                }
+
+                bytes.push(offset as i8 as u8);
+            }
+
+            OpcodeArg::Field {
+                byte,
+                offset,
+                width,
+            } => {
+                // Only the bits that fit in the byte are kept, which may be fewer than the width if the configuration
+                // wasn't validated.
+                let width = (*width).min(8u8.saturating_sub(*offset));
+
+                if value >> width != 0 {
+                    return Err(AssemblerError::FieldOutOfRange {
+                        mnemonic: opcode.mnemonic.clone(),
+                        width,
+                        value,
+                        mnemonic_span: instruction.token_span,
+                        arg_span: arg.span,
+                    });
+                }
+
+                fields.push((
+                    *byte as usize,
+                    (value as u64).checked_shl(*offset as u32).unwrap_or(0),
+                ));
+            }
+        }
     }
+
+    // Fields can go past the other arguments, so make sure there's room for them.
+    bytes.resize(opcode.size(address_bytes) as usize, 0);
+
+    for (byte, bits) in fields {
+        bytes[byte] |= bits as u8;
+    }
+
+    Ok(bytes)
+}
+
+/// Encodes an argument to `.word` into a word of the configured size.
+fn encode_word(
+    config: &AssemblerConfig,
+    symbols: &HashMap<Label, u32>,
+    arg: &Argument<u32>,
+) -> Result<Vec<u8>, AssemblerError> {
+    let value = decode_arg_u32(symbols, arg)?;
+    let bits = config.word_size as u32 * 8;
+
+    // Negative numbers are sign-extended to 32 bits, so they fit if everything above the word's sign bit is set.
+    if bits < 32 && value >> bits != 0 && value >> (bits - 1) != u32::MAX >> (bits - 1) {
+        return Err(AssemblerError::WordOutOfRange {
+            value,
+            size: config.word_size,
+            span: arg.span,
+        });
+    }
+
+    Ok(config.encode_bytes(value, config.word_size as u16))
+}
+
+/// Checks that an address fits in the configured address width.
+fn check_address(
+    config: &AssemblerConfig,
+    value: u32,
+    span: Position,
+) -> Result<u32, AssemblerError> {
+    if value > config.max_address() {
+        return Err(AssemblerError::AddressOutOfRange {
+            value,
+            bits: config.address_bits,
+            span,
+        });
+    }
+
+    Ok(value)
+}
+
+/// Checks that `len` bytes of code or data placed at `address` are all in the same ROM region. Anything goes if
+/// there's no memory map.
+fn check_region(
+    config: &AssemblerConfig,
+    address: u32,
+    len: u32,
+    span: Position,
+) -> Result<(), AssemblerError> {
+    if config.regions.is_empty() || len == 0 {
+        return Ok(());
+    }
+
+    let region = config
+        .region_at(address)
+        .ok_or(AssemblerError::UnmappedAddress { address, span })?;
+
+    if region.kind != RegionKind::Rom {
+        return Err(AssemblerError::OutsideRom {
+            address,
+            region: region.name.clone(),
+            span,
+        });
+    }
+
+    if address as u64 + len as u64 > region.end() {
+        return Err(AssemblerError::RegionOverflow {
+            region: region.name.clone(),
+            max: (region.end() - 1) as u32,
+            span,
+        });
+    }
+
+    Ok(())
+}
+
+/// Works out the value of an expression now that every label has an address.
+fn evaluate_expression(
+    symbols: &HashMap<Label, u32>,
+    expression: &Expression,
+) -> Result<u32, AssemblerError> {
+    expression.evaluate(symbols).map_err(|err| match err {
+        EvaluationError::LabelDNE { name, span } => AssemblerError::LabelDNE {
+            mnemonic: name,
+            span,
+        },
+        EvaluationError::DivisionByZero { span } => AssemblerError::DivisionByZero { span },
+    })
+}
+
+/// Gets the address a `.org` keyword sets the memory location to.
+fn org_address(
+    config: &AssemblerConfig,
+    symbols: &HashMap<Label, u32>,
+    instruction: &Instruction,
+    arguments: &[Argument<u32>],
+) -> Result<u32, AssemblerError> {
+    // Make sure there's only 1 argument.
+    if arguments.len() != 1 {
+        return Err(AssemblerError::WrongNumArgs {
+            mnemonic: Keyword::Org.to_string(),
+            expected: 1,
+            given: arguments.len() as u16,
+            mnemonic_span: instruction.token_span,
+            args_span: args_span(instruction, arguments),
+        });
+    }
+
+    // This is safe because we already checked the length.
+    let arg = &arguments[0];
+
+    // The address has to be a plain number or label, as there's nothing to point through or index by.
+    if !matches!(
+        arg.kind,
+        ArgumentKind::ImmediateNumber(_) | ArgumentKind::IndirectNumber(_) | ArgumentKind::Label(_)
+    ) {
+        return Err(AssemblerError::WrongArgType {
+            mnemonic: Keyword::Org.to_string(),
+            expected: OpcodeArg::Addr,
+            given: OpcodeArg::from(arg),
+            mnemonic_span: instruction.token_span,
+            arg_span: arg.span,
+        });
+    }
+
+    check_address(config, decode_arg_u32(symbols, arg)?, arg.span)
 }
 
-/// Decodes an argument into a series of bytes. Numbers and labels are converted to little endian. However, if the trailing
-/// byte(s) is/are 0, then they are cut. This does not happen to labels, and only happens to numbers.
+/// Gets the name of the vector that a `.entry` or `.vector` keyword sets, and the address that it's set to. The
+/// entry point is named after the vector that holds it, or `.entry` if there isn't one.
+fn vector_keyword(
+    config: &AssemblerConfig,
+    symbols: &HashMap<Label, u32>,
+    instruction: &Instruction,
+    keyword: Keyword,
+    arguments: &[Argument<u32>],
+) -> Result<(String, u32), AssemblerError> {
+    let expected = if keyword == Keyword::Entry { 1 } else { 2 };
+
+    if arguments.len() != expected {
+        return Err(AssemblerError::WrongNumArgs {
+            mnemonic: keyword.to_string(),
+            expected: expected as u16,
+            given: arguments.len() as u16,
+            mnemonic_span: instruction.token_span,
+            args_span: args_span(instruction, arguments),
+        });
+    }
+
+    let name = match (&arguments[0].kind, keyword) {
+        (_, Keyword::Entry) => config
+            .entry_vector()
+            .map_or(Keyword::Entry.to_string(), |vector| vector.name.clone()),
+
+        (ArgumentKind::Label(name), _) if config.get_vector(name).is_some() => name.clone(),
+
+        (_, _) => {
+            return Err(AssemblerError::VectorDNE {
+                name: arguments[0].to_string(),
+                span: arguments[0].span,
+            })
+        }
+    };
+
+    // This is safe because we already checked the length.
+    let target = &arguments[expected - 1];
+
+    Ok((
+        name,
+        check_address(config, decode_arg_u32(symbols, target)?, target.span)?,
+    ))
+}
+
+/// Gets the address, algorithm, and range of addresses of a `.checksum` keyword, which is written as
+/// `.checksum address, algorithm, start, end`. The range includes the start and not the end.
+fn checksum_keyword(
+    config: &AssemblerConfig,
+    symbols: &HashMap<Label, u32>,
+    instruction: &Instruction,
+    arguments: &[Argument<u32>],
+) -> Result<(u32, ChecksumAlgorithm, Range<u32>), AssemblerError> {
+    let [address, algorithm, start, end] = arguments else {
+        return Err(AssemblerError::WrongNumArgs {
+            mnemonic: Keyword::Checksum.to_string(),
+            expected: 4,
+            given: arguments.len() as u16,
+            mnemonic_span: instruction.token_span,
+            args_span: args_span(instruction, arguments),
+        });
+    };
+
+    let algorithm = match &algorithm.kind {
+        ArgumentKind::Label(name) => ChecksumAlgorithm::from_name(name),
+        _ => None,
+    }
+    .ok_or_else(|| AssemblerError::ChecksumAlgorithmDNE {
+        name: algorithm.to_string(),
+        span: algorithm.span,
+    })?;
+
+    let range = check_address(config, decode_arg_u32(symbols, start)?, start.span)?
+        ..decode_arg_u32(symbols, end)?;
+
+    // The end isn't part of the range, so it can be just past the last address.
+    if range.end as u64 > config.max_address() as u64 + 1 {
+        return Err(AssemblerError::AddressOutOfRange {
+            value: range.end,
+            bits: config.address_bits,
+            span: end.span,
+        });
+    }
+
+    if range.start > range.end {
+        return Err(AssemblerError::InvalidChecksumRange {
+            start: range.start,
+            end: range.end,
+            span: start.span.join(&end.span),
+        });
+    }
+
+    Ok((
+        check_address(config, decode_arg_u32(symbols, address)?, address.span)?,
+        algorithm,
+        range,
+    ))
+}
+
+/// Wraps an error caused by an instruction that came from a `.rept` or `.for` block, so that it says which iteration
+/// of the block the instruction came from. Errors from anything else are left as they are.
+fn in_iteration(err: AssemblerError, instruction: Option<&Instruction>) -> AssemblerError {
+    match instruction.and_then(|instruction| Some((instruction.iteration?, instruction))) {
+        Some((iteration, instruction)) => AssemblerError::InIteration {
+            iteration,
+            source: Box::new(err),
+            span: instruction.instruction_span,
+        },
+        None => err,
+    }
+}
+
+/// Gets the span of an instruction's mnemonic and all of its arguments.
+fn args_span<T>(instruction: &Instruction, arguments: &[Argument<T>]) -> Position {
+    arguments
+        .iter()
+        .map(|arg| arg.span)
+        .fold(instruction.token_span, |acc, span| acc.join(&span))
+}
+
+/// Decodes an argument into a series of bytes, in the configured byte order. Labels are as long as an address. Numbers
+/// have any leading 0 bytes cut, except for the last one. Numbers with the top bit set are negative (e.g. `-1`), so
+/// they have any leading 0xFF bytes cut instead, as long as the sign is kept (so `-1` is `0xFF`, and `-200` is
+/// `0xFF38`).
 pub fn decode_arg_bytes<T>(
-    symbol_table: &HashMap<Label, u16>,
+    config: &AssemblerConfig,
+    symbol_table: &HashMap<Label, u32>,
     arg: &Argument<T>,
 ) -> Result<Vec<u8>, AssemblerError>
 where
@@ -170,37 +1267,66 @@ where
     match &arg.kind {
         ArgumentKind::ImmediateNumber(num) | ArgumentKind::IndirectNumber(num) => {
             let mut bytes = num.to_le_bytes().as_ref().to_vec();
+            let negative = bytes.last().is_some_and(|byte| byte & 0x80 != 0);
+
+            // Remove trailing 0s (or 0xFFs, if the number is negative), except for the last one. A negative number has
+            // to keep a byte with the top bit set, so that it's still negative.
+            while let [.., next, last] = bytes[..] {
+                let redundant = if negative {
+                    last == 0xFF && next & 0x80 != 0
+                } else {
+                    last == 0
+                };
+
+                if !redundant {
+                    break;
+                }
 
-            // Remove trailing 0s, except for the last one.
-            while bytes.last() == Some(&0) && bytes.len() > 1 {
                 bytes.pop();
             }
 
+            if config.endianness == Endianness::Big {
+                bytes.reverse();
+            }
+
             Ok(bytes)
         }
 
-        ArgumentKind::Label(label) => Ok(symbol_table
-            .get(label)
-            .ok_or_else(|| AssemblerError::LabelDNE {
-                mnemonic: label.clone(),
-                span: arg.span,
-            })?
-            .to_le_bytes()
-            .to_vec()),
+        ArgumentKind::Register(register) => Ok(vec![register.binary]),
+
+        ArgumentKind::Indirect(_) | ArgumentKind::Indexed { .. } => {
+            decode_arg_bytes(config, symbol_table, arg.base())
+        }
+
+        ArgumentKind::Label(label) => Ok(config.encode_bytes(
+            *symbol_table
+                .get(label)
+                .ok_or_else(|| AssemblerError::LabelDNE {
+                    mnemonic: label.clone(),
+                    span: arg.span,
+                })?,
+            config.address_bytes(),
+        )),
     }
 }
 
-/// Decodes an argument into a `u16` type.
-pub fn decode_arg_u16<T>(
-    symbol_table: &HashMap<Label, u16>,
+/// Decodes an argument into a `u32` type.
+pub fn decode_arg_u32<T>(
+    symbol_table: &HashMap<Label, u32>,
     arg: &Argument<T>,
-) -> Result<u16, AssemblerError>
+) -> Result<u32, AssemblerError>
 where
     T: Num + ToBytes + ToPrimitive + Clone + FromPrimitive,
 {
     match &arg.kind {
         ArgumentKind::ImmediateNumber(num) | ArgumentKind::IndirectNumber(num) => {
-            Ok(num.to_u16().expect("Need to catch this error!"))
+            Ok(num.to_u32().expect("Need to catch this error!"))
+        }
+
+        ArgumentKind::Register(register) => Ok(register.binary as u32),
+
+        ArgumentKind::Indirect(_) | ArgumentKind::Indexed { .. } => {
+            decode_arg_u32(symbol_table, arg.base())
         }
 
         ArgumentKind::Label(label) => {
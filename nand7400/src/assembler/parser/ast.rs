@@ -1,8 +1,9 @@
-use crate::assembler::position::Position;
+use crate::assembler::{config::Register, position::Position};
 use core::fmt;
 use std::{collections::HashMap, mem};
 
-/// The size of labels, in bytes.
+/// The size of labels, in bytes, that the parser assumes when estimating addresses. The assembler uses the address
+/// width from its configuration instead.
 pub const LABEL_SIZE: u16 = 2;
 
 /// The entire AST. This includes the set of instructions, as well as the symbol table.
@@ -12,7 +13,11 @@ pub struct Ast {
     pub instructions: Vec<Instruction>,
 
     /// The symbol table in the AST. This translates from label names to the memory address they correspond to.
-    pub symbols: HashMap<Label, u16>,
+    pub symbols: HashMap<Label, u32>,
+
+    /// The constants defined by `.struct` and `.enum` blocks (e.g. `Sprite.x` and `Sprite.size`). These are numbers
+    /// rather than addresses, and any use of one after it's defined is parsed as if the number was written instead.
+    pub constants: HashMap<Label, u32>,
 }
 
 /// An actual instruction, which contains the position and instruction kind.
@@ -27,6 +32,14 @@ pub struct Instruction {
     /// The span of the 1st token of the instruction in the source code. Essentially, this is either the
     /// span of the opcode, keyword, or label without any arguments.
     pub token_span: Position,
+
+    /// If the instruction came from expanding a pseudo-instruction, the text of the instruction it expanded to. Its
+    /// spans are then those of the pseudo-instruction that was used, so that errors point there.
+    pub expansion: Option<String>,
+
+    /// If the instruction came from a `.rept` or `.for` block, which time through the innermost block it came from,
+    /// starting at 0. This is used to say which iteration errors happened in.
+    pub iteration: Option<u32>,
 }
 
 impl Instruction {
@@ -36,10 +49,14 @@ impl Instruction {
             kind,
             instruction_span,
             token_span,
+            expansion: None,
+            iteration: None,
         }
     }
 
     /// Gets the binary length of the instruction. This is used for calculating the memory address of the next instruction.
+    /// Note that this is only an estimate for opcodes, as it assumes every number is 1 byte. The assembler works out
+    /// the real layout once it knows which opcode is being used.
     pub fn binary_len(&self) -> u16 {
         match &self.kind {
             InstructionKind::Label(_) => 0, // 0 because labels and keywords don't take up any space
@@ -47,8 +64,12 @@ impl Instruction {
                 arguments // Account for the space that labels take up (more than a single byte)
                     .iter()
                     .map(|arg| match &arg.kind {
-                        ArgumentKind::ImmediateNumber(_) | ArgumentKind::IndirectNumber(_) => 1, // 1 because arguments for opcodes are always 1 byte
-                        ArgumentKind::Label(_) => LABEL_SIZE, // LABEL_SIZE because labels can be more than 1 byte
+                        ArgumentKind::ImmediateNumber(_)
+                        | ArgumentKind::IndirectNumber(_)
+                        | ArgumentKind::Register(_) => 1, // 1 because arguments for opcodes are always 1 byte
+                        ArgumentKind::Label(_)
+                        | ArgumentKind::Indirect(_)
+                        | ArgumentKind::Indexed { .. } => LABEL_SIZE, // LABEL_SIZE because labels and addresses can be more than 1 byte
                     })
                     .sum::<u16>()
                     + 1 // +1 for the opcode itself
@@ -57,8 +78,12 @@ impl Instruction {
                 match keyword {
                     Keyword::Org => 0,  // 0 because it simply sets the memory address
                     Keyword::Byte => 1, // 1 because it sets a physical byte in memory
+                    Keyword::Word => 1, // 1 because the parser doesn't know how big words are
+                    Keyword::Entry | Keyword::Vector => 0, // 0 because vectors are filled in at the end
+                    Keyword::Checksum => 0, // 0 because checksums are filled in at the end
                 }
             }
+            InstructionKind::Message { .. } => 0, // 0 because messages are only for the person assembling
         }
     }
 }
@@ -75,8 +100,9 @@ pub enum InstructionKind {
         /// The mnemonic of the opcode.
         mnemonic: String,
 
-        /// The arguments of the opcode.
-        arguments: Vec<Argument<u8>>,
+        /// The arguments of the opcode. These are 32-bit, as what size they need to be depends on the opcode they're
+        /// given to and the address width, which the parser doesn't know about.
+        arguments: Vec<Argument<u32>>,
     },
 
     /// A keyword instruction, which is a keyword followed by a list of arguments.
@@ -85,12 +111,127 @@ pub enum InstructionKind {
         keyword: Keyword,
 
         /// The arguments of the instruction.
-        arguments: Vec<Argument<u16>>,
+        arguments: Vec<Argument<u32>>,
+    },
+
+    /// A build-time check or message (`.assert`, `.error`, or `.warning`), which the assembler works out once every
+    /// label has an address.
+    Message {
+        /// The kind of message, including the condition if it's an assertion.
+        kind: MessageKind,
+
+        /// The message given, without its quotes.
+        message: String,
+    },
+}
+
+/// The kind of build-time check or message in the assembly code.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MessageKind {
+    /// The `.assert` keyword, which stops the code from being assembled with the message if the expression is 0.
+    Assert(Expression),
+
+    /// The `.error` keyword, which always stops the code from being assembled with the message.
+    Error,
+
+    /// The `.warning` keyword, which always gives a warning with the message.
+    Warning,
+}
+
+/// An expression, which can use labels as well as numbers and constants. Labels are worked out by the assembler, so
+/// these are kept as they were written until then.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Expression {
+    /// The kind of expression.
+    pub kind: ExpressionKind,
+
+    /// The span of the expression in the source code.
+    pub span: Position,
+}
+
+/// The kind of expression.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExpressionKind {
+    /// A number, or a constant that's been replaced with its value.
+    Number(u32),
+
+    /// A label, which is replaced with its address.
+    Label(Label),
+
+    /// The negation of an expression (e.g. `-x`).
+    Negate(Box<Expression>),
+
+    /// Two expressions with an operator between them (e.g. `x + 1`).
+    Binary {
+        /// The operator.
+        operator: BinaryOperator,
+
+        /// The expression on the left.
+        lhs: Box<Expression>,
+
+        /// The expression on the right.
+        rhs: Box<Expression>,
     },
 }
 
-/// An argument to a keyword instruction or opcode. `T` is the integer type of the argument, because arguments are generally the
-/// same, but opcodes are 8-bit and keyword instructions are 16-bit.
+/// The reason an expression couldn't be worked out.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EvaluationError {
+    /// A label in the expression doesn't have an address.
+    LabelDNE {
+        /// The name of the label.
+        name: Label,
+
+        /// The span of the label in the source code.
+        span: Position,
+    },
+
+    /// Something in the expression is divided by zero, or has the remainder taken of dividing by zero.
+    DivisionByZero {
+        /// The span of the zero in the source code.
+        span: Position,
+    },
+}
+
+/// An operator between two expressions. Arithmetic works on 32-bit numbers that wrap around, and comparisons are 1 if
+/// they're true and 0 if they aren't.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BinaryOperator {
+    /// `+`
+    Add,
+
+    /// `-`
+    Subtract,
+
+    /// `*`
+    Multiply,
+
+    /// `/`
+    Divide,
+
+    /// `%`
+    Remainder,
+
+    /// `==`
+    Equal,
+
+    /// `!=`
+    NotEqual,
+
+    /// `<`
+    Less,
+
+    /// `<=`
+    LessEqual,
+
+    /// `>`
+    Greater,
+
+    /// `>=`
+    GreaterEqual,
+}
+
+/// An argument to a keyword instruction or opcode. `T` is the integer type of the argument.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Argument<T> {
     /// The kind of argument.
@@ -111,6 +252,22 @@ pub enum ArgumentKind<T> {
 
     /// A label, which is a name.
     Label(Label),
+
+    /// A register, which is a name declared in the assembler configuration.
+    Register(Register),
+
+    /// An indirect argument, written as `(addr)` or `[addr]`. The value at the address is used as the address.
+    Indirect(Box<Argument<T>>),
+
+    /// An indexed argument, written as `addr,X` with no spaces. The address is offset by the value of the index
+    /// register.
+    Indexed {
+        /// The address being indexed.
+        base: Box<Argument<T>>,
+
+        /// The register the address is indexed by.
+        index: Register,
+    },
 }
 
 /// The type of keyword instruction in the assembly code.
@@ -121,6 +278,20 @@ pub enum Keyword {
 
     /// The `.byte` keyword, which defines the byte at the current location.
     Byte,
+
+    /// The `.word` keyword, which defines a word of the configured size at the current location for each argument.
+    Word,
+
+    /// The `.entry` keyword, which sets the entry point of the program. This fills in the reset vector, if the
+    /// configuration has one.
+    Entry,
+
+    /// The `.vector` keyword, which sets the vector with the given name from the configuration to an address.
+    Vector,
+
+    /// The `.checksum` keyword, which fills in a checksum of a range of the program at an address, once everything
+    /// else has been assembled.
+    Checksum,
 }
 
 /// A label type, which is a wrapper around a string. This is mainly used for enforcing type safety.
@@ -134,8 +305,29 @@ impl Ast {
         Self {
             instructions: Vec::new(),
             symbols: HashMap::new(),
+            constants: HashMap::new(),
         }
     }
+
+    /// Exports the symbol table and constants as text, with one symbol per line. Labels come first, in order of
+    /// address, and then constants, in order of name.
+    pub fn export_symbols(&self) -> String {
+        let mut labels = self.symbols.iter().collect::<Vec<_>>();
+        let mut constants = self.constants.iter().collect::<Vec<_>>();
+
+        labels.sort_by_key(|(label, address)| (**address, (*label).clone()));
+        constants.sort();
+
+        labels
+            .into_iter()
+            .map(|(label, address)| format!("{:#06x} {}\n", address, label))
+            .chain(
+                constants
+                    .into_iter()
+                    .map(|(name, value)| format!("{:#06x} {} (constant)\n", value, name)),
+            )
+            .collect()
+    }
 }
 
 impl Instruction {
@@ -147,6 +339,47 @@ impl Instruction {
                 arguments.iter().map(|arg| arg.len_bytes()).sum()
             }
             InstructionKind::Keyword { arguments, .. } => arguments.len(),
+            InstructionKind::Message { .. } => 0,
+        }
+    }
+}
+
+impl Expression {
+    /// Works out the value of the expression, given the address of every label.
+    pub fn evaluate(&self, symbols: &HashMap<Label, u32>) -> Result<u32, EvaluationError> {
+        match &self.kind {
+            ExpressionKind::Number(value) => Ok(*value),
+            ExpressionKind::Label(label) => {
+                symbols
+                    .get(label)
+                    .copied()
+                    .ok_or_else(|| EvaluationError::LabelDNE {
+                        name: label.clone(),
+                        span: self.span,
+                    })
+            }
+            ExpressionKind::Negate(inner) => Ok(inner.evaluate(symbols)?.wrapping_neg()),
+            ExpressionKind::Binary { operator, lhs, rhs } => {
+                let lhs_value = lhs.evaluate(symbols)?;
+                let rhs_value = rhs.evaluate(symbols)?;
+
+                Ok(match operator {
+                    BinaryOperator::Add => lhs_value.wrapping_add(rhs_value),
+                    BinaryOperator::Subtract => lhs_value.wrapping_sub(rhs_value),
+                    BinaryOperator::Multiply => lhs_value.wrapping_mul(rhs_value),
+                    BinaryOperator::Divide | BinaryOperator::Remainder if rhs_value == 0 => {
+                        return Err(EvaluationError::DivisionByZero { span: rhs.span })
+                    }
+                    BinaryOperator::Divide => lhs_value / rhs_value,
+                    BinaryOperator::Remainder => lhs_value % rhs_value,
+                    BinaryOperator::Equal => (lhs_value == rhs_value) as u32,
+                    BinaryOperator::NotEqual => (lhs_value != rhs_value) as u32,
+                    BinaryOperator::Less => (lhs_value < rhs_value) as u32,
+                    BinaryOperator::LessEqual => (lhs_value <= rhs_value) as u32,
+                    BinaryOperator::Greater => (lhs_value > rhs_value) as u32,
+                    BinaryOperator::GreaterEqual => (lhs_value >= rhs_value) as u32,
+                })
+            }
         }
     }
 }
@@ -164,6 +397,19 @@ impl<T> Argument<T> {
                 mem::size_of::<T>()
             }
             ArgumentKind::Label(_) => LABEL_SIZE as usize,
+            ArgumentKind::Register(_) => 1,
+            ArgumentKind::Indirect(_) | ArgumentKind::Indexed { .. } => self.base().len_bytes(),
+        }
+    }
+
+    /// Gets the argument without any addressing modes, i.e. the number, label, or register at the bottom of any
+    /// indirection or indexing.
+    pub fn base(&self) -> &Argument<T> {
+        match &self.kind {
+            ArgumentKind::Indirect(inner) | ArgumentKind::Indexed { base: inner, .. } => {
+                inner.base()
+            }
+            _ => self,
         }
     }
 }
@@ -173,6 +419,24 @@ impl fmt::Display for Keyword {
         match self {
             Keyword::Org => write!(f, ".org"),
             Keyword::Byte => write!(f, ".byte"),
+            Keyword::Word => write!(f, ".word"),
+            Keyword::Entry => write!(f, ".entry"),
+            Keyword::Vector => write!(f, ".vector"),
+            Keyword::Checksum => write!(f, ".checksum"),
+        }
+    }
+}
+
+/// Writes the argument the way it would be written in assembly code.
+impl<T: fmt::Display> fmt::Display for Argument<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            ArgumentKind::ImmediateNumber(value) => write!(f, "#{}", value),
+            ArgumentKind::IndirectNumber(value) => write!(f, "{}", value),
+            ArgumentKind::Label(label) => write!(f, "{}", label),
+            ArgumentKind::Register(register) => write!(f, "{}", register.name),
+            ArgumentKind::Indirect(inner) => write!(f, "({})", inner),
+            ArgumentKind::Indexed { base, index } => write!(f, "{},{}", base, index.name),
         }
     }
 }
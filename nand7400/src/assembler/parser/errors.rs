@@ -1,6 +1,7 @@
 use super::lexer::token::TokenKind;
 use crate::assembler::position::Position;
 use miette::Diagnostic;
+use std::borrow::Borrow;
 
 /// The error type for parsing errors.
 #[derive(Clone, Debug, PartialEq, Eq, thiserror::Error, Diagnostic)]
@@ -76,7 +77,10 @@ pub enum ParsingError {
     #[error("Keyword '{}' does not exist.", mnemonic)]
     #[diagnostic(
         code(nand7400::errors::keyword_dne),
-        help("The only defined keywords are '.byte' and '.org'. All others are invalid.")
+        help(
+            "The defined keywords are '.org', '.byte', '.word', '.entry', '.vector', '.struct', '.enum', '.rept', \
+             '.for', '.assert', '.error', '.warning', and '.checksum'."
+        )
     )]
     KeywordDNE {
         /// The keyword that does not exist.
@@ -86,6 +90,218 @@ pub enum ParsingError {
         #[label("This keyword")]
         span: Position,
     },
+
+    /// A label has the same name as a register.
+    #[error("Label '{}' has the same name as a register.", name)]
+    #[diagnostic(
+        code(nand7400::errors::label_is_register),
+        help("Rename the label, as the name is reserved for the register.")
+    )]
+    LabelIsRegister {
+        /// The name of the label.
+        name: String,
+
+        /// The span of the label in the source code.
+        #[label("This label")]
+        span: Position,
+    },
+
+    /// A `.struct`, `.enum`, `.rept`, or `.for` block is never ended.
+    #[error("'{}' block is never ended.", keyword)]
+    #[diagnostic(
+        code(nand7400::errors::unterminated_block),
+        help("End blocks with '.endstruct', '.endenum', '.endr', or '.endfor' to match how they were started.")
+    )]
+    UnterminatedBlock {
+        /// The keyword that started the block.
+        keyword: String,
+
+        /// The span of the start of the block in the source code.
+        #[label("Started here")]
+        span: Position,
+    },
+
+    /// A label or constant is defined more than once.
+    #[error("'{}' is already defined.", name)]
+    #[diagnostic(
+        code(nand7400::errors::duplicate_symbol),
+        help("Labels, struct fields, and enum members all need different names.")
+    )]
+    DuplicateSymbol {
+        /// The name of the label or constant.
+        name: String,
+
+        /// The span of the second definition in the source code.
+        #[label("Defined again here")]
+        span: Position,
+    },
+
+    /// A struct field's size is given as the name of a struct that hasn't been defined.
+    #[error("Struct '{}' does not exist.", name)]
+    #[diagnostic(
+        code(nand7400::errors::struct_dne),
+        help("Structs have to be defined before they can be used as the size of a field.")
+    )]
+    StructDNE {
+        /// The name of the struct.
+        name: String,
+
+        /// The span of the name in the source code.
+        #[label("Here")]
+        span: Position,
+    },
+
+    /// A struct field ends, or an enum member is numbered, past the largest value a constant can have.
+    #[error("'{}' is too large for a constant.", name)]
+    #[diagnostic(
+        code(nand7400::errors::constant_overflow),
+        help("Constants are 32-bit, so struct fields can't end past 0xFFFFFFFF and enum members can't go past it.")
+    )]
+    ConstantOverflow {
+        /// The name of the struct field or enum member.
+        name: String,
+
+        /// The span of the field or member in the source code.
+        #[label("This one")]
+        span: Position,
+    },
+
+    /// A string is missing its closing quote.
+    #[error("String is never closed.")]
+    #[diagnostic(
+        code(nand7400::errors::unterminated_string),
+        help("Strings have to end with a '\"' on the same line.")
+    )]
+    UnterminatedString {
+        /// The span of the string in the source code.
+        #[label("This string")]
+        span: Position,
+    },
+
+    /// An identifier in an expression isn't a constant or loop variable.
+    #[error("'{}' is not a constant.", name)]
+    #[diagnostic(
+        code(nand7400::errors::not_constant),
+        help("Expressions can only use numbers, struct fields, enum members, and '.for' loop variables.")
+    )]
+    NotConstant {
+        /// The name of the identifier.
+        name: String,
+
+        /// The span of the identifier in the source code.
+        #[label("Here")]
+        span: Position,
+    },
+
+    /// An argument has arithmetic written straight after it, outside of braces (e.g. `table+1`).
+    #[error("Arithmetic in arguments has to be in braces.")]
+    #[diagnostic(
+        code(nand7400::errors::unbraced_expression),
+        help(
+            "Write the expression in braces (e.g. '{{SIZE + 1}}'). Expressions in arguments are worked out straight \
+             away, so they can only use numbers, struct fields, enum members, and '.for' loop variables, not labels."
+        )
+    )]
+    UnbracedExpression {
+        /// The span of the argument and the operator after it in the source code.
+        #[label("Here")]
+        span: Position,
+    },
+
+    /// An expression divides by zero, or takes the remainder of dividing by zero.
+    #[error("Division by zero.")]
+    #[diagnostic(code(nand7400::errors::division_by_zero))]
+    DivisionByZero {
+        /// The span of the zero in the source code.
+        #[label("This is zero")]
+        span: Position,
+    },
+
+    /// A `.rept` or `.for` block repeats more times than it's allowed to.
+    #[error(
+        "Blocks would repeat {} times in total, but can only repeat up to {} times.",
+        count,
+        max
+    )]
+    #[diagnostic(
+        code(nand7400::errors::too_many_iterations),
+        help("Blocks can't repeat more times than there are addresses, counting every time a nested block repeats, so check that the count isn't negative.")
+    )]
+    TooManyIterations {
+        /// The number of times blocks would repeat in total, including this one.
+        count: u64,
+
+        /// The most times blocks can repeat in total.
+        max: u64,
+
+        /// The span of the count (or range, for `.for`) in the source code.
+        #[label("This many times")]
+        span: Position,
+    },
+
+    /// A label in a `.rept` or `.for` block is defined again by a later iteration (or was already defined).
+    #[error("Label '{}' is defined more than once by a repeated block.", name)]
+    #[diagnostic(
+        code(nand7400::errors::label_in_loop),
+        help("Every iteration of a block defines its labels again, so put the label outside of the block.")
+    )]
+    LabelInLoop {
+        /// The name of the label.
+        name: String,
+
+        /// The span of the label in the source code.
+        #[label("This label")]
+        span: Position,
+    },
+
+    /// There was an error in one of the iterations of a `.rept` or `.for` block.
+    #[error("In iteration {} of the block: {}", iteration, source)]
+    #[diagnostic(code(nand7400::errors::in_iteration))]
+    InIteration {
+        /// The iteration the error happened in, starting at 0.
+        iteration: u32,
+
+        /// The error itself.
+        #[diagnostic_source]
+        source: Box<ParsingError>,
+
+        /// The span of the keyword that started the block in the source code.
+        #[label("In this block")]
+        span: Position,
+    },
+}
+
+/// Lets the error wrapped by `ParsingError::InIteration` be shown as the diagnostic it is, with its own code and labels.
+impl Borrow<dyn Diagnostic> for Box<ParsingError> {
+    fn borrow(&self) -> &(dyn Diagnostic + 'static) {
+        self.as_ref()
+    }
+}
+
+impl ParsingError {
+    /// Gets the span of the error in the source code.
+    pub fn span(&self) -> Position {
+        match self {
+            Self::UnknownCharacter { span, .. }
+            | Self::Unexpected { span, .. }
+            | Self::Overflow { span, .. }
+            | Self::Underflow { span, .. }
+            | Self::EmptyLiteral { span }
+            | Self::KeywordDNE { span, .. }
+            | Self::LabelIsRegister { span, .. }
+            | Self::UnterminatedBlock { span, .. }
+            | Self::DuplicateSymbol { span, .. }
+            | Self::StructDNE { span, .. }
+            | Self::UnterminatedString { span }
+            | Self::NotConstant { span, .. }
+            | Self::DivisionByZero { span }
+            | Self::ConstantOverflow { span, .. }
+            | Self::UnbracedExpression { span }
+            | Self::TooManyIterations { span, .. }
+            | Self::LabelInLoop { span, .. } => *span,
+            Self::InIteration { source, .. } => source.span(),
+        }
+    }
 }
 
 // Helper function to join a list of strings with commas, replace the last comma with "or", and return the result.
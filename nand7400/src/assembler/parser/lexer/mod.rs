@@ -29,6 +29,7 @@ pub struct Lexer {
     /// remove this field and just use `current_position + 1`, I decided to keep it because it
     /// 1) allows for us to "prime" the lexer without having complicated code, and 2) makes
     /// it easier to understand what is going on.
+    #[allow(clippy::doc_lazy_continuation)]
     next_position: usize,
 
     /// The current character we are lexing in the input string. This is used as a "storage
@@ -68,9 +69,30 @@ impl Lexer {
 
             ':' => self.make_one_char_token(TokenKind::Colon),
             ';' => self.make_one_char_token(TokenKind::Semicolon),
+            ',' => self.make_one_char_token(TokenKind::Comma),
+            '(' => self.make_one_char_token(TokenKind::LParen),
+            ')' => self.make_one_char_token(TokenKind::RParen),
+            '[' => self.make_one_char_token(TokenKind::LBracket),
+            ']' => self.make_one_char_token(TokenKind::RBracket),
             '#' => self.make_one_char_token(TokenKind::Hash),
             '+' => self.make_one_char_token(TokenKind::Plus),
             '-' => self.make_one_char_token(TokenKind::Minus),
+            '{' => self.make_one_char_token(TokenKind::LBrace),
+            '}' => self.make_one_char_token(TokenKind::RBrace),
+            '*' => self.make_one_char_token(TokenKind::Star),
+            '/' => self.make_one_char_token(TokenKind::Slash),
+            '%' => self.make_one_char_token(TokenKind::Percent),
+            '=' if self.peek_char() == '=' => self.make_two_char_token(TokenKind::DoubleEquals),
+            '=' => self.make_one_char_token(TokenKind::Equals),
+            '!' if self.peek_char() == '=' => self.make_two_char_token(TokenKind::NotEquals),
+            '<' if self.peek_char() == '=' => self.make_two_char_token(TokenKind::LessEquals),
+            '<' => self.make_one_char_token(TokenKind::Less),
+            '>' if self.peek_char() == '=' => self.make_two_char_token(TokenKind::GreaterEquals),
+            '>' => self.make_one_char_token(TokenKind::Greater),
+
+            // Parse strings. Returning here because we don't need to call `read_char` again, as we already did that
+            // in the `read_string` function.
+            '"' => self.read_string(),
 
             // Standard POSIX newlines
             '\n' => self.make_one_char_token(TokenKind::Newline),
@@ -211,13 +233,27 @@ impl Lexer {
         let position = self.current_position;
 
         // If we encounter a period, we are lexing a keyword, so we need to read the period.
-        if self.ch == '.' {
+        let is_keyword = self.ch == '.';
+
+        if is_keyword {
             self.read_char();
         }
 
         // Keep reading characters until we encounter a character that is not a letter, digit,
-        // or underscore.
-        while self.ch.is_alphanumeric() || self.ch == '_' {
+        // or underscore. Identifiers can also have parts separated by periods (e.g. `Sprite.x`),
+        // as long as each part starts with a letter or underscore.
+        loop {
+            while self.ch.is_alphanumeric() || self.ch == '_' {
+                self.read_char();
+            }
+
+            if is_keyword
+                || self.ch != '.'
+                || !(self.peek_char().is_alphabetic() || self.peek_char() == '_')
+            {
+                break;
+            }
+
             self.read_char();
         }
 
@@ -298,6 +334,29 @@ impl Lexer {
         self.read_while(|c| c.is_ascii_digit()).iter().collect()
     }
 
+    /// Reads a string from the input string, and returns it as a token. The literal is the string with its quotes,
+    /// so that the parser can tell if it's missing the closing quote (strings end at the end of the line if so). It
+    /// expects that `ch` is initially the opening quote. Note that if you use this function, you cannot call
+    /// `read_char` again, because this function already does that.
+    fn read_string(&mut self) -> Token {
+        let initial_position = self.current_position;
+
+        self.read_char(); // Consume the opening quote.
+        self.read_while(|c| !matches!(c, '"' | '\n' | '\r' | '\0'));
+
+        if self.ch == '"' {
+            self.read_char(); // Consume the closing quote.
+        }
+
+        Token::new(
+            TokenKind::String,
+            Position::new(initial_position, self.current_position),
+            self.input[initial_position..self.current_position]
+                .iter()
+                .collect::<String>(),
+        )
+    }
+
     /// Creates a new token from a single character. Note that you don't have to call `read_char` after
     /// calling this function, because this function already does that.
     fn make_one_char_token(&mut self, kind: TokenKind) -> Token {
@@ -311,4 +370,19 @@ impl Lexer {
 
         token
     }
+
+    /// Creates a new token from the current character and the one after it, such as `==`. Note that you don't have
+    /// to call `read_char` after calling this function, because this function already does that.
+    fn make_two_char_token(&mut self, kind: TokenKind) -> Token {
+        let token = Token::new(
+            kind,
+            Position::new(self.current_position, self.current_position + 2),
+            format!("{}{}", self.ch, self.peek_char()),
+        );
+
+        self.read_char();
+        self.read_char();
+
+        token
+    }
 }
@@ -72,7 +72,7 @@ fn lex_positions() {
 /// Tests the lexer's ability to handle special characters.
 #[test]
 fn lex_special_characters() {
-    let input = "+ : # - ;";
+    let input = "+ : # - ; , ( ) [ ] { } * / % = == != < <= > >=";
 
     let tests = vec![
         Token::new(TokenKind::Plus, Position::new(0, 1), "+"),
@@ -80,6 +80,42 @@ fn lex_special_characters() {
         Token::new(TokenKind::Hash, Position::new(4, 5), "#"),
         Token::new(TokenKind::Minus, Position::new(6, 7), "-"),
         Token::new(TokenKind::Semicolon, Position::new(8, 9), ";"),
+        Token::new(TokenKind::Comma, Position::new(10, 11), ","),
+        Token::new(TokenKind::LParen, Position::new(12, 13), "("),
+        Token::new(TokenKind::RParen, Position::new(14, 15), ")"),
+        Token::new(TokenKind::LBracket, Position::new(16, 17), "["),
+        Token::new(TokenKind::RBracket, Position::new(18, 19), "]"),
+        Token::new(TokenKind::LBrace, Position::new(20, 21), "{"),
+        Token::new(TokenKind::RBrace, Position::new(22, 23), "}"),
+        Token::new(TokenKind::Star, Position::new(24, 25), "*"),
+        Token::new(TokenKind::Slash, Position::new(26, 27), "/"),
+        Token::new(TokenKind::Percent, Position::new(28, 29), "%"),
+        Token::new(TokenKind::Equals, Position::new(30, 31), "="),
+        Token::new(TokenKind::DoubleEquals, Position::new(32, 34), "=="),
+        Token::new(TokenKind::NotEquals, Position::new(35, 37), "!="),
+        Token::new(TokenKind::Less, Position::new(38, 39), "<"),
+        Token::new(TokenKind::LessEquals, Position::new(40, 42), "<="),
+        Token::new(TokenKind::Greater, Position::new(43, 44), ">"),
+        Token::new(TokenKind::GreaterEquals, Position::new(45, 47), ">="),
+    ];
+
+    lexes_as(input, tests)
+}
+
+/// Tests the lexer's ability to tokenize strings, including ones missing their closing quote.
+#[test]
+fn lex_strings() {
+    let input = "\"Too big!\" \"\" \"unclosed ; not a comment\n";
+
+    let tests = vec![
+        Token::new(TokenKind::String, Position::new(0, 10), "\"Too big!\""),
+        Token::new(TokenKind::String, Position::new(11, 13), "\"\""),
+        Token::new(
+            TokenKind::String,
+            Position::new(14, 39),
+            "\"unclosed ; not a comment",
+        ),
+        Token::new(TokenKind::Newline, Position::new(39, 40), "\n"),
     ];
 
     lexes_as(input, tests)
@@ -114,6 +150,23 @@ fn lex_identifiers() {
     lexes_as(input, tests)
 }
 
+/// Tests that identifiers can have parts separated by periods, but keywords can't.
+#[test]
+fn lex_dotted_identifiers() {
+    let input = "Sprite.x a._b.c2 end. .org.byte";
+
+    let tests = vec![
+        Token::new(TokenKind::Ident, Position::new(0, 8), "Sprite.x"),
+        Token::new(TokenKind::Ident, Position::new(9, 16), "a._b.c2"),
+        Token::new(TokenKind::Ident, Position::new(17, 20), "end"),
+        Token::new(TokenKind::Invalid, Position::new(20, 21), "."),
+        Token::new(TokenKind::Keyword, Position::new(22, 26), ".org"),
+        Token::new(TokenKind::Keyword, Position::new(26, 31), ".byte"),
+    ];
+
+    lexes_as(input, tests)
+}
+
 /// Tests the lexer's ability to tokenize different numbers, including hex, binary, and octal. These are all byte values.
 /// Note that the lexer does not support floating point numbers.
 #[test]
@@ -139,10 +192,10 @@ fn lex_numeric_values() {
 /// Test failing conditions for the lexer -- unknown characters and keywords.
 #[test]
 fn lex_failing_conditions() {
-    let input = "% !";
+    let input = "$ !";
 
     let tests = vec![
-        Token::new(TokenKind::Invalid, Position::new(0, 1), "%"),
+        Token::new(TokenKind::Invalid, Position::new(0, 1), "$"),
         Token::new(TokenKind::Invalid, Position::new(2, 3), "!"),
     ];
 
@@ -67,6 +67,21 @@ pub enum TokenKind {
     /// A semicolon
     Semicolon,
 
+    /// A comma, which can optionally separate arguments.
+    Comma,
+
+    /// A left parenthesis.
+    LParen,
+
+    /// A right parenthesis.
+    RParen,
+
+    /// A left square bracket.
+    LBracket,
+
+    /// A right square bracket.
+    RBracket,
+
     /// A plus sign.
     Plus,
 
@@ -76,6 +91,45 @@ pub enum TokenKind {
     /// A pound sign.
     Hash,
 
+    /// A left curly brace, which starts an expression.
+    LBrace,
+
+    /// A right curly brace, which ends an expression.
+    RBrace,
+
+    /// An asterisk.
+    Star,
+
+    /// A forward slash.
+    Slash,
+
+    /// A percent sign.
+    Percent,
+
+    /// An equals sign.
+    Equals,
+
+    /// Two equals signs, which compare if two values are equal.
+    DoubleEquals,
+
+    /// An exclamation mark and an equals sign, which compare if two values are different.
+    NotEquals,
+
+    /// A less-than sign.
+    Less,
+
+    /// A less-than sign and an equals sign.
+    LessEquals,
+
+    /// A greater-than sign.
+    Greater,
+
+    /// A greater-than sign and an equals sign.
+    GreaterEquals,
+
+    /// A string in double quotes (e.g. `"Table crosses a page"`).
+    String,
+
     /// A numeric value.
     Number,
 
@@ -94,7 +148,25 @@ impl Display for TokenKind {
                 TokenKind::Newline => "a newline",
                 TokenKind::Colon => "a ':'",
                 TokenKind::Semicolon => "a ';'",
+                TokenKind::Comma => "a ','",
+                TokenKind::LParen => "a '('",
+                TokenKind::RParen => "a ')'",
+                TokenKind::LBracket => "a '['",
+                TokenKind::RBracket => "a ']'",
                 TokenKind::Hash => "a '#'",
+                TokenKind::LBrace => "a '{'",
+                TokenKind::RBrace => "a '}'",
+                TokenKind::Star => "a '*'",
+                TokenKind::Slash => "a '/'",
+                TokenKind::Percent => "a '%'",
+                TokenKind::Equals => "a '='",
+                TokenKind::DoubleEquals => "a '=='",
+                TokenKind::NotEquals => "a '!='",
+                TokenKind::Less => "a '<'",
+                TokenKind::LessEquals => "a '<='",
+                TokenKind::Greater => "a '>'",
+                TokenKind::GreaterEquals => "a '>='",
+                TokenKind::String => "a string",
                 TokenKind::Plus => "a '+'",
                 TokenKind::Minus => "a '-'",
                 TokenKind::Ident => "an identifier",
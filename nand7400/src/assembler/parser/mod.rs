@@ -4,10 +4,16 @@ pub mod lexer;
 
 mod tests;
 
-use std::num::{IntErrorKind, ParseIntError};
+use std::{
+    collections::HashMap,
+    num::{IntErrorKind, ParseIntError},
+};
 
 use self::{
-    ast::{Argument, ArgumentKind, Instruction, Keyword},
+    ast::{
+        Argument, ArgumentKind, BinaryOperator, EvaluationError, Expression, ExpressionKind,
+        Instruction, Keyword, MessageKind,
+    },
     errors::ParsingError,
     lexer::{
         token::{Token, TokenKind},
@@ -15,12 +21,17 @@ use self::{
     },
 };
 use crate::assembler::{
+    config::Register,
     parser::ast::{InstructionKind, Label},
     position::Position,
 };
 use ast::Ast;
 use num_traits::{AsPrimitive, FromPrimitive, Num, Signed, Unsigned};
 
+/// The most times that `.rept` and `.for` blocks can repeat in total, counting every iteration of nested blocks, which
+/// is the size of a 16-bit address space.
+pub const MAX_ITERATIONS: u64 = 0x10000;
+
 /// The parser type, used to parse the source code.
 pub struct Parser {
     /// The lexer used to lex the source code.
@@ -30,15 +41,34 @@ pub struct Parser {
     ast: Ast,
 
     /// The memory location of the next instruction.
-    next_mem_location: u16,
+    next_mem_location: u32,
 
     /// The current token type.
     current_token: Token,
+
+    /// The registers that can be used as arguments. Any identifier with one of these names is a register rather than
+    /// a label.
+    registers: Vec<Register>,
+
+    /// The variables of the `.for` blocks being parsed and their current values, from the outermost block in.
+    loop_variables: Vec<(String, u32)>,
+
+    /// How many `.rept` and `.for` blocks are being parsed, one inside the other.
+    loop_depth: usize,
+
+    /// How many times `.rept` and `.for` blocks have repeated so far, across every level of nesting. This can't go
+    /// over `MAX_ITERATIONS`, so that nested blocks can't multiply their counts into something that never finishes.
+    iterations: u64,
 }
 
 impl Parser {
     /// Create a new parser from some source code.
     pub fn new(source: &str) -> Result<Self, ParsingError> {
+        Self::with_registers(source, &[])
+    }
+
+    /// Create a new parser from some source code, which knows about the given registers.
+    pub fn with_registers(source: &str, registers: &[Register]) -> Result<Self, ParsingError> {
         let mut parser = Self {
             lexer: Lexer::new(source),
             ast: Ast::empty(),
@@ -48,6 +78,10 @@ impl Parser {
                 position: Position::new(0, 0),
                 literal: String::new(),
             },
+            registers: registers.to_vec(),
+            loop_variables: vec![],
+            loop_depth: 0,
+            iterations: 0,
         };
 
         // Get the first token, and remove the invalid placeholder one.
@@ -58,15 +92,33 @@ impl Parser {
 
     /// Parses and returns the AST.
     pub fn parse(mut self) -> Result<Ast, ParsingError> {
+        self.parse_instructions(None)?;
+
+        Ok(self.ast)
+    }
+}
+
+impl Parser {
+    /// Parses instructions into the AST until the end of the file, or until the keyword `end` if this is the body of
+    /// a `.rept` or `.for` block (where `block` is the token of the keyword that started the block, and `end`).
+    fn parse_instructions(&mut self, block: Option<(&Token, &str)>) -> Result<(), ParsingError> {
         // Developer notes: The main loop is just a loop that calls these parsing functions, inserts instructions, and then
-        // returns the AST when it's done. Sub-parsers are required to update the symbol table as necessary.
+        // returns when it's done. Sub-parsers are required to update the symbol table as necessary.
 
         // Loop until we finish parsing.
         loop {
             // Match on the token, and then parse it.
             let instruction = match self.current_token.kind {
-                // If the token is an EOF, then we're done parsing.
-                TokenKind::Eof => return Ok(self.ast),
+                // If the token is an EOF, then we're done parsing, unless we're still in a block.
+                TokenKind::Eof => {
+                    return match block {
+                        Some((keyword_token, _)) => Err(ParsingError::UnterminatedBlock {
+                            keyword: keyword_token.literal.clone(),
+                            span: keyword_token.position,
+                        }),
+                        None => Ok(()),
+                    }
+                }
 
                 // If the token is a newline while parsing a file, then we skip it. We only care about these when
                 // parsing an opcode or keyword.
@@ -77,37 +129,425 @@ impl Parser {
 
                 // If we reach a comment, we consume it in entirety.
                 TokenKind::Semicolon => {
-                    self.read_token_unchecked();
-
-                    while !matches!(self.current_token.kind, TokenKind::Newline | TokenKind::Eof) {
-                        self.read_token_unchecked();
-                    }
-
+                    self.skip_comment();
                     continue;
                 }
 
                 // If the token is a identifier, then we have either a label or opcode.
                 TokenKind::Ident => self.parse_label_or_opcode()?,
 
-                // If the token is a keyword, then we have a keyword instruction.
+                // If we reach the end of the block we're in, then we're done parsing it.
+                TokenKind::Keyword
+                    if block.is_some_and(|(_, end)| {
+                        self.current_token.literal.eq_ignore_ascii_case(end)
+                    }) =>
+                {
+                    self.read_token()?;
+
+                    return self.expect_line_end();
+                }
+
+                // If the token is a keyword, then we have a keyword instruction, or a block.
                 TokenKind::Keyword => {
                     let current = self.current_token.clone();
 
                     self.read_token()?;
 
-                    self.parse_keyword(current)?
+                    match current.literal.to_ascii_lowercase().as_str() {
+                        ".struct" => {
+                            self.parse_struct(current)?;
+                            continue;
+                        }
+
+                        ".enum" => {
+                            self.parse_enum(current)?;
+                            continue;
+                        }
+
+                        ".rept" => {
+                            self.parse_rept(current)?;
+                            continue;
+                        }
+
+                        ".for" => {
+                            self.parse_for(current)?;
+                            continue;
+                        }
+
+                        ".assert" | ".error" | ".warning" => self.parse_message(current)?,
+
+                        _ => self.parse_keyword(current)?,
+                    }
                 }
 
-                _ => todo!(),
+                _ => {
+                    return Err(ParsingError::Unexpected {
+                        expected: vec![TokenKind::Ident, TokenKind::Keyword],
+                        found: self.current_token.kind,
+                        span: self.current_token.position,
+                    })
+                }
             };
 
             self.ast.instructions.push(instruction.clone());
-            self.next_mem_location += instruction.binary_len();
+            self.next_mem_location = self
+                .next_mem_location
+                .saturating_add(instruction.binary_len() as u32);
         }
     }
-}
 
-impl Parser {
+    /// Parses a `.rept` block, which repeats the instructions in it a number of times. We expect that the current
+    /// token is *not* the keyword, but the token after it; and that `keyword_token` is the token of the keyword.
+    fn parse_rept(&mut self, keyword_token: Token) -> Result<(), ParsingError> {
+        let (count, span) = self.parse_value()?;
+
+        self.expect_line_end()?;
+        self.parse_loop(&keyword_token, ".endr", None, 0, (count as u64, span))
+    }
+
+    /// Parses a `.for` block (e.g. `.for i = 0 to 9`), which repeats the instructions in it once for every value from
+    /// the start to the end, inclusive. The loop variable can be used as a number anywhere in the block, including in
+    /// expressions. We expect that the current token is *not* the keyword, but the token after it; and that
+    /// `keyword_token` is the token of the keyword.
+    fn parse_for(&mut self, keyword_token: Token) -> Result<(), ParsingError> {
+        let variable = self.expect(TokenKind::Ident)?;
+
+        self.expect(TokenKind::Equals)?;
+
+        let (start, start_span) = self.parse_value()?;
+
+        if !self.current_token.literal.eq_ignore_ascii_case("to") {
+            return Err(ParsingError::Unexpected {
+                expected: vec![TokenKind::Ident],
+                found: self.current_token.kind,
+                span: self.current_token.position,
+            });
+        }
+
+        self.read_token()?;
+
+        let (end, end_span) = self.parse_value()?;
+
+        self.expect_line_end()?;
+
+        // Looping "backwards" doesn't run the block at all.
+        let count = (end as u64 + 1).saturating_sub(start as u64);
+
+        self.parse_loop(
+            &keyword_token,
+            ".endfor",
+            Some(variable.literal),
+            start,
+            (count, start_span.join(&end_span)),
+        )
+    }
+
+    /// Parses the body of a `.rept` or `.for` block `count` times (where `count` comes with the span it was given
+    /// at), with the loop variable (if there is one) starting at `start` and going up by 1 each time. Each time, the
+    /// body is parsed again from the same place in the source code, so that everything in it (including the loop
+    /// variable) is laid out like it was written out in full. Any errors in the body are wrapped with the iteration
+    /// they happened in, starting at 0.
+    fn parse_loop(
+        &mut self,
+        keyword_token: &Token,
+        end: &str,
+        variable: Option<String>,
+        start: u32,
+        (count, count_span): (u64, Position),
+    ) -> Result<(), ParsingError> {
+        let iterations = self.iterations.saturating_add(count);
+
+        if iterations > MAX_ITERATIONS {
+            return Err(ParsingError::TooManyIterations {
+                count: iterations,
+                max: MAX_ITERATIONS,
+                span: count_span,
+            });
+        }
+
+        self.iterations = iterations;
+
+        let count = count as u32; // This is safe because we already checked it's at most `MAX_ITERATIONS`.
+        let body = (self.lexer.clone(), self.current_token.clone());
+
+        // The body still has to be parsed once to find where it ends, so anything from it is thrown away afterwards.
+        let discarded = (count == 0).then(|| {
+            (
+                self.ast.instructions.len(),
+                self.ast.symbols.clone(),
+                self.ast.constants.clone(),
+                self.next_mem_location,
+            )
+        });
+
+        for iteration in 0..count.max(1) {
+            (self.lexer, self.current_token) = body.clone();
+
+            let first = self.ast.instructions.len();
+
+            if let Some(variable) = &variable {
+                self.loop_variables
+                    .push((variable.clone(), start.wrapping_add(iteration)));
+            }
+
+            self.loop_depth += 1;
+
+            let result = self.parse_instructions(Some((keyword_token, end)));
+
+            self.loop_depth -= 1;
+
+            if variable.is_some() {
+                self.loop_variables.pop();
+            }
+
+            result.map_err(|err| ParsingError::InIteration {
+                iteration,
+                source: Box::new(err),
+                span: keyword_token.position,
+            })?;
+
+            // Nested loops have already marked their instructions with their own iterations.
+            for instruction in &mut self.ast.instructions[first..] {
+                instruction.iteration.get_or_insert(iteration);
+            }
+        }
+
+        if let Some((len, symbols, constants, next_mem_location)) = discarded {
+            self.ast.instructions.truncate(len);
+            self.ast.symbols = symbols;
+            self.ast.constants = constants;
+            self.next_mem_location = next_mem_location;
+        }
+
+        Ok(())
+    }
+
+    /// Parses a single number, constant, or expression (e.g. the count of a `.rept` block), returning its value and
+    /// span.
+    fn parse_value(&mut self) -> Result<(u32, Position), ParsingError> {
+        let arg = self.parse_numeric_argument::<u32, i32>()?;
+
+        match arg.kind {
+            ArgumentKind::ImmediateNumber(value) | ArgumentKind::IndirectNumber(value) => {
+                Ok((value, arg.span))
+            }
+
+            _ => unreachable!("Numeric arguments are always numbers!"),
+        }
+    }
+
+    /// Gets the value of a constant, or of a loop variable in one of the `.for` blocks being parsed. Loop variables
+    /// take precedence over constants, and inner loops over outer ones.
+    fn constant(&self, name: &str) -> Option<u32> {
+        self.loop_variables
+            .iter()
+            .rev()
+            .find(|(variable, _)| variable == name)
+            .map(|(_, value)| *value)
+            .or_else(|| self.ast.constants.get(name).copied())
+    }
+
+    /// Parses an expression inside of curly braces (e.g. `{i * 2 + 1}`), returning its value. These can only use
+    /// numbers, constants, and loop variables, as they're worked out straight away. We expect that the current token
+    /// is the `{`.
+    fn parse_braced_expression(&mut self) -> Result<Argument<u32>, ParsingError> {
+        let open_pos = self.current_token.position;
+
+        // Consume the `{`.
+        self.read_token()?;
+
+        let expression = self.parse_expression()?;
+        let close = self.expect(TokenKind::RBrace)?;
+
+        let value = expression
+            .evaluate(&HashMap::new())
+            .map_err(|err| match err {
+                EvaluationError::LabelDNE { name, span } => {
+                    ParsingError::NotConstant { name, span }
+                }
+                EvaluationError::DivisionByZero { span } => ParsingError::DivisionByZero { span },
+            })?;
+
+        Ok(Argument {
+            kind: ArgumentKind::IndirectNumber(value),
+            span: open_pos.join(&close.position),
+        })
+    }
+
+    /// Parses an expression. Expressions are made of numbers, constants, loop variables, and labels, combined with
+    /// `+`, `-`, `*`, `/`, `%`, comparisons, and parentheses. Constants and loop variables are replaced with their
+    /// values, and anything else is assumed to be a label.
+    fn parse_expression(&mut self) -> Result<Expression, ParsingError> {
+        let mut expression = self.parse_sum()?;
+
+        loop {
+            let operator = match self.current_token.kind {
+                TokenKind::DoubleEquals => BinaryOperator::Equal,
+                TokenKind::NotEquals => BinaryOperator::NotEqual,
+                TokenKind::Less => BinaryOperator::Less,
+                TokenKind::LessEquals => BinaryOperator::LessEqual,
+                TokenKind::Greater => BinaryOperator::Greater,
+                TokenKind::GreaterEquals => BinaryOperator::GreaterEqual,
+                _ => return Ok(expression),
+            };
+
+            self.read_token()?;
+
+            expression = binary_expression(operator, expression, self.parse_sum()?);
+        }
+    }
+
+    /// Parses terms added and subtracted together in an expression.
+    fn parse_sum(&mut self) -> Result<Expression, ParsingError> {
+        let mut expression = self.parse_product()?;
+
+        loop {
+            let operator = match self.current_token.kind {
+                TokenKind::Plus => BinaryOperator::Add,
+                TokenKind::Minus => BinaryOperator::Subtract,
+                _ => return Ok(expression),
+            };
+
+            self.read_token()?;
+
+            expression = binary_expression(operator, expression, self.parse_product()?);
+        }
+    }
+
+    /// Parses factors multiplied, divided, and taken the remainder of together in an expression.
+    fn parse_product(&mut self) -> Result<Expression, ParsingError> {
+        let mut expression = self.parse_factor()?;
+
+        loop {
+            let operator = match self.current_token.kind {
+                TokenKind::Star => BinaryOperator::Multiply,
+                TokenKind::Slash => BinaryOperator::Divide,
+                TokenKind::Percent => BinaryOperator::Remainder,
+                _ => return Ok(expression),
+            };
+
+            self.read_token()?;
+
+            expression = binary_expression(operator, expression, self.parse_factor()?);
+        }
+    }
+
+    /// Parses a single number, constant, label, or parenthesized expression in an expression, along with any signs
+    /// in front of it.
+    fn parse_factor(&mut self) -> Result<Expression, ParsingError> {
+        let start_pos = self.current_token.position;
+
+        match self.current_token.kind {
+            TokenKind::Plus => {
+                self.read_token()?;
+
+                let inner = self.parse_factor()?;
+
+                Ok(Expression {
+                    span: start_pos.join(&inner.span),
+                    ..inner
+                })
+            }
+
+            TokenKind::Minus => {
+                self.read_token()?;
+
+                let inner = self.parse_factor()?;
+
+                Ok(Expression {
+                    span: start_pos.join(&inner.span),
+                    kind: ExpressionKind::Negate(Box::new(inner)),
+                })
+            }
+
+            TokenKind::LParen => {
+                self.read_token()?;
+
+                let inner = self.parse_expression()?;
+                let close = self.expect(TokenKind::RParen)?;
+
+                Ok(Expression {
+                    span: start_pos.join(&close.position),
+                    ..inner
+                })
+            }
+
+            TokenKind::Number => {
+                let number = self.expect(TokenKind::Number)?;
+
+                Ok(Expression {
+                    kind: ExpressionKind::Number(parse_number(&number.literal, number.position)?),
+                    span: start_pos,
+                })
+            }
+
+            TokenKind::Ident => {
+                let name = self.expect(TokenKind::Ident)?.literal;
+
+                Ok(Expression {
+                    kind: match self.constant(&name) {
+                        Some(value) => ExpressionKind::Number(value),
+                        None => ExpressionKind::Label(name),
+                    },
+                    span: start_pos,
+                })
+            }
+
+            found => Err(ParsingError::Unexpected {
+                expected: vec![
+                    TokenKind::Number,
+                    TokenKind::Ident,
+                    TokenKind::LParen,
+                    TokenKind::Plus,
+                    TokenKind::Minus,
+                ],
+                found,
+                span: start_pos,
+            }),
+        }
+    }
+
+    /// Parses a build-time check or message (`.assert`, `.error`, or `.warning`). These are followed by a message in
+    /// quotes, and `.assert` has an expression before the message as well (e.g. `.assert END <= 0x8000, "Too big!"`).
+    /// We expect that the current token is *not* the keyword, but the token after it; and that `keyword_token` is
+    /// the token of the keyword.
+    fn parse_message(&mut self, keyword_token: Token) -> Result<Instruction, ParsingError> {
+        let kind = match keyword_token.literal.to_ascii_lowercase().as_str() {
+            ".assert" => {
+                let condition = self.parse_expression()?;
+
+                // The comma between the condition and the message is optional, like between arguments.
+                if self.current_token.kind == TokenKind::Comma {
+                    self.read_token()?;
+                }
+
+                MessageKind::Assert(condition)
+            }
+            ".error" => MessageKind::Error,
+            _ => MessageKind::Warning,
+        };
+
+        let message = self.expect(TokenKind::String)?;
+
+        // The string runs until the end of the line if it's never closed.
+        if message.literal.len() < 2 || !message.literal.ends_with('"') {
+            return Err(ParsingError::UnterminatedString {
+                span: message.position,
+            });
+        }
+
+        self.expect_line_end()?;
+
+        Ok(Instruction::new(
+            InstructionKind::Message {
+                kind,
+                message: message.literal[1..message.literal.len() - 1].to_string(),
+            },
+            keyword_token.position.join(&message.position),
+            keyword_token.position,
+        ))
+    }
+
     /// Gets the next token from the lexer.
     fn read_token(&mut self) -> Result<Token, ParsingError> {
         let token = self.read_token_unchecked();
@@ -124,6 +564,214 @@ impl Parser {
         }
     }
 
+    /// Gets the register with the given name, if there is one.
+    fn register(&self, name: &str) -> Option<&Register> {
+        self.registers.iter().find(|register| register.name == name)
+    }
+
+    /// Consumes a comment, up to (but not including) the newline or EOF that ends it. We expect that the current
+    /// token is the `;` that starts the comment.
+    fn skip_comment(&mut self) {
+        self.read_token_unchecked();
+
+        while !matches!(self.current_token.kind, TokenKind::Newline | TokenKind::Eof) {
+            self.read_token_unchecked();
+        }
+    }
+
+    /// Consumes the current token if it's of the given kind, returning it. Otherwise, this is an error.
+    fn expect(&mut self, kind: TokenKind) -> Result<Token, ParsingError> {
+        if self.current_token.kind != kind {
+            return Err(ParsingError::Unexpected {
+                expected: vec![kind],
+                found: self.current_token.kind,
+                span: self.current_token.position,
+            });
+        }
+
+        let token = self.current_token.clone();
+        self.read_token()?;
+
+        Ok(token)
+    }
+
+    /// Consumes the end of a line, including any comment before it. The newline itself is left for the caller.
+    fn expect_line_end(&mut self) -> Result<(), ParsingError> {
+        match self.current_token.kind {
+            TokenKind::Newline | TokenKind::Eof => Ok(()),
+
+            TokenKind::Semicolon => {
+                self.skip_comment();
+                Ok(())
+            }
+
+            found => Err(ParsingError::Unexpected {
+                expected: vec![TokenKind::Newline, TokenKind::Eof],
+                found,
+                span: self.current_token.position,
+            }),
+        }
+    }
+
+    /// Defines a constant, which can't have the same name as any label or constant defined before it.
+    fn define_constant(
+        &mut self,
+        name: String,
+        value: u32,
+        span: Position,
+    ) -> Result<(), ParsingError> {
+        if self.ast.constants.contains_key(&name) || self.ast.symbols.contains_key(&name) {
+            return Err(ParsingError::DuplicateSymbol { name, span });
+        }
+
+        self.ast.constants.insert(name, value);
+
+        Ok(())
+    }
+
+    /// Parses the lines of a `.struct` or `.enum` block up to the keyword that ends it, calling `parse_line` with the
+    /// token at the start of each line (which has already been consumed). We expect that the current token is the
+    /// name of the block, and that `keyword_token` is the token of the keyword that started it.
+    fn parse_block(
+        &mut self,
+        keyword_token: &Token,
+        end: &str,
+        mut parse_line: impl FnMut(&mut Self, Token) -> Result<(), ParsingError>,
+    ) -> Result<Token, ParsingError> {
+        let name = self.expect(TokenKind::Ident)?;
+
+        self.expect_line_end()?;
+
+        loop {
+            match self.current_token.kind {
+                TokenKind::Newline => {
+                    self.read_token()?;
+                }
+
+                TokenKind::Semicolon => self.skip_comment(),
+
+                TokenKind::Keyword if self.current_token.literal.eq_ignore_ascii_case(end) => {
+                    self.read_token()?;
+                    self.expect_line_end()?;
+
+                    return Ok(name);
+                }
+
+                TokenKind::Ident => {
+                    let line = self.current_token.clone();
+
+                    self.read_token()?;
+                    parse_line(self, line)?;
+                    self.expect_line_end()?;
+                }
+
+                TokenKind::Eof => {
+                    return Err(ParsingError::UnterminatedBlock {
+                        keyword: keyword_token.literal.clone(),
+                        span: keyword_token.position.join(&name.position),
+                    })
+                }
+
+                found => {
+                    return Err(ParsingError::Unexpected {
+                        expected: vec![TokenKind::Ident, TokenKind::Keyword],
+                        found,
+                        span: self.current_token.position,
+                    })
+                }
+            }
+        }
+    }
+
+    /// Parses a `.struct` block, which defines a constant for the offset of each field (e.g. `Sprite.x`) and one for
+    /// the size of the whole struct (`Sprite.size`). Each line is the name of a field followed by its size, which is
+    /// either a number of bytes or the name of a struct defined before it. We expect that the current token is *not*
+    /// the keyword, but the token after it; and that `keyword_token` is the token of the keyword.
+    fn parse_struct(&mut self, keyword_token: Token) -> Result<(), ParsingError> {
+        let name = self.current_token.clone();
+        let mut fields = vec![];
+        let mut offset = 0_u32;
+
+        self.parse_block(&keyword_token, ".endstruct", |parser, field| {
+            let size = match parser.current_token.kind {
+                TokenKind::Ident => {
+                    let size_name = format!("{}.size", parser.current_token.literal);
+
+                    let size = *parser.ast.constants.get(&size_name).ok_or_else(|| {
+                        ParsingError::StructDNE {
+                            name: parser.current_token.literal.clone(),
+                            span: parser.current_token.position,
+                        }
+                    })?;
+
+                    parser.read_token()?;
+
+                    size
+                }
+
+                _ => {
+                    let size = parser.expect(TokenKind::Number)?;
+
+                    parse_number::<u32>(&size.literal, size.position)?
+                }
+            };
+
+            let field_name = format!("{}.{}", name.literal, field.literal);
+            let end = offset
+                .checked_add(size)
+                .ok_or_else(|| ParsingError::ConstantOverflow {
+                    name: field_name.clone(),
+                    span: field.position,
+                })?;
+
+            fields.push((field_name, offset, field.position));
+            offset = end;
+
+            Ok(())
+        })?;
+
+        for (field, offset, span) in fields {
+            self.define_constant(field, offset, span)?;
+        }
+
+        self.define_constant(format!("{}.size", name.literal), offset, name.position)
+    }
+
+    /// Parses a `.enum` block, which defines a constant for each member (e.g. `Color.Red`). Members are numbered from
+    /// 0 in order, but a member can be followed by a number to give it that value, and the ones after it carry on
+    /// from there. We expect that the current token is *not* the keyword, but the token after it; and that
+    /// `keyword_token` is the token of the keyword.
+    fn parse_enum(&mut self, keyword_token: Token) -> Result<(), ParsingError> {
+        let name = self.current_token.clone();
+        let mut members = vec![];
+        let mut next_value = Some(0_u32); // This is `None` if the last member was the largest value there is.
+
+        self.parse_block(&keyword_token, ".endenum", |parser, member| {
+            if parser.current_token.kind == TokenKind::Number {
+                let value = parser.expect(TokenKind::Number)?;
+
+                next_value = Some(parse_number(&value.literal, value.position)?);
+            }
+
+            let member_name = format!("{}.{}", name.literal, member.literal);
+            let value = next_value.ok_or_else(|| ParsingError::ConstantOverflow {
+                name: member_name.clone(),
+                span: member.position,
+            })?;
+
+            members.push((member_name, value, member.position));
+            next_value = value.checked_add(1);
+
+            Ok(())
+        })?;
+
+        for (member, value, span) in members {
+            self.define_constant(member, value, span)?;
+        }
+
+        Ok(())
+    }
+
     /// Gets the next token from the lexer, without regard to illegality.
     fn read_token_unchecked(&mut self) -> Token {
         let token = self.lexer.next_token();
@@ -150,6 +798,28 @@ impl Parser {
     fn parse_label(&mut self, label_token: Token) -> Result<Instruction, ParsingError> {
         let label_name: Label = label_token.literal;
 
+        if self.register(&label_name).is_some() {
+            return Err(ParsingError::LabelIsRegister {
+                name: label_name,
+                span: label_token.position,
+            });
+        }
+
+        if self.ast.constants.contains_key(&label_name) {
+            return Err(ParsingError::DuplicateSymbol {
+                name: label_name,
+                span: label_token.position,
+            });
+        }
+
+        // Labels in a repeated block would be moved by every iteration, so they can't be defined more than once.
+        if self.loop_depth > 0 && self.ast.symbols.contains_key(&label_name) {
+            return Err(ParsingError::LabelInLoop {
+                name: label_name,
+                span: label_token.position,
+            });
+        }
+
         let instruction = Instruction::new(
             InstructionKind::Label(label_name.clone()),
             label_token.position.join(&self.current_token.position), // Include the colon in the instruction span.
@@ -167,7 +837,8 @@ impl Parser {
     /// Parse a single opcode from tokens. We expect that the current token is *not* the opcode, but the token after it;
     /// and that `opcode_token` is the token of the opcode.
     fn parse_opcode(&mut self, opcode_token: Token) -> Result<Instruction, ParsingError> {
-        let (arguments, current_pos) = self.parse_argument_list::<u8, i8>(opcode_token.position)?;
+        let (arguments, current_pos) =
+            self.parse_argument_list::<u32, i32>(opcode_token.position)?;
 
         let opcode = Instruction::new(
             InstructionKind::Opcode {
@@ -192,19 +863,24 @@ impl Parser {
     /// and that `keyword_token` is the token of the keyword.
     fn parse_keyword(&mut self, keyword_token: Token) -> Result<Instruction, ParsingError> {
         let (arguments, current_pos) =
-            self.parse_argument_list::<u16, i16>(keyword_token.position)?;
+            self.parse_argument_list::<u32, i32>(keyword_token.position)?;
 
         let keyword_kind = match keyword_token.literal.to_ascii_lowercase().as_str() {
             ".byte" => Keyword::Byte,
+            ".word" => Keyword::Word,
+            ".entry" => Keyword::Entry,
+            ".vector" => Keyword::Vector,
+            ".checksum" => Keyword::Checksum,
             ".org" => {
-                // Set the current memory address to the first argument, so labels end up in the correct place.
-                match arguments.first() {
-                    Some(Argument {
-                        kind:
-                            ArgumentKind::ImmediateNumber(number) | ArgumentKind::IndirectNumber(number), // Both numbers and labels are valid for the first argument, and are treated as immediate values.
-                        ..
-                    }) => self.next_mem_location = *number,
-                    _ => unreachable!(),
+                // Set the current memory address to the first argument if it's a number, so labels end up in the
+                // correct place. Anything else (e.g. a label) is worked out and checked by the assembler.
+                if let Some(Argument {
+                    kind:
+                        ArgumentKind::ImmediateNumber(number) | ArgumentKind::IndirectNumber(number),
+                    ..
+                }) = arguments.first()
+                {
+                    self.next_mem_location = *number;
                 }
 
                 Keyword::Org
@@ -249,27 +925,36 @@ impl Parser {
     {
         let mut arguments = vec![];
         let mut current_pos = pos;
+        let mut after_comma = false; // Whether the last token was a comma, which has to be followed by an argument.
 
         // Parse all the arguments.
         while !matches!(self.current_token.kind, TokenKind::Newline | TokenKind::Eof) {
             match self.current_token.kind {
-                TokenKind::Ident => {
-                    let label_name: Label = self.current_token.literal.clone();
-
-                    // Insert the label.
-                    arguments.push(Argument {
-                        kind: ArgumentKind::Label(label_name),
-                        span: self.current_token.position,
-                    });
-
-                    current_pos = current_pos.join(&self.current_token.position);
-
-                    // Consume the label name.
+                // Commas between arguments are optional, but there can only be one between each pair of arguments.
+                TokenKind::Comma if !arguments.is_empty() && !after_comma => {
                     self.read_token()?;
+                    after_comma = true;
                 }
 
                 _ => {
-                    let arg = self.parse_numeric_argument::<U, V>()?;
+                    let arg = self.parse_argument::<U, V>()?;
+
+                    after_comma = false;
+
+                    // Otherwise, `table+1` would be read as the label `table` and the number `+1`.
+                    if matches!(
+                        self.current_token.kind,
+                        TokenKind::Plus
+                            | TokenKind::Minus
+                            | TokenKind::Star
+                            | TokenKind::Slash
+                            | TokenKind::Percent
+                    ) && self.current_token.position.start == arg.span.end
+                    {
+                        return Err(ParsingError::UnbracedExpression {
+                            span: arg.span.join(&self.current_token.position),
+                        });
+                    }
 
                     current_pos = current_pos.join(&arg.span);
                     arguments.push(arg);
@@ -277,9 +962,161 @@ impl Parser {
             }
         }
 
+        // A comma can't be left at the end of the line without an argument after it.
+        if after_comma {
+            return Err(ParsingError::Unexpected {
+                expected: vec![TokenKind::Ident, TokenKind::Number, TokenKind::Hash],
+                found: self.current_token.kind,
+                span: self.current_token.position,
+            });
+        }
+
         Ok((arguments, current_pos))
     }
 
+    /// Parse a single argument from tokens, including its addressing mode (i.e. indirection with `(...)`/`[...]` and
+    /// indexing with `,X`).
+    fn parse_argument<U, V>(&mut self) -> Result<Argument<U>, ParsingError>
+    where
+        U: 'static + Num<FromStrRadixErr = ParseIntError> + Unsigned + FromPrimitive + Copy,
+        V: Num<FromStrRadixErr = ParseIntError> + Signed + AsPrimitive<U>,
+    {
+        let arg = match self.current_token.kind {
+            TokenKind::LParen | TokenKind::LBracket => self.parse_indirect_argument::<U, V>()?,
+            _ => self.parse_simple_argument::<U, V>()?,
+        };
+
+        self.parse_index(arg)
+    }
+
+    /// Parse an indirect argument from tokens. We expect that the current token is a `(` or `[`, and the argument
+    /// ends with the matching `)` or `]`. The argument inside can be indexed, e.g. `(addr,X)`.
+    fn parse_indirect_argument<U, V>(&mut self) -> Result<Argument<U>, ParsingError>
+    where
+        U: 'static + Num<FromStrRadixErr = ParseIntError> + Unsigned + FromPrimitive + Copy,
+        V: Num<FromStrRadixErr = ParseIntError> + Signed + AsPrimitive<U>,
+    {
+        let open_pos = self.current_token.position;
+        let close = match self.current_token.kind {
+            TokenKind::LParen => TokenKind::RParen,
+            _ => TokenKind::RBracket,
+        };
+
+        // Consume the `(`/`[`.
+        self.read_token()?;
+
+        let inner = self.parse_simple_argument::<U, V>()?;
+        let inner = self.parse_index(inner)?;
+
+        if self.current_token.kind != close {
+            return Err(ParsingError::Unexpected {
+                expected: vec![close],
+                found: self.current_token.kind,
+                span: self.current_token.position,
+            });
+        }
+
+        let span = open_pos.join(&self.current_token.position);
+
+        // Consume the `)`/`]`.
+        self.read_token()?;
+
+        Ok(Argument {
+            kind: ArgumentKind::Indirect(Box::new(inner)),
+            span,
+        })
+    }
+
+    /// Parse the index of an argument, if it has one. An argument is indexed if it's an address (i.e. a number,
+    /// label, or indirect argument) followed by a comma and a register, with no spaces in between (e.g. `addr,X`).
+    /// Otherwise, the argument is returned as-is, and the comma is left to separate it from the next argument, so that
+    /// `addr, X` is an address and a register.
+    fn parse_index<U>(&mut self, arg: Argument<U>) -> Result<Argument<U>, ParsingError> {
+        if self.current_token.kind != TokenKind::Comma
+            || self.current_token.position.start != arg.span.end
+            || !matches!(
+                arg.kind,
+                ArgumentKind::IndirectNumber(_)
+                    | ArgumentKind::Label(_)
+                    | ArgumentKind::Indirect(_)
+            )
+        {
+            return Ok(arg);
+        }
+
+        // Look past the comma without consuming it, to see if a register follows.
+        let next_token = self.lexer.clone().next_token();
+
+        let index = match next_token.kind {
+            TokenKind::Ident if next_token.position.start == self.current_token.position.end => {
+                match self.register(&next_token.literal) {
+                    Some(register) => register.clone(),
+                    None => return Ok(arg),
+                }
+            }
+            _ => return Ok(arg),
+        };
+
+        // Consume the comma and the register.
+        self.read_token()?;
+        self.read_token()?;
+
+        Ok(Argument {
+            span: arg.span.join(&next_token.position),
+            kind: ArgumentKind::Indexed {
+                base: Box::new(arg),
+                index,
+            },
+        })
+    }
+
+    /// Parse a single argument without an addressing mode from tokens, i.e. a register, label, or number.
+    fn parse_simple_argument<U, V>(&mut self) -> Result<Argument<U>, ParsingError>
+    where
+        U: 'static + Num<FromStrRadixErr = ParseIntError> + Unsigned + FromPrimitive + Copy,
+        V: Num<FromStrRadixErr = ParseIntError> + Signed + AsPrimitive<U>,
+    {
+        match self.current_token.kind {
+            TokenKind::Ident if self.register(&self.current_token.literal).is_some() => {
+                let register = self
+                    .register(&self.current_token.literal)
+                    .cloned()
+                    .expect("We just checked that the register exists!");
+
+                let arg = Argument {
+                    kind: ArgumentKind::Register(register),
+                    span: self.current_token.position,
+                };
+
+                // Consume the register name.
+                self.read_token()?;
+
+                Ok(arg)
+            }
+
+            // Constants are written in place of the number they stand for.
+            TokenKind::Ident if self.constant(&self.current_token.literal).is_some() => {
+                self.parse_numeric_argument::<U, V>()
+            }
+
+            TokenKind::Ident => {
+                let label_name: Label = self.current_token.literal.clone();
+
+                let arg = Argument {
+                    kind: ArgumentKind::Label(label_name),
+                    span: self.current_token.position,
+                };
+
+                // Consume the label name.
+                self.read_token()?;
+
+                Ok(arg)
+            }
+
+            _ => self.parse_numeric_argument::<U, V>(),
+        }
+    }
+
     /// Parse a single numeric argument from tokens. We expect that the current token is a number or a `#`. `U` is the
     /// unsigned type of number the argument is, and `V` is the signed variang the number is parsed as if it's signed.
     fn parse_numeric_argument<U, V>(&mut self) -> Result<Argument<U>, ParsingError>
@@ -307,6 +1144,39 @@ impl Parser {
                 })
             }
 
+            // If it's a constant, then we consume it and use its value as the number.
+            TokenKind::Ident if self.constant(&self.current_token.literal).is_some() => {
+                let value = self
+                    .constant(&self.current_token.literal)
+                    .expect("We just checked that the constant exists!");
+                let pos = self.current_token.position;
+
+                // Consume the constant's name.
+                self.read_token()?;
+
+                Ok(Argument {
+                    kind: ArgumentKind::IndirectNumber(
+                        U::from_u32(value).expect("Arguments are parsed as 32-bit numbers!"),
+                    ),
+                    span: pos,
+                })
+            }
+
+            // If it's an expression, then we work out its value and use that as the number.
+            TokenKind::LBrace => {
+                let arg = self.parse_braced_expression()?;
+
+                Ok(Argument {
+                    kind: match arg.kind {
+                        ArgumentKind::IndirectNumber(value) => ArgumentKind::IndirectNumber(
+                            U::from_u32(value).expect("Arguments are parsed as 32-bit numbers!"),
+                        ),
+                        _ => unreachable!(),
+                    },
+                    span: arg.span,
+                })
+            }
+
             // If it's positive, then we consume the `+` and then parse the number.
             TokenKind::Plus => {
                 // Get the `+` position so we join it with the number.
@@ -392,6 +1262,18 @@ impl Parser {
     }
 }
 
+/// Joins two expressions together with an operator, spanning from the start of the first to the end of the second.
+fn binary_expression(operator: BinaryOperator, lhs: Expression, rhs: Expression) -> Expression {
+    Expression {
+        span: lhs.span.join(&rhs.span),
+        kind: ExpressionKind::Binary {
+            operator,
+            lhs: Box::new(lhs),
+            rhs: Box::new(rhs),
+        },
+    }
+}
+
 /// Parse a number, *not* a numeric argument. This returns the number as a `T`, and is used for parsing arguments.
 /// Note that this does *not* call `read_token`, because it's used in `parse_numeric_argument`, which does that for us.
 /// It expects that `literal` does *not* contain the numeric prefix (e.g. "0x", "0b", "0o").
@@ -399,10 +1281,10 @@ fn parse_number<T>(literal: &str, span: Position) -> Result<T, ParsingError>
 where
     T: Num<FromStrRadixErr = ParseIntError>,
 {
-    match &literal[..2] {
-        "0x" | "0X" => T::from_str_radix(&literal[2..], 16),
-        "0b" | "0B" => T::from_str_radix(&literal[2..], 2),
-        "0o" | "0O" => T::from_str_radix(&literal[2..], 8),
+    match literal.get(..2) {
+        Some("0x" | "0X") => T::from_str_radix(&literal[2..], 16),
+        Some("0b" | "0B") => T::from_str_radix(&literal[2..], 2),
+        Some("0o" | "0O") => T::from_str_radix(&literal[2..], 8),
         _ => T::from_str_radix(literal, 10),
     }
     .map_err(|err| match err.kind() {
@@ -52,8 +52,11 @@ fn parse_comment() -> miette::Result<()> {
                 kind: InstructionKind::Label("label".to_string()),
                 instruction_span: Position::new(0, 6),
                 token_span: Position::new(0, 5),
+                expansion: None,
+                iteration: None,
             }],
             symbols: HashMap::from([("label".to_string(), 0)]),
+            constants: HashMap::new(),
         },
     );
 
@@ -71,8 +74,11 @@ fn parse_label() -> miette::Result<()> {
                 kind: InstructionKind::Label("label".to_string()),
                 instruction_span: Position::new(0, 6),
                 token_span: Position::new(0, 5),
+                expansion: None,
+                iteration: None,
             }],
             symbols: HashMap::from([("label".to_string(), 0)]),
+            constants: HashMap::new(),
         },
     );
 
@@ -84,8 +90,11 @@ fn parse_label() -> miette::Result<()> {
                 kind: InstructionKind::Label("asdf123".to_string()),
                 instruction_span: Position::new(0, 8),
                 token_span: Position::new(0, 7),
+                expansion: None,
+                iteration: None,
             }],
             symbols: HashMap::from([("asdf123".to_string(), 0)]),
+            constants: HashMap::new(),
         },
     );
 
@@ -106,8 +115,11 @@ fn parse_opcode() -> miette::Result<()> {
                 },
                 instruction_span: Position::new(0, 3),
                 token_span: Position::new(0, 3),
+                expansion: None,
+                iteration: None,
             }],
             symbols: HashMap::new(),
+            constants: HashMap::new(),
         },
     );
 
@@ -122,8 +134,11 @@ fn parse_opcode() -> miette::Result<()> {
                 },
                 instruction_span: Position::new(0, 3),
                 token_span: Position::new(0, 3),
+                expansion: None,
+                iteration: None,
             }],
             symbols: HashMap::new(),
+            constants: HashMap::new(),
         },
     );
 
@@ -157,8 +172,11 @@ fn parse_opcode_with_arguments() -> miette::Result<()> {
                 },
                 instruction_span: Position::new(0, 18),
                 token_span: Position::new(0, 5),
+                expansion: None,
+                iteration: None,
             }],
             symbols: HashMap::new(),
+            constants: HashMap::new(),
         },
     );
 
@@ -182,8 +200,11 @@ fn parse_keyword() -> miette::Result<()> {
                 },
                 instruction_span: Position::new(0, 10),
                 token_span: Position::new(0, 5),
+                expansion: None,
+                iteration: None,
             }],
             symbols: HashMap::new(),
+            constants: HashMap::new(),
         },
     );
 
@@ -201,8 +222,11 @@ fn parse_keyword() -> miette::Result<()> {
                 },
                 instruction_span: Position::new(0, 10),
                 token_span: Position::new(0, 4),
+                expansion: None,
+                iteration: None,
             }],
             symbols: HashMap::new(),
+            constants: HashMap::new(),
         },
     );
 
@@ -221,6 +245,8 @@ fn parse_org_label_addrs() -> miette::Result<()> {
                     kind: InstructionKind::Label("label1".to_string()),
                     instruction_span: Position::new(0, 7),
                     token_span: Position::new(0, 6),
+                    expansion: None,
+                    iteration: None,
                 },
                 Instruction {
                     kind: InstructionKind::Keyword {
@@ -232,14 +258,19 @@ fn parse_org_label_addrs() -> miette::Result<()> {
                     },
                     instruction_span: Position::new(8, 18),
                     token_span: Position::new(8, 12),
+                    expansion: None,
+                    iteration: None,
                 },
                 Instruction {
                     kind: InstructionKind::Label("label2".to_string()),
                     instruction_span: Position::new(19, 26),
                     token_span: Position::new(19, 25),
+                    expansion: None,
+                    iteration: None,
                 },
             ],
             symbols: HashMap::from([("label1".to_string(), 0), ("label2".to_string(), 0x123)]),
+            constants: HashMap::new(),
         },
     );
 
@@ -371,6 +402,8 @@ fn parse_program() -> miette::Result<()> {
                     },
                     instruction_span: Position::new(176, 185),
                     token_span: Position::new(176, 180),
+                    expansion: None,
+                    iteration: None,
                 },
                 Instruction {
                     kind: InstructionKind::Opcode {
@@ -382,6 +415,8 @@ fn parse_program() -> miette::Result<()> {
                     },
                     instruction_span: Position::new(186, 194),
                     token_span: Position::new(186, 189),
+                    expansion: None,
+                    iteration: None,
                 },
                 Instruction {
                     kind: InstructionKind::Opcode {
@@ -393,11 +428,15 @@ fn parse_program() -> miette::Result<()> {
                     },
                     instruction_span: Position::new(195, 204),
                     token_span: Position::new(195, 198),
+                    expansion: None,
+                    iteration: None,
                 },
                 Instruction {
                     kind: InstructionKind::Label("LABEL".to_string()),
                     instruction_span: Position::new(206, 212),
                     token_span: Position::new(206, 211),
+                    expansion: None,
+                    iteration: None,
                 },
                 Instruction {
                     kind: InstructionKind::Keyword {
@@ -409,6 +448,8 @@ fn parse_program() -> miette::Result<()> {
                     },
                     instruction_span: Position::new(213, 223),
                     token_span: Position::new(213, 218),
+                    expansion: None,
+                    iteration: None,
                 },
                 Instruction {
                     kind: InstructionKind::Opcode {
@@ -430,6 +471,8 @@ fn parse_program() -> miette::Result<()> {
                     },
                     instruction_span: Position::new(224, 242),
                     token_span: Position::new(224, 227),
+                    expansion: None,
+                    iteration: None,
                 },
                 Instruction {
                     kind: InstructionKind::Opcode {
@@ -438,11 +481,471 @@ fn parse_program() -> miette::Result<()> {
                     },
                     instruction_span: Position::new(243, 246),
                     token_span: Position::new(243, 246),
+                    expansion: None,
+                    iteration: None,
                 },
             ],
             symbols: HashMap::from([("LABEL".to_string(), 21)]),
+            constants: HashMap::new(),
         }
     );
 
     Ok(())
 }
+
+/// Test the parsing of indirect arguments, with both parentheses and brackets.
+#[test]
+fn parse_indirect_arguments() -> miette::Result<()> {
+    parses_as!(
+        parse,
+        "jmp (0x10) [LABEL]",
+        Ast {
+            instructions: vec![Instruction {
+                kind: InstructionKind::Opcode {
+                    mnemonic: "jmp".to_string(),
+                    arguments: vec![
+                        Argument {
+                            kind: ArgumentKind::Indirect(Box::new(Argument {
+                                kind: ArgumentKind::IndirectNumber(0x10),
+                                span: Position::new(5, 9),
+                            })),
+                            span: Position::new(4, 10),
+                        },
+                        Argument {
+                            kind: ArgumentKind::Indirect(Box::new(Argument {
+                                kind: ArgumentKind::Label("LABEL".to_string()),
+                                span: Position::new(12, 17),
+                            })),
+                            span: Position::new(11, 18),
+                        },
+                    ]
+                },
+                instruction_span: Position::new(0, 18),
+                token_span: Position::new(0, 3),
+                expansion: None,
+                iteration: None,
+            }],
+            symbols: HashMap::new(),
+            constants: HashMap::new(),
+        },
+    );
+
+    Ok(())
+}
+
+/// Test that indirect arguments have to be closed with the matching bracket.
+#[test]
+fn parse_unclosed_indirect_argument() {
+    let result = Parser::new("jmp (0x10]").and_then(|parser| parser.parse());
+
+    assert_eq!(
+        result,
+        Err(ParsingError::Unexpected {
+            expected: vec![TokenKind::RParen],
+            found: TokenKind::RBracket,
+            span: Position::new(9, 10),
+        })
+    );
+}
+
+/// Test that `.struct` and `.enum` blocks define constants, which are parsed as numbers wherever they're used.
+#[test]
+fn parse_structs_and_enums() {
+    let source = ".struct Vec2\n\
+                  x 1\n\
+                  y 1\n\
+                  .endstruct\n\
+                  .struct Sprite ; A sprite in RAM.\n\
+                  pos Vec2\n\
+                  tile 0x02\n\
+                  \n\
+                  flags 1\n\
+                  .endstruct\n\
+                  .enum Color\n\
+                  Red\n\
+                  Green 10\n\
+                  Blue\n\
+                  .endenum\n\
+                  lda #Sprite.size Color.Blue\n";
+
+    let ast = Parser::new(source)
+        .and_then(|parser| parser.parse())
+        .unwrap();
+
+    assert_eq!(
+        ast.constants,
+        HashMap::from([
+            ("Vec2.x".to_string(), 0),
+            ("Vec2.y".to_string(), 1),
+            ("Vec2.size".to_string(), 2),
+            ("Sprite.pos".to_string(), 0),
+            ("Sprite.tile".to_string(), 2),
+            ("Sprite.flags".to_string(), 4),
+            ("Sprite.size".to_string(), 5),
+            ("Color.Red".to_string(), 0),
+            ("Color.Green".to_string(), 10),
+            ("Color.Blue".to_string(), 11),
+        ])
+    );
+    assert_eq!(
+        ast.instructions[0].kind,
+        InstructionKind::Opcode {
+            mnemonic: "lda".to_string(),
+            arguments: vec![
+                Argument {
+                    kind: ArgumentKind::ImmediateNumber(5),
+                    span: Position::new(148, 160),
+                },
+                Argument {
+                    kind: ArgumentKind::IndirectNumber(11),
+                    span: Position::new(161, 171),
+                },
+            ],
+        }
+    );
+}
+
+/// Test the errors for `.struct` and `.enum` blocks.
+#[test]
+fn parse_struct_errors() {
+    let parse = |source: &str| Parser::new(source).and_then(|parser| parser.parse());
+
+    assert_eq!(
+        parse(".struct Point\nx 1\n"),
+        Err(ParsingError::UnterminatedBlock {
+            keyword: ".struct".to_string(),
+            span: Position::new(0, 13),
+        })
+    );
+    assert_eq!(
+        parse(".struct Point\nx 1\nx 1\n.endstruct\n"),
+        Err(ParsingError::DuplicateSymbol {
+            name: "Point.x".to_string(),
+            span: Position::new(18, 19),
+        })
+    );
+    assert_eq!(
+        parse(".struct Line\nstart Point\n.endstruct\n"),
+        Err(ParsingError::StructDNE {
+            name: "Point".to_string(),
+            span: Position::new(19, 24),
+        })
+    );
+    assert_eq!(
+        parse(".enum Color\nRed\n.endenum\nColor.Red: nop\n"),
+        Err(ParsingError::DuplicateSymbol {
+            name: "Color.Red".to_string(),
+            span: Position::new(25, 34),
+        })
+    );
+    assert_eq!(
+        parse(".struct Point\nx\n.endstruct\n"),
+        Err(ParsingError::Unexpected {
+            expected: vec![TokenKind::Number],
+            found: TokenKind::Newline,
+            span: Position::new(15, 16),
+        })
+    );
+    // Offsets and values can't go past the largest constant, even if nothing uses them.
+    assert_eq!(
+        parse(".struct Big\nx 0xFFFFFFFF\ny 1\n.endstruct\n"),
+        Err(ParsingError::ConstantOverflow {
+            name: "Big.y".to_string(),
+            span: Position::new(25, 26),
+        })
+    );
+    assert_eq!(
+        parse(".enum Big\nLast 0xFFFFFFFF\nNext\n.endenum\n"),
+        Err(ParsingError::ConstantOverflow {
+            name: "Big.Next".to_string(),
+            span: Position::new(26, 30),
+        })
+    );
+    assert!(parse(".enum Big\nLast 0xFFFFFFFF\n.endenum\n").is_ok());
+}
+
+/// Test parsing `.rept` and `.for` blocks, and expressions using their loop variables.
+#[test]
+fn parse_loops() {
+    let source = ".rept 2\n\
+                  nop\n\
+                  .endr\n\
+                  .for i = 1 to 3 ; Inclusive.\n\
+                  .byte {i * 2 + 1}\n\
+                  .endfor\n\
+                  .for i = 3 to 2\n\
+                  skipped:\n\
+                  .endfor\n\
+                  .for i = 0 TO 1\n\
+                  .for j = 0 to 1\n\
+                  .byte {(i * 2 + j) % 3}\n\
+                  .endfor\n\
+                  .endfor\n";
+
+    let ast = Parser::new(source)
+        .and_then(|parser| parser.parse())
+        .unwrap();
+
+    let kinds = ast
+        .instructions
+        .iter()
+        .map(|instruction| match &instruction.kind {
+            InstructionKind::Opcode { mnemonic, .. } => mnemonic.clone(),
+            InstructionKind::Keyword { arguments, .. } => match arguments[0].kind {
+                ArgumentKind::IndirectNumber(value) => value.to_string(),
+                _ => unreachable!(),
+            },
+            InstructionKind::Label(label) => label.clone(),
+            InstructionKind::Message { message, .. } => message.clone(),
+        })
+        .collect::<Vec<_>>();
+
+    assert_eq!(kinds, vec!["nop", "nop", "3", "5", "7", "0", "1", "2", "0"]);
+    assert_eq!(
+        ast.instructions
+            .iter()
+            .map(|instruction| instruction.iteration)
+            .collect::<Vec<_>>(),
+        vec![
+            Some(0),
+            Some(1),
+            Some(0),
+            Some(1),
+            Some(2),
+            Some(0),
+            Some(1),
+            Some(0),
+            Some(1)
+        ]
+    );
+    assert!(ast.symbols.is_empty());
+}
+
+/// Test parsing expressions, which can use constants as well as numbers.
+#[test]
+fn parse_expressions() {
+    let source = ".enum Color\nRed\nGreen\n.endenum\nlda #{-(Color.Green + 2) * 3 / 2}\n";
+
+    let ast = Parser::new(source)
+        .and_then(|parser| parser.parse())
+        .unwrap();
+
+    assert_eq!(
+        ast.instructions[0].kind,
+        InstructionKind::Opcode {
+            mnemonic: "lda".to_string(),
+            arguments: vec![Argument {
+                kind: ArgumentKind::ImmediateNumber(3u32.wrapping_neg().wrapping_mul(3) / 2),
+                span: Position::new(35, 64),
+            }],
+        }
+    );
+}
+
+/// Test the errors for `.rept` and `.for` blocks, and for expressions.
+#[test]
+fn parse_loop_errors() {
+    let parse = |source: &str| Parser::new(source).and_then(|parser| parser.parse());
+
+    assert_eq!(
+        parse(".rept 2\nnop\n"),
+        Err(ParsingError::InIteration {
+            iteration: 0,
+            source: Box::new(ParsingError::UnterminatedBlock {
+                keyword: ".rept".to_string(),
+                span: Position::new(0, 5),
+            }),
+            span: Position::new(0, 5),
+        })
+    );
+    assert_eq!(
+        parse(".for i = 0 to 2\n.byte {4 / (1 - i)}\n.endfor\n"),
+        Err(ParsingError::InIteration {
+            iteration: 1,
+            source: Box::new(ParsingError::DivisionByZero {
+                span: Position::new(27, 34),
+            }),
+            span: Position::new(0, 4),
+        })
+    );
+    assert_eq!(
+        parse(".rept 2\nloop: nop\n.endr\n"),
+        Err(ParsingError::InIteration {
+            iteration: 1,
+            source: Box::new(ParsingError::LabelInLoop {
+                name: "loop".to_string(),
+                span: Position::new(8, 12),
+            }),
+            span: Position::new(0, 5),
+        })
+    );
+    assert_eq!(
+        parse(".rept -1\nnop\n.endr\n"),
+        Err(ParsingError::TooManyIterations {
+            count: u32::MAX as u64,
+            max: MAX_ITERATIONS,
+            span: Position::new(6, 8),
+        })
+    );
+    assert_eq!(
+        parse(".for i = 0 to 0xFFFFFFFF\n.endfor\n"),
+        Err(ParsingError::TooManyIterations {
+            count: u32::MAX as u64 + 1,
+            max: MAX_ITERATIONS,
+            span: Position::new(9, 24),
+        })
+    );
+    // Nested blocks share the limit, so they can't multiply their counts past it.
+    assert_eq!(
+        parse(".rept 0x10000\n.rept 0x10000\nnop\n.endr\n.endr\n"),
+        Err(ParsingError::InIteration {
+            iteration: 0,
+            source: Box::new(ParsingError::TooManyIterations {
+                count: 0x20000,
+                max: MAX_ITERATIONS,
+                span: Position::new(20, 27),
+            }),
+            span: Position::new(0, 5),
+        })
+    );
+    assert_eq!(
+        parse(".rept 2\n.rept 0x8000\n.endr\n.endr\n"),
+        Err(ParsingError::InIteration {
+            iteration: 1,
+            source: Box::new(ParsingError::TooManyIterations {
+                count: 0x10002,
+                max: MAX_ITERATIONS,
+                span: Position::new(14, 20),
+            }),
+            span: Position::new(0, 5),
+        })
+    );
+    assert!(parse(".rept 1\nloop: nop\n.endr\n").is_ok());
+
+    // Nothing from a block that repeats 0 times is kept, including constants.
+    assert_eq!(
+        parse(".rept 0\n.enum Color\nRed\n.endenum\n.endr\n.byte {Color.Red}\n"),
+        Err(ParsingError::NotConstant {
+            name: "Color.Red".to_string(),
+            span: Position::new(46, 55),
+        })
+    );
+    assert!(parse(".for i = 0xFFFF to 0xFFFF\n.byte {i}\n.endfor\n").is_ok());
+    assert_eq!(
+        parse("nop\n{1+2}\n"),
+        Err(ParsingError::Unexpected {
+            expected: vec![TokenKind::Ident, TokenKind::Keyword],
+            found: TokenKind::LBrace,
+            span: Position::new(4, 5),
+        })
+    );
+    assert_eq!(
+        parse(".byte {i}\n"),
+        Err(ParsingError::NotConstant {
+            name: "i".to_string(),
+            span: Position::new(7, 8),
+        })
+    );
+    assert_eq!(
+        parse(".for i = 0 until 2\n.endfor\n"),
+        Err(ParsingError::Unexpected {
+            expected: vec![TokenKind::Ident],
+            found: TokenKind::Ident,
+            span: Position::new(11, 16),
+        })
+    );
+}
+
+/// Test parsing `.assert`, `.error`, and `.warning`, whose expressions can use labels as well as constants.
+#[test]
+fn parse_messages() {
+    let source = ".enum Size\nMax 0x10\n.endenum\n\
+                  .assert END - START <= Size.Max, \"Too big!\"\n\
+                  .error \"Unsupported\" ; Not finished.\n\
+                  .warning \"\"\n";
+
+    let ast = Parser::new(source)
+        .and_then(|parser| parser.parse())
+        .unwrap();
+
+    let label = |name: &str, start| Expression {
+        kind: ExpressionKind::Label(name.to_string()),
+        span: Position::new(start, start + name.len()),
+    };
+
+    assert_eq!(
+        ast.instructions
+            .iter()
+            .map(|instruction| (instruction.kind.clone(), instruction.instruction_span))
+            .collect::<Vec<_>>(),
+        vec![
+            (
+                InstructionKind::Message {
+                    kind: MessageKind::Assert(Expression {
+                        kind: ExpressionKind::Binary {
+                            operator: BinaryOperator::LessEqual,
+                            lhs: Box::new(Expression {
+                                kind: ExpressionKind::Binary {
+                                    operator: BinaryOperator::Subtract,
+                                    lhs: Box::new(label("END", 37)),
+                                    rhs: Box::new(label("START", 43)),
+                                },
+                                span: Position::new(37, 48),
+                            }),
+                            rhs: Box::new(Expression {
+                                kind: ExpressionKind::Number(0x10),
+                                span: Position::new(52, 60),
+                            }),
+                        },
+                        span: Position::new(37, 60),
+                    }),
+                    message: "Too big!".to_string(),
+                },
+                Position::new(29, 72),
+            ),
+            (
+                InstructionKind::Message {
+                    kind: MessageKind::Error,
+                    message: "Unsupported".to_string(),
+                },
+                Position::new(73, 93),
+            ),
+            (
+                InstructionKind::Message {
+                    kind: MessageKind::Warning,
+                    message: String::new(),
+                },
+                Position::new(110, 121),
+            ),
+        ]
+    );
+}
+
+/// Test the errors for `.assert`, `.error`, and `.warning`.
+#[test]
+fn parse_message_errors() {
+    let parse = |source: &str| Parser::new(source).and_then(|parser| parser.parse());
+
+    assert_eq!(
+        parse(".error \"Unclosed\n"),
+        Err(ParsingError::UnterminatedString {
+            span: Position::new(7, 16),
+        })
+    );
+    assert_eq!(
+        parse(".assert 1 == 1\n"),
+        Err(ParsingError::Unexpected {
+            expected: vec![TokenKind::String],
+            found: TokenKind::Newline,
+            span: Position::new(14, 15),
+        })
+    );
+    assert_eq!(
+        parse(".warning \"a\" \"b\"\n"),
+        Err(ParsingError::Unexpected {
+            expected: vec![TokenKind::Newline, TokenKind::Eof],
+            found: TokenKind::String,
+            span: Position::new(13, 16),
+        })
+    );
+}
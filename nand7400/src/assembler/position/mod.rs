@@ -81,3 +81,59 @@ impl From<Position> for SourceSpan {
         )
     }
 }
+
+/// Some source code, along with where each of its lines and characters start. This is worked out once, so that the
+/// line a position is on can be found without counting the newlines before it every time, and so that the text of a
+/// position (which counts characters, not bytes) can be sliced out of the source without splitting a character.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceIndex<'a> {
+    /// The source code itself.
+    source: &'a str,
+
+    /// The index of the first character of each line, in order.
+    line_starts: Vec<usize>,
+
+    /// The byte offset of each character, followed by the length of the source in bytes.
+    char_offsets: Vec<usize>,
+}
+
+impl<'a> SourceIndex<'a> {
+    /// Creates a new index of the given source code.
+    pub fn new(source: &'a str) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(
+                source
+                    .chars()
+                    .enumerate()
+                    .filter(|(_, c)| *c == '\n')
+                    .map(|(i, _)| i + 1),
+            )
+            .collect();
+        let char_offsets = source
+            .char_indices()
+            .map(|(offset, _)| offset)
+            .chain(std::iter::once(source.len()))
+            .collect();
+
+        Self {
+            source,
+            line_starts,
+            char_offsets,
+        }
+    }
+
+    /// Gets the line that a position starts on, starting at 1.
+    pub fn line(&self, position: Position) -> usize {
+        self.line_starts
+            .partition_point(|start| *start <= position.starting_char())
+    }
+
+    /// Gets the text of the source code that a position covers.
+    pub fn text(&self, position: Position) -> &'a str {
+        let last = self.char_offsets.len() - 1;
+        let start = self.char_offsets[(position.start as usize).min(last)];
+        let end = self.char_offsets[(position.end as usize).min(last)];
+
+        &self.source[start..end]
+    }
+}
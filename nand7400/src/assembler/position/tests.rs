@@ -40,3 +40,27 @@ fn test_non_adjacent_joins() {
     assert_eq!(joined.ending_char(), 5);
     assert_eq!(joined.len(), 6);
 }
+
+/// Tests finding the line that a position is on.
+#[test]
+fn test_source_index_lines() {
+    let index = SourceIndex::new("nop\n\nlabel:\n  hlt");
+
+    assert_eq!(index.line(Position::new(0, 3)), 1);
+    assert_eq!(index.line(Position::new(3, 4)), 1);
+    assert_eq!(index.line(Position::from(4)), 2);
+    assert_eq!(index.line(Position::new(5, 11)), 3);
+    assert_eq!(index.line(Position::new(14, 17)), 4);
+}
+
+/// Tests getting the text of positions in source code that has characters longer than a byte.
+#[test]
+fn test_source_index_text() {
+    let index = SourceIndex::new("; é 日本\nnop");
+
+    assert_eq!(index.text(Position::new(0, 3)), "; é");
+    assert_eq!(index.text(Position::new(4, 6)), "日本");
+    assert_eq!(index.text(Position::new(7, 10)), "nop");
+    assert_eq!(index.text(Position::from(10)), "");
+    assert_eq!(index.line(Position::new(7, 10)), 2);
+}
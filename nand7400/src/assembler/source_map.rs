@@ -0,0 +1,109 @@
+use super::{
+    parser::ast::Label,
+    position::{Position, SourceIndex},
+};
+use std::collections::{BTreeMap, HashMap};
+
+/// A map from addresses in the assembled binary back to the instructions in the source code that produced them.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SourceMap {
+    /// The entries of the map, keyed by the address they start at.
+    entries: BTreeMap<u32, SourceMapEntry>,
+}
+
+/// A single instruction (or `.byte` keyword) in the source map.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SourceMapEntry {
+    /// The first address of the instruction.
+    pub address: u32,
+
+    /// The number of bytes the instruction takes up.
+    pub len: u32,
+
+    /// The span of the instruction in the source code.
+    pub span: Position,
+
+    /// The line the instruction is on in the source code, starting at 1.
+    pub line: usize,
+
+    /// Whether this is code or data.
+    pub kind: SourceMapEntryKind,
+}
+
+/// What a source map entry holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SourceMapEntryKind {
+    /// An opcode and its arguments.
+    Code,
+
+    /// Raw data, from a `.byte` keyword.
+    Data,
+}
+
+/// Public API for the source map.
+impl SourceMap {
+    /// Creates a new, empty source map.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an instruction to the source map. `source` is the source code, used to find the line the instruction is
+    /// on. Empty instructions are ignored.
+    pub fn insert(
+        &mut self,
+        source: &SourceIndex,
+        address: u32,
+        len: u32,
+        span: Position,
+        kind: SourceMapEntryKind,
+    ) {
+        if len == 0 {
+            return;
+        }
+
+        let line = source.line(span);
+
+        self.entries.insert(
+            address,
+            SourceMapEntry {
+                address,
+                len,
+                span,
+                line,
+                kind,
+            },
+        );
+    }
+
+    /// Gets the instruction that covers an address, if any.
+    pub fn get(&self, address: u32) -> Option<&SourceMapEntry> {
+        self.entries
+            .range(..=address)
+            .next_back()
+            .map(|(_, entry)| entry)
+            .filter(|entry| (address as usize) < entry.address as usize + entry.len as usize)
+    }
+
+    /// Iterates over all of the instructions in the source map, in order of address.
+    pub fn entries(&self) -> impl Iterator<Item = &SourceMapEntry> {
+        self.entries.values()
+    }
+}
+
+/// Finds the closest label at or before an address, returning it along with how far past the label the address is.
+/// Labels at the same address are broken alphabetically, so the result is stable.
+pub fn nearest_label(symbols: &HashMap<Label, u32>, address: u32) -> Option<(&Label, u32)> {
+    symbols
+        .iter()
+        .filter(|(_, label_address)| **label_address <= address)
+        .max_by(|(a, a_address), (b, b_address)| a_address.cmp(b_address).then(b.cmp(a)))
+        .map(|(label, label_address)| (label, address - label_address))
+}
+
+/// Gets a line (starting at 1) of the source code, without the trailing newline.
+pub fn source_line(source: &str, line: usize) -> Option<&str> {
+    source
+        .lines()
+        .nth(line.checked_sub(1)?)
+        .map(|line| line.trim_end_matches('\r'))
+}
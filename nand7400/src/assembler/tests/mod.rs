@@ -1,7 +1,49 @@
 #![cfg(test)]
 
 use super::*;
-use crate::assembler::config::Opcode;
+use crate::assembler::{
+    config::{
+        pseudos::PseudoInstruction,
+        regions::{Region, RegionKind},
+        vectors::Vector,
+        ArgConstraint, Opcode, Register,
+    },
+    parser::{errors::ParsingError, lexer::token::TokenKind},
+};
+
+/// The configuration that most tests assemble with: an opcode with no arguments, and opcodes with a few of the common
+/// argument kinds. Tests that need more than this build their own configuration on top of it.
+fn test_config() -> AssemblerConfig {
+    AssemblerConfig {
+        opcodes: vec![
+            Opcode {
+                mnemonic: "nop".to_string(),
+                binary: 0x00,
+                args: vec![],
+                ..Default::default()
+            },
+            Opcode {
+                mnemonic: "jmp".to_string(),
+                binary: 0x01,
+                args: vec![OpcodeArg::Addr],
+                ..Default::default()
+            },
+            Opcode {
+                mnemonic: "lda".to_string(),
+                binary: 0x02,
+                args: vec![OpcodeArg::Immediate],
+                ..Default::default()
+            },
+            Opcode {
+                mnemonic: "ldi".to_string(),
+                binary: 0x03,
+                args: vec![OpcodeArg::Imm16],
+                ..Default::default()
+            },
+        ],
+        ..Default::default()
+    }
+}
 
 /// Test the `.byte` and `.org` keywords.
 #[test]
@@ -14,7 +56,10 @@ fn assemble_keywords() {
                         .byte 0x04\n\
                         .byte 0x05\n";
 
-    let mut assembler = Assembler::new(AssemblerConfig { opcodes: vec![] });
+    let mut assembler = Assembler::new(AssemblerConfig {
+        opcodes: vec![],
+        ..Default::default()
+    });
     let result = assembler.assemble(source).unwrap();
 
     assert_eq!(
@@ -28,6 +73,25 @@ fn assemble_keywords() {
     );
 }
 
+/// Test that negative numbers in `.byte` keep their sign in as few bytes as possible.
+#[test]
+fn assemble_negative_bytes() {
+    let source = ".byte -1 -128 -129 -200 0x80 0x1FF\n";
+
+    assert_eq!(
+        Assembler::new(test_config()).assemble(source),
+        Ok(vec![0xFF, 0x80, 0x7F, 0xFF, 0x38, 0xFF, 0x80, 0xFF, 0x01])
+    );
+    assert_eq!(
+        Assembler::new(AssemblerConfig {
+            endianness: Endianness::Big,
+            ..test_config()
+        })
+        .assemble(source),
+        Ok(vec![0xFF, 0x80, 0xFF, 0x7F, 0xFF, 0x38, 0x80, 0x01, 0xFF])
+    );
+}
+
 /// Test opcodes with no arguments (i.e. implied opcodes)
 #[test]
 fn assemble_opcodes_implied() {
@@ -47,48 +111,58 @@ fn assemble_opcodes_implied() {
                 mnemonic: "nop".to_string(),
                 binary: 0x00,
                 args: vec![],
+                ..Default::default()
             },
             Opcode {
                 mnemonic: "hlt".to_string(),
                 binary: 0x01,
                 args: vec![],
+                ..Default::default()
             },
             Opcode {
                 mnemonic: "clc".to_string(),
                 binary: 0x02,
                 args: vec![],
+                ..Default::default()
             },
             Opcode {
                 mnemonic: "sec".to_string(),
                 binary: 0x03,
                 args: vec![],
+                ..Default::default()
             },
             Opcode {
                 mnemonic: "cli".to_string(),
                 binary: 0x04,
                 args: vec![],
+                ..Default::default()
             },
             Opcode {
                 mnemonic: "sei".to_string(),
                 binary: 0x05,
                 args: vec![],
+                ..Default::default()
             },
             Opcode {
                 mnemonic: "cld".to_string(),
                 binary: 0x06,
                 args: vec![],
+                ..Default::default()
             },
             Opcode {
                 mnemonic: "sed".to_string(),
                 binary: 0x07,
                 args: vec![],
+                ..Default::default()
             },
             Opcode {
                 mnemonic: "clv".to_string(),
                 binary: 0x08,
                 args: vec![],
+                ..Default::default()
             },
         ],
+        ..Default::default()
     });
 
     let result = assembler.assemble(source).unwrap();
@@ -121,13 +195,16 @@ fn test_opcode_1_arg() {
                 mnemonic: "lda".to_string(),
                 binary: 0x00,
                 args: vec![OpcodeArg::Immediate],
+                ..Default::default()
             },
             Opcode {
                 mnemonic: "ldb".to_string(),
                 binary: 0x01,
                 args: vec![OpcodeArg::Indirect],
+                ..Default::default()
             },
         ],
+        ..Default::default()
     });
 
     let result = assembler.assemble(source).unwrap();
@@ -148,11 +225,13 @@ fn assemble_opcodes_many_args() {
                 mnemonic: "add".to_string(),
                 binary: 0x00,
                 args: vec![OpcodeArg::Immediate, OpcodeArg::Indirect],
+                ..Default::default()
             },
             Opcode {
                 mnemonic: "sub".to_string(),
                 binary: 0x01,
                 args: vec![OpcodeArg::Indirect, OpcodeArg::Immediate],
+                ..Default::default()
             },
             Opcode {
                 mnemonic: "foo".to_string(),
@@ -163,8 +242,10 @@ fn assemble_opcodes_many_args() {
                     OpcodeArg::Indirect,
                     OpcodeArg::Indirect,
                 ],
+                ..Default::default()
             },
         ],
+        ..Default::default()
     });
 
     let result = assembler.assemble(source).unwrap();
@@ -186,10 +267,1262 @@ fn assemble_labels_args() {
             mnemonic: "jmp".to_string(),
             binary: 0xF1,
             args: vec![OpcodeArg::Immediate, OpcodeArg::Immediate],
+            ..Default::default()
         }],
+        ..Default::default()
     });
 
     let result = assembler.assemble(source).unwrap();
 
     assert_eq!(result, vec![0x00, 0x00, 0x00, 0x00, 0xF1, 0x04, 0x00,]);
 }
+
+/// Test that the source map points each address back to the instruction that produced it.
+#[test]
+fn assemble_source_map() {
+    let source = "start:\n\
+                  \tnop\n\
+                  \tlda #0x01\n\
+                  .byte 0x02 0x03\n";
+
+    let mut assembler = Assembler::new(AssemblerConfig {
+        opcodes: vec![
+            Opcode {
+                mnemonic: "nop".to_string(),
+                binary: 0x00,
+                args: vec![],
+                ..Default::default()
+            },
+            Opcode {
+                mnemonic: "lda".to_string(),
+                binary: 0x01,
+                args: vec![OpcodeArg::Immediate],
+                ..Default::default()
+            },
+        ],
+        ..Default::default()
+    });
+
+    let (binary, _, source_map) = assembler.assemble_with_source_map(source).unwrap();
+
+    assert_eq!(binary, vec![0x00, 0x01, 0x01, 0x02, 0x03]);
+
+    assert_eq!(source_map.get(0x00).unwrap().line, 2);
+    assert_eq!(source_map.get(0x01).unwrap().line, 3);
+    assert_eq!(source_map.get(0x02).unwrap().address, 0x01);
+    assert_eq!(source_map.get(0x04).unwrap().line, 4);
+    assert_eq!(source_map.get(0x04).unwrap().len, 2);
+    assert_eq!(source_map.get(0x05), None);
+    assert_eq!(source_map.entries().count(), 3);
+}
+
+/// Test that overloaded mnemonics pick the opcode whose arguments match, and the errors when they match no or too
+/// many opcodes.
+#[test]
+fn assemble_overloaded_opcodes() {
+    let source = "lda #5\n\
+                  lda 5\n\
+                  LABEL: lda LABEL\n";
+
+    let mut assembler = Assembler::new(AssemblerConfig {
+        opcodes: vec![
+            Opcode {
+                mnemonic: "lda".to_string(),
+                binary: 0x01,
+                args: vec![OpcodeArg::Immediate],
+                ..Default::default()
+            },
+            Opcode {
+                mnemonic: "lda".to_string(),
+                binary: 0x02,
+                args: vec![OpcodeArg::Indirect],
+                ..Default::default()
+            },
+            Opcode {
+                mnemonic: "lda".to_string(),
+                binary: 0x03,
+                args: vec![OpcodeArg::Immediate, OpcodeArg::Immediate],
+                ..Default::default()
+            },
+            Opcode {
+                mnemonic: "ldb".to_string(),
+                binary: 0x04,
+                args: vec![OpcodeArg::Immediate],
+                ..Default::default()
+            },
+            Opcode {
+                mnemonic: "ldb".to_string(),
+                binary: 0x05,
+                args: vec![OpcodeArg::Immediate],
+                ..Default::default()
+            },
+        ],
+        ..Default::default()
+    });
+
+    let result = assembler.assemble(source).unwrap();
+
+    assert_eq!(result, vec![0x01, 0x05, 0x02, 0x05, 0x03, 0x04, 0x00]);
+
+    assert_eq!(
+        assembler.assemble("lda 1 2 3"),
+        Err(AssemblerError::NoMatchingOpcode {
+            mnemonic: "lda".to_string(),
+            candidates: vec![
+                "lda Immediate".to_string(),
+                "lda Indirect".to_string(),
+                "lda Immediate Immediate".to_string(),
+            ],
+            mnemonic_span: Position::new(0, 3),
+            args_span: Position::new(0, 9),
+        })
+    );
+
+    assert_eq!(
+        assembler.assemble("ldb #1"),
+        Err(AssemblerError::AmbiguousOpcode {
+            mnemonic: "ldb".to_string(),
+            candidates: vec!["ldb Immediate".to_string(), "ldb Immediate".to_string()],
+            mnemonic_span: Position::new(0, 3),
+            args_span: Position::new(0, 6),
+        })
+    );
+}
+
+/// Test that the sized argument kinds are encoded to the right number of bytes, including labels used before they're
+/// defined, and the errors for arguments that don't fit in their kind.
+#[test]
+fn assemble_sized_args() {
+    let source = "START: ldi #0x12\n\
+                  ldi #-1\n\
+                  ldw #0x1234\n\
+                  ldw END\n\
+                  jmp END\n\
+                  bra START\n\
+                  END: bra END\n";
+
+    let mut assembler = Assembler::new(AssemblerConfig {
+        opcodes: vec![
+            Opcode {
+                mnemonic: "ldi".to_string(),
+                binary: 0x10,
+                args: vec![OpcodeArg::Imm8],
+                ..Default::default()
+            },
+            Opcode {
+                mnemonic: "ldw".to_string(),
+                binary: 0x11,
+                args: vec![OpcodeArg::Imm16],
+                ..Default::default()
+            },
+            Opcode {
+                mnemonic: "jmp".to_string(),
+                binary: 0x12,
+                args: vec![OpcodeArg::Addr],
+                ..Default::default()
+            },
+            Opcode {
+                mnemonic: "bra".to_string(),
+                binary: 0x13,
+                args: vec![OpcodeArg::Rel8],
+                ..Default::default()
+            },
+        ],
+        ..Default::default()
+    });
+
+    let result = assembler.assemble(source).unwrap();
+
+    assert_eq!(
+        result,
+        vec![
+            0x10, 0x12, // ldi #0x12
+            0x10, 0xFF, // ldi #-1
+            0x11, 0x34, 0x12, // ldw #0x1234
+            0x11, 0x0F, 0x00, // ldw END
+            0x12, 0x0F, 0x00, // jmp END
+            0x13, 0xF1, // bra START
+            0x13, 0xFE, // END: bra END
+        ]
+    );
+
+    assert_eq!(
+        assembler.assemble("ldi #0x123"),
+        Err(AssemblerError::ArgOutOfRange {
+            mnemonic: "ldi".to_string(),
+            expected: OpcodeArg::Imm8,
+            value: 0x123,
+            mnemonic_span: Position::new(0, 3),
+            arg_span: Position::new(4, 10),
+        })
+    );
+
+    assert_eq!(
+        assembler.assemble("ldi 5"),
+        Err(AssemblerError::WrongArgType {
+            mnemonic: "ldi".to_string(),
+            expected: OpcodeArg::Imm8,
+            given: OpcodeArg::Indirect,
+            mnemonic_span: Position::new(0, 3),
+            arg_span: Position::new(4, 5),
+        })
+    );
+
+    assert_eq!(
+        assembler.assemble(".org 0x200\nbra 0"),
+        Err(AssemblerError::RelativeOutOfRange {
+            mnemonic: "bra".to_string(),
+            target: 0,
+            offset: -0x202,
+            mnemonic_span: Position::new(11, 14),
+            arg_span: Position::new(15, 16),
+        })
+    );
+}
+
+/// Test that going back with `.org` overwrites what was there, rather than shifting it along.
+#[test]
+fn assemble_org_overwrites() {
+    let result = Assembler::new(test_config())
+        .assemble(".byte 1 2 3\n.org 1\n.byte 4\n")
+        .unwrap();
+
+    assert_eq!(result, vec![0x01, 0x04, 0x03]);
+}
+
+/// Test that bit field arguments are OR-ed into the opcode and the bytes after it, and the error for an argument that
+/// doesn't fit in its bit field.
+#[test]
+fn assemble_bit_fields() {
+    let source = "mov 1 2\n\
+                  out #0x02 5\n\
+                  sel 3\n\
+                  END: .byte END\n";
+
+    let mut assembler = Assembler::new(AssemblerConfig {
+        opcodes: vec![
+            Opcode {
+                mnemonic: "mov".to_string(),
+                binary: 0b01_000_000,
+                args: vec![
+                    OpcodeArg::Field {
+                        byte: 0,
+                        offset: 3,
+                        width: 3,
+                    },
+                    OpcodeArg::Field {
+                        byte: 0,
+                        offset: 0,
+                        width: 3,
+                    },
+                ],
+                ..Default::default()
+            },
+            Opcode {
+                mnemonic: "out".to_string(),
+                binary: 0x80,
+                args: vec![
+                    OpcodeArg::Imm8,
+                    OpcodeArg::Field {
+                        byte: 1,
+                        offset: 4,
+                        width: 4,
+                    },
+                ],
+                ..Default::default()
+            },
+            Opcode {
+                mnemonic: "sel".to_string(),
+                binary: 0x90,
+                args: vec![OpcodeArg::Field {
+                    byte: 1,
+                    offset: 0,
+                    width: 2,
+                }],
+                ..Default::default()
+            },
+        ],
+        ..Default::default()
+    });
+
+    let result = assembler.assemble(source).unwrap();
+
+    assert_eq!(
+        result,
+        vec![
+            0b01_001_010, // mov 1 2
+            0x80,
+            0x52, // out #0x02 5
+            0x90,
+            0x03, // sel 3
+            0x05,
+            0x00, // END: .byte END
+        ]
+    );
+    assert_eq!(
+        assembler.assemble("mov 1 8"),
+        Err(AssemblerError::FieldOutOfRange {
+            mnemonic: "mov".to_string(),
+            width: 3,
+            value: 8,
+            mnemonic_span: Position::new(0, 3),
+            arg_span: Position::new(6, 7),
+        })
+    );
+
+    // Fields that go past the end of their byte can only take values that fit in the bits that are left.
+    let mut assembler = Assembler::new(AssemblerConfig {
+        opcodes: vec![Opcode {
+            mnemonic: "sel".to_string(),
+            binary: 0x00,
+            args: vec![OpcodeArg::Field {
+                byte: 0,
+                offset: 6,
+                width: 4,
+            }],
+            ..Default::default()
+        }],
+        ..Default::default()
+    });
+
+    assert_eq!(assembler.assemble("sel 3"), Ok(vec![0b11_000_000]));
+    assert_eq!(
+        assembler.assemble("sel 4"),
+        Err(AssemblerError::FieldOutOfRange {
+            mnemonic: "sel".to_string(),
+            width: 2,
+            value: 4,
+            mnemonic_span: Position::new(0, 3),
+            arg_span: Position::new(4, 5),
+        })
+    );
+}
+
+/// Test that registers are encoded as their binary representation, both as whole bytes and in bit fields, and the
+/// errors for registers of the wrong class and labels with the same name as registers.
+#[test]
+fn assemble_registers() {
+    let source = "ld A, #5\n\
+                  ldx X DATA\n\
+                  mov B, X\n\
+                  DATA: .byte 0xFF\n";
+
+    let mut assembler = Assembler::new(AssemblerConfig {
+        opcodes: vec![
+            Opcode {
+                mnemonic: "ld".to_string(),
+                binary: 0x20,
+                args: vec![
+                    OpcodeArg::RegisterClass {
+                        class: "general".to_string(),
+                    },
+                    OpcodeArg::Imm8,
+                ],
+                ..Default::default()
+            },
+            Opcode {
+                mnemonic: "ldx".to_string(),
+                binary: 0x30,
+                args: vec![
+                    OpcodeArg::RegisterClass {
+                        class: "index".to_string(),
+                    },
+                    OpcodeArg::Addr,
+                ],
+                ..Default::default()
+            },
+            Opcode {
+                mnemonic: "mov".to_string(),
+                binary: 0b01_000_000,
+                args: vec![
+                    OpcodeArg::Field {
+                        byte: 0,
+                        offset: 3,
+                        width: 3,
+                    },
+                    OpcodeArg::Field {
+                        byte: 0,
+                        offset: 0,
+                        width: 3,
+                    },
+                ],
+                ..Default::default()
+            },
+        ],
+        registers: vec![
+            Register {
+                name: "A".to_string(),
+                binary: 0,
+                class: "general".to_string(),
+            },
+            Register {
+                name: "B".to_string(),
+                binary: 1,
+                class: "general".to_string(),
+            },
+            Register {
+                name: "X".to_string(),
+                binary: 2,
+                class: "index".to_string(),
+            },
+        ],
+        ..Default::default()
+    });
+
+    let result = assembler.assemble(source).unwrap();
+
+    assert_eq!(
+        result,
+        vec![
+            0x20, 0x00, 0x05, // ld A, #5
+            0x30, 0x02, 0x08, 0x00, // ldx X DATA
+            0x4A, // mov B, X
+            0xFF, // DATA: .byte 0xFF
+        ]
+    );
+
+    assert_eq!(
+        assembler.assemble("ld X, #5"),
+        Err(AssemblerError::WrongArgType {
+            mnemonic: "ld".to_string(),
+            expected: OpcodeArg::RegisterClass {
+                class: "general".to_string(),
+            },
+            given: OpcodeArg::RegisterClass {
+                class: "index".to_string(),
+            },
+            mnemonic_span: Position::new(0, 2),
+            arg_span: Position::new(3, 4),
+        })
+    );
+
+    assert_eq!(
+        assembler.assemble("A: ld A, #5"),
+        Err(AssemblerError::Parsing {
+            source: ParsingError::LabelIsRegister {
+                name: "A".to_string(),
+                span: Position::new(0, 1),
+            },
+        })
+    );
+    // Only one comma can go between arguments, and not after the last one.
+    assert_eq!(
+        assembler.assemble("ld A,,#5"),
+        Err(AssemblerError::Parsing {
+            source: ParsingError::Unexpected {
+                expected: vec![
+                    TokenKind::Number,
+                    TokenKind::Plus,
+                    TokenKind::Minus,
+                    TokenKind::Hash
+                ],
+                found: TokenKind::Comma,
+                span: Position::new(5, 6),
+            },
+        })
+    );
+    assert_eq!(
+        assembler.assemble("ld A, #5,\nnop"),
+        Err(AssemblerError::Parsing {
+            source: ParsingError::Unexpected {
+                expected: vec![TokenKind::Ident, TokenKind::Number, TokenKind::Hash],
+                found: TokenKind::Newline,
+                span: Position::new(9, 10),
+            },
+        })
+    );
+}
+
+/// Test that each addressing mode picks the right opcode, for both numbers and labels, and that indexing by a register
+/// of the wrong class doesn't match any addressing mode.
+#[test]
+fn assemble_addressing_modes() {
+    let source = "lda 0x1234\n\
+                  lda (0x1234)\n\
+                  lda [PTR]\n\
+                  lda PTR,X\n\
+                  lda (PTR,X)\n\
+                  lda (PTR),X\n\
+                  lda #5\n\
+                  PTR: .byte 0xFF\n";
+
+    let indexed = || "index".to_string();
+    let mut assembler = Assembler::new(AssemblerConfig {
+        opcodes: vec![
+            Opcode {
+                mnemonic: "lda".to_string(),
+                binary: 0xA0,
+                args: vec![OpcodeArg::Addr],
+                ..Default::default()
+            },
+            Opcode {
+                mnemonic: "lda".to_string(),
+                binary: 0xA1,
+                args: vec![OpcodeArg::AddrIndirect],
+                ..Default::default()
+            },
+            Opcode {
+                mnemonic: "lda".to_string(),
+                binary: 0xA2,
+                args: vec![OpcodeArg::AddrIndexed { class: indexed() }],
+                ..Default::default()
+            },
+            Opcode {
+                mnemonic: "lda".to_string(),
+                binary: 0xA3,
+                args: vec![OpcodeArg::AddrIndexedIndirect { class: indexed() }],
+                ..Default::default()
+            },
+            Opcode {
+                mnemonic: "lda".to_string(),
+                binary: 0xA4,
+                args: vec![OpcodeArg::AddrIndirectIndexed { class: indexed() }],
+                ..Default::default()
+            },
+            Opcode {
+                mnemonic: "lda".to_string(),
+                binary: 0xA5,
+                args: vec![OpcodeArg::Imm8],
+                ..Default::default()
+            },
+            Opcode {
+                mnemonic: "stx".to_string(),
+                binary: 0xB0,
+                args: vec![
+                    OpcodeArg::Addr,
+                    OpcodeArg::RegisterClass { class: indexed() },
+                ],
+                ..Default::default()
+            },
+        ],
+        registers: vec![
+            Register {
+                name: "A".to_string(),
+                binary: 0,
+                class: "general".to_string(),
+            },
+            Register {
+                name: "X".to_string(),
+                binary: 1,
+                class: "index".to_string(),
+            },
+        ],
+        ..Default::default()
+    });
+
+    let result = assembler.assemble(source).unwrap();
+
+    assert_eq!(
+        result,
+        vec![
+            0xA0, 0x34, 0x12, // lda 0x1234
+            0xA1, 0x34, 0x12, // lda (0x1234)
+            0xA1, 0x14, 0x00, // lda [PTR]
+            0xA2, 0x14, 0x00, // lda PTR,X
+            0xA3, 0x14, 0x00, // lda (PTR,X)
+            0xA4, 0x14, 0x00, // lda (PTR),X
+            0xA5, 0x05, // lda #5
+            0xFF, // PTR: .byte 0xFF
+        ]
+    );
+
+    assert!(matches!(
+        assembler.assemble("lda 0x10,A"),
+        Err(AssemblerError::NoMatchingOpcode { .. })
+    ));
+
+    // With a space after the comma, it's an address and a register rather than an indexed address.
+    assert_eq!(
+        assembler.assemble("stx 0x10, X"),
+        Ok(vec![0xB0, 0x10, 0x00, 0x01])
+    );
+    assert!(matches!(
+        assembler.assemble("stx 0x10,X"),
+        Err(AssemblerError::WrongNumArgs { .. })
+    ));
+
+    // Labels can't be offset outside of braces, and can't be used in braces at all.
+    assert_eq!(
+        assembler.assemble("lda PTR+1\nPTR: .byte 0"),
+        Err(AssemblerError::Parsing {
+            source: ParsingError::UnbracedExpression {
+                span: Position::new(4, 8),
+            }
+        })
+    );
+
+    // `.org` takes a plain address or a label defined before it.
+    assert_eq!(
+        assembler.assemble(".org (0x10)"),
+        Err(AssemblerError::WrongArgType {
+            mnemonic: ".org".to_string(),
+            expected: OpcodeArg::Addr,
+            given: OpcodeArg::AddrIndirect,
+            mnemonic_span: Position::new(0, 4),
+            arg_span: Position::new(5, 11),
+        })
+    );
+    assert_eq!(
+        assembler.assemble(".org"),
+        Err(AssemblerError::WrongNumArgs {
+            mnemonic: ".org".to_string(),
+            expected: 1,
+            given: 0,
+            mnemonic_span: Position::new(0, 4),
+            args_span: Position::new(0, 4),
+        })
+    );
+    assert_eq!(
+        assembler.assemble("START: .org START\n.byte 1"),
+        Ok(vec![0x01])
+    );
+}
+
+/// Test that addresses, labels, and words are as wide as configured, in the configured byte order.
+#[test]
+fn assemble_address_widths() {
+    let result = Assembler::new(AssemblerConfig {
+        address_bits: 8,
+        ..test_config()
+    })
+    .assemble("jmp END\nEND: lda END\n")
+    .unwrap();
+
+    assert_eq!(result, vec![0x01, 0x02, 0x02, 0x02]);
+
+    let result = Assembler::new(AssemblerConfig {
+        word_size: 2,
+        endianness: Endianness::Big,
+        ..test_config()
+    })
+    .assemble("ldi #0x1234\njmp END\nEND: .word 0xABCD -1\n.byte 0x0102\n")
+    .unwrap();
+
+    assert_eq!(
+        result,
+        vec![0x03, 0x12, 0x34, 0x01, 0x00, 0x06, 0xAB, 0xCD, 0xFF, 0xFF, 0x01, 0x02]
+    );
+
+    let result = Assembler::new(AssemblerConfig {
+        address_bits: 24,
+        word_size: 3,
+        endianness: Endianness::Big,
+        ..test_config()
+    })
+    .assemble("jmp END\n.org 0x012345\nEND: .word END\n")
+    .unwrap();
+
+    assert_eq!(result[..4], [0x01, 0x01, 0x23, 0x45]);
+    assert_eq!(result[0x012345..], [0x01, 0x23, 0x45]);
+}
+
+/// Test the errors for addresses and words that don't fit, and for going past the end of the address space.
+#[test]
+fn assemble_address_width_errors() {
+    let mut assembler = Assembler::new(AssemblerConfig {
+        address_bits: 12,
+        word_size: 2,
+        ..test_config()
+    });
+
+    assert_eq!(
+        assembler.assemble("jmp 0x1000"),
+        Err(AssemblerError::AddressOutOfRange {
+            value: 0x1000,
+            bits: 12,
+            span: Position::new(4, 10),
+        })
+    );
+    assert_eq!(
+        assembler.assemble(".org 0x1000"),
+        Err(AssemblerError::AddressOutOfRange {
+            value: 0x1000,
+            bits: 12,
+            span: Position::new(5, 11),
+        })
+    );
+    assert_eq!(
+        assembler.assemble(".word 0x10000"),
+        Err(AssemblerError::WordOutOfRange {
+            value: 0x10000,
+            size: 2,
+            span: Position::new(6, 13),
+        })
+    );
+
+    // The last byte can be at the highest address, but nothing can go after it.
+    let mut assembler = Assembler::new(test_config());
+
+    assert!(assembler.assemble(".org 0xFFFF\nnop").is_ok());
+    assert_eq!(
+        assembler.assemble(".org 0xFFFF\nnop\nnop"),
+        Err(AssemblerError::AddressSpaceOverflow {
+            max: 0xFFFF,
+            span: Position::new(16, 19),
+        })
+    );
+    assert!(matches!(
+        assembler.assemble(".org 0xFFFE\njmp 0x0000"),
+        Err(AssemblerError::AddressSpaceOverflow { .. })
+    ));
+
+    // Configurations that weren't loaded can still have word sizes that can't be encoded.
+    for size in [0, 5] {
+        let mut assembler = Assembler::new(AssemblerConfig {
+            word_size: size,
+            ..test_config()
+        });
+
+        assert_eq!(
+            assembler.assemble(
+                ".word 1
+nop"
+            ),
+            Err(AssemblerError::InvalidWordSize { size })
+        );
+    }
+}
+
+/// Test that code and data can only be placed in ROM, and that the usage of each region is reported.
+#[test]
+fn assemble_regions() {
+    let region = |name: &str, start, size, kind| Region {
+        name: name.to_string(),
+        start,
+        size,
+        kind,
+    };
+
+    let mut assembler = Assembler::new(AssemblerConfig {
+        regions: vec![
+            region("rom", 0x0000, 0x8000, RegionKind::Rom),
+            region("ram", 0x8000, 0x7F00, RegionKind::Ram),
+            region("io", 0xFF00, 0x0080, RegionKind::Io),
+        ],
+        ..test_config()
+    });
+
+    let (_, _, source_map) = assembler
+        .assemble_with_source_map("jmp END\n.org 0x7FFE\nEND: .byte 0x01 0x02\n")
+        .unwrap();
+    let usage = assembler.config.region_usage(&source_map);
+
+    assert_eq!(
+        usage.iter().map(|usage| usage.used).collect::<Vec<_>>(),
+        vec![5, 0, 0]
+    );
+    assert_eq!(usage[0].free(), 0x8000 - 5);
+
+    assert_eq!(
+        assembler.assemble(".org 0x8000\nnop"),
+        Err(AssemblerError::OutsideRom {
+            address: 0x8000,
+            region: "ram".to_string(),
+            span: Position::new(12, 15),
+        })
+    );
+    assert_eq!(
+        assembler.assemble(".org 0xFF80\n.byte 0x01"),
+        Err(AssemblerError::UnmappedAddress {
+            address: 0xFF80,
+            span: Position::new(12, 22),
+        })
+    );
+    assert_eq!(
+        assembler.assemble(".org 0x7FFF\njmp 0x0000"),
+        Err(AssemblerError::RegionOverflow {
+            region: "rom".to_string(),
+            max: 0x7FFF,
+            span: Position::new(12, 22),
+        })
+    );
+
+    // Skipping over RAM with `.org` is fine, as nothing is placed there.
+    assert!(assembler.assemble(".org 0x8000\n.org 0x0000\nnop").is_ok());
+}
+
+/// Test that pseudo-instructions are expanded before labels are laid out, and that errors in their expansion point to
+/// where they were used.
+#[test]
+fn assemble_pseudo_instructions() {
+    let pseudo = |mnemonic: &str, params: &[&str], body: &[&str]| PseudoInstruction {
+        mnemonic: mnemonic.to_string(),
+        params: params.iter().map(|param| param.to_string()).collect(),
+        body: body.iter().map(|line| line.to_string()).collect(),
+    };
+
+    let mut assembler = Assembler::new(AssemblerConfig {
+        pseudo_instructions: vec![
+            pseudo("skip2", &[], &["nop", "nop"]),
+            pseudo("goto", &["target"], &["jmp target"]),
+            pseudo("ld16", &["value"], &["ldi value", "lda #0"]),
+            pseudo("big", &[], &["lda #0x100"]),
+        ],
+        ..test_config()
+    });
+
+    let result = assembler
+        .assemble("goto END\nskip2\nEND: ld16 #0x1234\n")
+        .unwrap();
+
+    assert_eq!(
+        result,
+        vec![0x01, 0x05, 0x00, 0x00, 0x00, 0x03, 0x34, 0x12, 0x02, 0x00]
+    );
+
+    assert_eq!(
+        assembler.assemble("nop\nld16 #0x12345"),
+        Err(AssemblerError::ArgOutOfRange {
+            mnemonic: "ldi".to_string(),
+            expected: OpcodeArg::Imm16,
+            value: 0x12345,
+            mnemonic_span: Position::new(4, 8),
+            arg_span: Position::new(9, 17),
+        })
+    );
+    assert_eq!(
+        assembler.assemble("nop\nbig"),
+        Err(AssemblerError::ArgOutOfRange {
+            mnemonic: "lda".to_string(),
+            expected: OpcodeArg::Immediate,
+            value: 0x100,
+            mnemonic_span: Position::new(4, 7),
+            arg_span: Position::new(4, 7),
+        })
+    );
+    assert_eq!(
+        assembler.assemble("goto"),
+        Err(AssemblerError::WrongNumArgs {
+            mnemonic: "goto".to_string(),
+            expected: 1,
+            given: 0,
+            mnemonic_span: Position::new(0, 4),
+            args_span: Position::new(0, 4),
+        })
+    );
+}
+
+/// Test that listings show pseudo-instructions along with what they expanded to, and have the right text and lines
+/// for source code with characters longer than a byte.
+#[test]
+fn assemble_listing() {
+    let mut assembler = Assembler::new(AssemblerConfig {
+        pseudo_instructions: vec![
+            PseudoInstruction {
+                mnemonic: "goto".to_string(),
+                params: vec!["target".to_string()],
+                body: vec!["jmp target".to_string()],
+            },
+            PseudoInstruction {
+                mnemonic: "ld16".to_string(),
+                params: vec!["value".to_string()],
+                body: vec!["ldi value".to_string(), "lda #0".to_string()],
+            },
+        ],
+        ..test_config()
+    });
+
+    let (_, listing) = assembler
+        .assemble_with_listing("goto END\nEND: ld16 #0x0102\n")
+        .unwrap();
+
+    assert_eq!(
+        listing.to_string(),
+        "    1  0000           goto END\n\
+         \x20   1  0000  01 03 00     jmp END\n\
+         \x20   2  0003           END:\n\
+         \x20   2  0003           ld16 #0x0102\n\
+         \x20   2  0003  03 02 01     ldi #0x0102\n\
+         \x20   2  0006  02 00        lda #0\n"
+    );
+    assert_eq!(
+        listing
+            .lines()
+            .filter(|line| line.expanded)
+            .map(|line| line.bytes.len())
+            .sum::<usize>(),
+        8
+    );
+
+    let (binary, listing) = assembler
+        .assemble_with_listing("; 日本語\nnop\n; é\ngoto END\nEND:")
+        .unwrap();
+
+    assert_eq!(binary, vec![0x00, 0x01, 0x04, 0x00]);
+    assert_eq!(
+        listing.to_string(),
+        "    2  0000  00       nop\n\
+         \x20   4  0001           goto END\n\
+         \x20   4  0001  01 04 00     jmp END\n\
+         \x20   5  0004           END:\n"
+    );
+}
+
+/// Test that aliases assemble to their opcode, and that deprecated opcodes are warned about.
+#[test]
+fn assemble_aliases_and_deprecated_opcodes() {
+    let mut config = test_config();
+
+    config.opcodes[1].aliases = vec!["goto".to_string()];
+    config.opcodes[2].deprecated = true;
+
+    let mut assembler = Assembler::new(config);
+
+    assert_eq!(
+        assembler.assemble("goto 0x1234\nlda #0x05\nnop\n").unwrap(),
+        vec![0x01, 0x34, 0x12, 0x02, 0x05, 0x00]
+    );
+    assert_eq!(
+        assembler.warnings(),
+        [AssemblerWarning::DeprecatedOpcode {
+            mnemonic: "lda".to_string(),
+            span: Position::new(12, 15),
+        }]
+    );
+
+    assembler.assemble("nop").unwrap();
+
+    assert!(assembler.warnings().is_empty());
+}
+
+/// Test that argument values are checked against the opcode's constraints once labels are resolved.
+#[test]
+fn assemble_arg_constraints() {
+    let mut config = test_config();
+
+    config.opcodes[1].constraints = vec![ArgConstraint {
+        max: Some(0x00FF),
+        align: Some(2),
+        ..Default::default()
+    }];
+    config.opcodes[2].constraints = vec![ArgConstraint {
+        allowed: vec![1, 2, 4],
+        ..Default::default()
+    }];
+
+    let mut assembler = Assembler::new(config);
+
+    assert_eq!(
+        assembler
+            .assemble("jmp END\nlda #4\nnop\nEND: nop\n")
+            .unwrap(),
+        vec![0x01, 0x06, 0x00, 0x02, 0x04, 0x00, 0x00]
+    );
+    assert_eq!(
+        assembler.assemble("jmp END\nEND: nop\n"),
+        Err(AssemblerError::ConstraintViolated {
+            mnemonic: "jmp".to_string(),
+            value: 3,
+            constraint: "a multiple of 2".to_string(),
+            mnemonic_span: Position::new(0, 3),
+            arg_span: Position::new(4, 7),
+        })
+    );
+    assert_eq!(
+        assembler.assemble("lda #3\n"),
+        Err(AssemblerError::ConstraintViolated {
+            mnemonic: "lda".to_string(),
+            value: 3,
+            constraint: "one of 1, 2, 4".to_string(),
+            mnemonic_span: Position::new(0, 3),
+            arg_span: Position::new(4, 6),
+        })
+    );
+}
+
+/// Test that `.entry` and `.vector` fill in the vector table and set the entry point, and the errors for vectors that
+/// are missing, set twice, don't exist, or are in the way of the program.
+#[test]
+fn assemble_vectors() {
+    let config = AssemblerConfig {
+        address_bits: 8,
+        vectors: vec![
+            Vector {
+                name: "reset".to_string(),
+                address: 0xFE,
+                entry: true,
+            },
+            Vector {
+                name: "irq".to_string(),
+                address: 0xFF,
+                entry: false,
+            },
+        ],
+        ..test_config()
+    };
+
+    let mut assembler = Assembler::new(config.clone());
+    let binary = assembler
+        .assemble(".entry START\n.vector irq, HANDLER\nlda #1\nSTART: nop\nHANDLER: nop\n")
+        .unwrap();
+
+    assert_eq!(binary.len(), 0x100);
+    assert_eq!(binary[..4], [0x02, 0x01, 0x00, 0x00]);
+    assert_eq!(binary[0xFE..], [0x02, 0x03]);
+    assert_eq!(assembler.entry_point(), Some(0x02));
+
+    // Without any vectors in the configuration, the entry point is still kept.
+    let mut assembler = Assembler::new(test_config());
+
+    assert_eq!(
+        assembler.assemble(".entry 0x1234\nnop\n").unwrap(),
+        vec![0x00]
+    );
+    assert_eq!(assembler.entry_point(), Some(0x1234));
+
+    assembler.assemble("nop\n").unwrap();
+
+    assert_eq!(assembler.entry_point(), None);
+
+    let mut assembler = Assembler::new(config);
+
+    assert_eq!(
+        assembler.assemble(".entry START\nSTART: nop\n"),
+        Err(AssemblerError::MissingVector {
+            name: "irq".to_string(),
+            address: 0xFF,
+        })
+    );
+    assert_eq!(
+        assembler.assemble(".vector nmi, START\nSTART: nop\n"),
+        Err(AssemblerError::VectorDNE {
+            name: "nmi".to_string(),
+            span: Position::new(8, 11),
+        })
+    );
+    assert_eq!(
+        assembler.assemble("; é\n.vector #1, START\nSTART: nop\n"),
+        Err(AssemblerError::VectorDNE {
+            name: "#1".to_string(),
+            span: Position::new(12, 14),
+        })
+    );
+    assert_eq!(
+        assembler.assemble(".entry 0\n.vector reset, 0\n"),
+        Err(AssemblerError::VectorSetTwice {
+            name: "reset".to_string(),
+            span: Position::new(9, 25),
+        })
+    );
+    assert_eq!(
+        assembler.assemble(".entry 0\n.vector irq\n"),
+        Err(AssemblerError::WrongNumArgs {
+            mnemonic: ".vector".to_string(),
+            expected: 2,
+            given: 1,
+            mnemonic_span: Position::new(9, 16),
+            args_span: Position::new(9, 20),
+        })
+    );
+    assert_eq!(
+        assembler.assemble(".entry 0\n.vector irq, 0\n.org 0xFD\nlda #1\n"),
+        Err(AssemblerError::VectorOverlapsCode {
+            name: "reset".to_string(),
+            address: 0xFE,
+            span: Position::new(34, 40),
+        })
+    );
+}
+
+/// Test that struct fields and enum members can be used as arguments, and are exported with the symbols.
+#[test]
+fn assemble_structs_and_enums() {
+    let (binary, ast) = Assembler::new(test_config())
+        .assemble_with_ast(
+            ".struct Sprite\nx 1\ny 1\n.endstruct\n.enum Mode\nOff\nOn\n.endenum\n\
+             START: lda #Sprite.y\njmp Sprite.size\n.byte Mode.On\n",
+        )
+        .unwrap();
+
+    assert_eq!(binary, vec![0x02, 0x01, 0x01, 0x02, 0x00, 0x01]);
+    assert_eq!(
+        ast.export_symbols(),
+        "0x0000 START\n\
+         0x0000 Mode.Off (constant)\n\
+         0x0001 Mode.On (constant)\n\
+         0x0002 Sprite.size (constant)\n\
+         0x0000 Sprite.x (constant)\n\
+         0x0001 Sprite.y (constant)\n"
+    );
+}
+
+/// Test that `.rept` and `.for` blocks are laid out like they were written out in full, and that errors in them say
+/// which iteration they happened in.
+#[test]
+fn assemble_loops() {
+    let mut assembler = Assembler::new(test_config());
+
+    assert_eq!(
+        assembler.assemble(
+            ".for i = 0 to 2\n.byte {i * 16}\n.endfor\n.rept 2\nnop\n.endr\nEND: jmp END\n"
+        ),
+        Ok(vec![0x00, 0x10, 0x20, 0x00, 0x00, 0x01, 0x05, 0x00])
+    );
+    assert_eq!(
+        assembler.assemble(".for i = 0 to 1\nlda #{i * 256}\n.endfor\n"),
+        Err(AssemblerError::InIteration {
+            iteration: 1,
+            source: Box::new(AssemblerError::ArgOutOfRange {
+                mnemonic: "lda".to_string(),
+                expected: OpcodeArg::Immediate,
+                value: 0x100,
+                mnemonic_span: Position::new(16, 19),
+                arg_span: Position::new(20, 30),
+            }),
+            span: Position::new(16, 30),
+        })
+    );
+}
+
+/// Test that `.assert`, `.error`, and `.warning` are worked out once every label has an address, including labels
+/// defined after them.
+#[test]
+fn assemble_messages() {
+    let mut assembler = Assembler::new(test_config());
+    let table = ".assert TABLE / 256 == (END - 1) / 256, \"Table crosses a page\"\n\
+                 .org 0xfe\nTABLE: .byte 1 2\nEND:\n";
+
+    assert!(assembler.assemble(table).is_ok());
+    assert_eq!(
+        assembler.assemble(&table.replace("0xfe", "0xff")),
+        Err(AssemblerError::AssertionFailed {
+            message: "Table crosses a page".to_string(),
+            span: Position::new(0, 62),
+        })
+    );
+    assert_eq!(
+        assembler.assemble("nop\n.warning \"Not tested yet\"\n"),
+        Ok(vec![0x00])
+    );
+    assert_eq!(
+        assembler.warnings(),
+        [AssemblerWarning::UserWarning {
+            message: "Not tested yet".to_string(),
+            span: Position::new(4, 29),
+        }]
+    );
+    assert_eq!(
+        assembler.assemble(".error \"Unsupported\"\n"),
+        Err(AssemblerError::UserError {
+            message: "Unsupported".to_string(),
+            span: Position::new(0, 20),
+        })
+    );
+    assert_eq!(
+        assembler.assemble(".assert ROM_END <= 0x8000, \"Too big\"\n"),
+        Err(AssemblerError::LabelDNE {
+            mnemonic: "ROM_END".to_string(),
+            span: Position::new(8, 15),
+        })
+    );
+    assert_eq!(
+        assembler.assemble("END:\n.assert 1 / END, \"Not at 0\"\n"),
+        Err(AssemblerError::DivisionByZero {
+            span: Position::new(17, 20),
+        })
+    );
+}
+
+/// Test the checksum algorithms against their standard check values.
+#[test]
+fn checksum_algorithms() {
+    let check = |algorithm: ChecksumAlgorithm| algorithm.compute(*b"123456789");
+
+    assert_eq!(check(ChecksumAlgorithm::Sum8), 0xDD);
+    assert_eq!(check(ChecksumAlgorithm::Xor8), 0x31);
+    assert_eq!(check(ChecksumAlgorithm::Crc8), 0xF4);
+    assert_eq!(check(ChecksumAlgorithm::Crc16Ccitt), 0x29B1);
+    assert_eq!(
+        ChecksumAlgorithm::from_name("CRC16"),
+        Some(ChecksumAlgorithm::Crc16Ccitt)
+    );
+}
+
+/// Test that `.checksum` fills in a checksum of the finished binary, leaving its own bytes out of the range.
+#[test]
+fn assemble_checksums() {
+    let mut assembler = Assembler::new(test_config());
+
+    assert_eq!(
+        assembler.assemble(".checksum 0, sum8\n"),
+        Err(AssemblerError::WrongNumArgs {
+            mnemonic: ".checksum".to_string(),
+            expected: 4,
+            given: 2,
+            mnemonic_span: Position::new(0, 9),
+            args_span: Position::new(0, 17),
+        })
+    );
+    assert_eq!(
+        assembler.assemble(".checksum END, sum8, 0, {4}\n.byte 1 2 3\nEND:\n"),
+        Ok(vec![0x01, 0x02, 0x03, 0x06])
+    );
+    assert_eq!(
+        assembler.assemble(".byte 1 2 3\n.checksum 3, crc16, 0, 3\n"),
+        Ok(vec![0x01, 0x02, 0x03, 0xAD, 0xAD])
+    );
+    assert_eq!(
+        assembler.assemble(".byte 1 2\n.checksum 1, xor8, 0, 2\n"),
+        Err(AssemblerError::ChecksumOverlap {
+            address: 1,
+            checksum_span: Position::new(10, 33),
+            span: Position::new(0, 9),
+        })
+    );
+    assert_eq!(
+        assembler.assemble(".checksum 0, md5, 0, 0\n"),
+        Err(AssemblerError::ChecksumAlgorithmDNE {
+            name: "md5".to_string(),
+            span: Position::new(13, 16),
+        })
+    );
+    assert_eq!(
+        assembler.assemble(".checksum 0, xor8, 4, 2\n"),
+        Err(AssemblerError::InvalidChecksumRange {
+            start: 4,
+            end: 2,
+            span: Position::new(19, 23),
+        })
+    );
+    assert_eq!(
+        assembler.assemble("; é\n.checksum 0, #5, 0, 0\n"),
+        Err(AssemblerError::ChecksumAlgorithmDNE {
+            name: "#5".to_string(),
+            span: Position::new(17, 19),
+        })
+    );
+    assert!(assembler
+        .assemble(".checksum 0, sum8, 0xFFFF, 0x10000\n")
+        .is_ok());
+    assert_eq!(
+        assembler.assemble(".checksum 0, sum8, 0, 0x10001\n"),
+        Err(AssemblerError::AddressOutOfRange {
+            value: 0x10001,
+            bits: 16,
+            span: Position::new(22, 29),
+        })
+    );
+    assert_eq!(
+        assembler.assemble(".checksum 0, sum8, 0x10000, 0x10000\n"),
+        Err(AssemblerError::AddressOutOfRange {
+            value: 0x10000,
+            bits: 16,
+            span: Position::new(19, 26),
+        })
+    );
+
+    // A checksum that covers another is filled in after it, whatever order they're written in.
+    assert_eq!(
+        assembler.assemble(".org 2\n.byte 5\n.checksum 0, sum8, 1, 4\n.checksum 1, sum8, 2, 3\n"),
+        Ok(vec![0x0A, 0x05, 0x05])
+    );
+    assert_eq!(
+        assembler.assemble(".checksum 0, sum8, 1, 2\n.checksum 1, sum8, 0, 1\n"),
+        Err(AssemblerError::ChecksumCycle {
+            address: 0,
+            other: 1,
+            checksum_span: Position::new(0, 23),
+            span: Position::new(24, 47),
+        })
+    );
+}
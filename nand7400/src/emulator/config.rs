@@ -0,0 +1,38 @@
+use serde::{Deserialize, Serialize};
+
+/// The configuration for which devices are mapped onto the bus, and where. This is meant to be stored as JSON next
+/// to the `AssemblerConfig` for a program.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub struct DeviceMapConfig {
+    /// The devices to map onto the bus.
+    pub devices: Vec<DeviceConfig>,
+}
+
+/// A single device to map onto the bus.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeviceConfig {
+    /// The name of the device. This is used to look the device up later, and must be unique.
+    pub name: String,
+
+    /// The kind of built-in device to map.
+    pub kind: DeviceKind,
+
+    /// The first address the device is mapped to.
+    pub start: u16,
+}
+
+/// The kinds of built-in devices.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DeviceKind {
+    /// A character output port that prints to stdout. See `ConsolePort`.
+    Console,
+
+    /// An 8-LED register. See `LedRegister`.
+    Leds,
+
+    /// A 4-digit 7-segment display. See `SevenSegment`.
+    SevenSegment,
+
+    /// A keypad input queue. See `Keypad`.
+    Keypad,
+}
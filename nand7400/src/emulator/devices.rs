@@ -0,0 +1,310 @@
+use std::{
+    any::Any,
+    collections::VecDeque,
+    fmt,
+    io::{self, Write},
+};
+
+/// A memory-mapped peripheral. Devices are mapped onto the `Bus` at some starting address, and take up `size()`
+/// addresses from there. All reads and writes to those addresses are sent to the device instead of memory, with the
+/// address given as an offset from the start of the device.
+pub trait Device: fmt::Debug + Any + Send {
+    /// The number of addresses this device takes up on the bus.
+    fn size(&self) -> u16;
+
+    /// Reads a byte from the device at the given offset. This takes `&mut self` because reading from a device can
+    /// have side effects (e.g. popping a key off of a queue).
+    fn read(&mut self, offset: u16) -> u8;
+
+    /// Writes a byte to the device at the given offset.
+    fn write(&mut self, offset: u16, value: u8);
+
+    /// Saves the internal state of the device, so it can be put in a snapshot. Devices without any state don't need
+    /// to implement this.
+    fn save_state(&self) -> Vec<u8> {
+        vec![]
+    }
+
+    /// Restores the internal state of the device from what `save_state` returned. Returns `false` if the state
+    /// isn't valid for this device.
+    fn load_state(&mut self, state: &[u8]) -> bool {
+        state.is_empty()
+    }
+
+    /// Gets the device as an `Any`, so it can be downcasted to its concrete type.
+    fn as_any(&self) -> &dyn Any;
+
+    /// Gets the device as a mutable `Any`, so it can be downcasted to its concrete type.
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+/// A character output port. Every byte written to it is treated as an ASCII character and printed out. Reading from
+/// it always returns 0.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConsolePort {
+    /// Everything that has been written to the port so far.
+    output: Vec<u8>,
+
+    /// Whether or not to also print written characters to stdout.
+    echo: bool,
+}
+
+impl ConsolePort {
+    /// Creates a console port that prints everything written to it to stdout.
+    pub fn stdout() -> Self {
+        Self {
+            output: vec![],
+            echo: true,
+        }
+    }
+
+    /// Creates a console port that only buffers everything written to it, without printing it.
+    pub fn buffered() -> Self {
+        Self {
+            output: vec![],
+            echo: false,
+        }
+    }
+
+    /// Gets everything that has been written to the port so far.
+    pub fn output(&self) -> &[u8] {
+        &self.output
+    }
+
+    /// Takes everything that has been written to the port so far, leaving it empty.
+    pub fn take_output(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.output)
+    }
+}
+
+impl Device for ConsolePort {
+    fn size(&self) -> u16 {
+        1
+    }
+
+    fn read(&mut self, _offset: u16) -> u8 {
+        0
+    }
+
+    fn write(&mut self, _offset: u16, value: u8) {
+        self.output.push(value);
+
+        if self.echo {
+            // If stdout is gone, there's nothing useful we can do about it, so the error is ignored.
+            let mut stdout = io::stdout();
+            let _ = stdout.write_all(&[value]).and_then(|_| stdout.flush());
+        }
+    }
+
+    fn save_state(&self) -> Vec<u8> {
+        self.output.clone()
+    }
+
+    fn load_state(&mut self, state: &[u8]) -> bool {
+        self.output = state.to_vec();
+        true
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// A single register driving 8 LEDs, one per bit. Bit 0 is the first LED.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct LedRegister {
+    /// The current value of the register.
+    value: u8,
+}
+
+impl LedRegister {
+    /// Creates a new LED register with all LEDs off.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Gets the raw value of the register.
+    pub fn value(&self) -> u8 {
+        self.value
+    }
+
+    /// Gets whether or not each LED is on. Index 0 is bit 0.
+    pub fn leds(&self) -> [bool; 8] {
+        std::array::from_fn(|i| self.value & (1 << i) != 0)
+    }
+}
+
+impl Device for LedRegister {
+    fn size(&self) -> u16 {
+        1
+    }
+
+    fn read(&mut self, _offset: u16) -> u8 {
+        self.value
+    }
+
+    fn write(&mut self, _offset: u16, value: u8) {
+        self.value = value;
+    }
+
+    fn save_state(&self) -> Vec<u8> {
+        vec![self.value]
+    }
+
+    fn load_state(&mut self, state: &[u8]) -> bool {
+        match state {
+            [value] => {
+                self.value = *value;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// The segment patterns for the hex digits `0`-`F`. Bit 0 is segment `a`, bit 6 is segment `g`.
+pub const SEVEN_SEGMENT_DIGITS: [u8; 16] = [
+    0x3F, 0x06, 0x5B, 0x4F, 0x66, 0x6D, 0x7D, 0x07, 0x7F, 0x6F, 0x77, 0x7C, 0x39, 0x5E, 0x79, 0x71,
+];
+
+/// A 4-digit 7-segment display with a built-in hex decoder. Each digit has its own address (offset 0 is the leftmost
+/// digit), and the low nibble of the value written to it is decoded into the segments to light up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SevenSegment {
+    /// The raw values written to each digit.
+    digits: [u8; 4],
+}
+
+impl SevenSegment {
+    /// Creates a new 7-segment display with every digit set to `0`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Gets the raw values written to each digit.
+    pub fn digits(&self) -> [u8; 4] {
+        self.digits
+    }
+
+    /// Gets the decoded segments for each digit. Bit 0 is segment `a`, bit 6 is segment `g`.
+    pub fn segments(&self) -> [u8; 4] {
+        self.digits
+            .map(|digit| SEVEN_SEGMENT_DIGITS[(digit & 0x0F) as usize])
+    }
+}
+
+impl fmt::Display for SevenSegment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for digit in self.digits {
+            write!(f, "{:X}", digit & 0x0F)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Device for SevenSegment {
+    fn size(&self) -> u16 {
+        4
+    }
+
+    fn read(&mut self, offset: u16) -> u8 {
+        self.digits[offset as usize]
+    }
+
+    fn write(&mut self, offset: u16, value: u8) {
+        self.digits[offset as usize] = value;
+    }
+
+    fn save_state(&self) -> Vec<u8> {
+        self.digits.to_vec()
+    }
+
+    fn load_state(&mut self, state: &[u8]) -> bool {
+        match state.try_into() {
+            Ok(digits) => {
+                self.digits = digits;
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// A keypad with a queue of pressed keys. Offset 0 is the status register, which holds the number of keys waiting in
+/// the queue, and offset 1 is the data register, which pops the next key off of the queue (or returns 0 if it's
+/// empty). Writes are ignored.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Keypad {
+    /// The keys that have been pressed but not read yet, oldest first.
+    queue: VecDeque<u8>,
+}
+
+impl Keypad {
+    /// Creates a new keypad with no keys pressed.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Presses a key, adding it to the back of the queue.
+    pub fn press(&mut self, key: u8) {
+        self.queue.push_back(key);
+    }
+
+    /// Gets the number of keys waiting in the queue.
+    pub fn pending(&self) -> usize {
+        self.queue.len()
+    }
+}
+
+impl Device for Keypad {
+    fn size(&self) -> u16 {
+        2
+    }
+
+    fn read(&mut self, offset: u16) -> u8 {
+        match offset {
+            0 => self.queue.len().min(u8::MAX as usize) as u8,
+            _ => self.queue.pop_front().unwrap_or(0),
+        }
+    }
+
+    fn write(&mut self, _offset: u16, _value: u8) {}
+
+    fn save_state(&self) -> Vec<u8> {
+        self.queue.iter().copied().collect()
+    }
+
+    fn load_state(&mut self, state: &[u8]) -> bool {
+        self.queue = state.iter().copied().collect();
+        true
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
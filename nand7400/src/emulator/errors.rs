@@ -0,0 +1,122 @@
+use miette::Diagnostic;
+
+/// The error type used to report errors from the emulator.
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error, Diagnostic)]
+pub enum EmulatorError {
+    /// A device doesn't fit in the address space.
+    #[error(
+        "Device '{}' of size {} can't be mapped at {:#06X}.",
+        name,
+        size,
+        start
+    )]
+    #[diagnostic(
+        code(nand7400::errors::emulator::device_out_of_range),
+        help("Make sure the device fits entirely inside of the address space.")
+    )]
+    DeviceOutOfRange {
+        /// The name of the device.
+        name: String,
+
+        /// The address the device was supposed to start at.
+        start: u16,
+
+        /// The number of addresses the device takes up.
+        size: u16,
+    },
+
+    /// A device overlaps another device.
+    #[error("Device '{}' overlaps device '{}'.", name, other)]
+    #[diagnostic(
+        code(nand7400::errors::emulator::device_overlap),
+        help("Move one of the devices to a different address.")
+    )]
+    DeviceOverlap {
+        /// The name of the device that was being mapped.
+        name: String,
+
+        /// The name of the device that was already mapped.
+        other: String,
+    },
+
+    /// A device with the same name is already mapped.
+    #[error("A device named '{}' is already mapped.", name)]
+    #[diagnostic(
+        code(nand7400::errors::emulator::duplicate_device),
+        help("Give each device a unique name.")
+    )]
+    DuplicateDevice {
+        /// The name of the device.
+        name: String,
+    },
+
+    /// An opcode doesn't correspond to any instruction.
+    #[error("Illegal opcode {:#04X} at {:#06X}.", opcode, pc)]
+    #[diagnostic(
+        code(nand7400::errors::emulator::illegal_opcode),
+        help("Make sure the program counter points to an instruction.")
+    )]
+    IllegalOpcode {
+        /// The opcode byte.
+        opcode: u8,
+
+        /// The address of the opcode.
+        pc: u16,
+    },
+
+    /// The CPU has halted, so it can't execute anything else until it's reset.
+    #[error("The CPU is halted at {:#06X}.", pc)]
+    #[diagnostic(
+        code(nand7400::errors::emulator::halted),
+        help("Reset the machine to keep running.")
+    )]
+    Halted {
+        /// The address the CPU halted at.
+        pc: u16,
+    },
+
+    /// A snapshot couldn't be read or restored.
+    #[error("Invalid snapshot: {}.", reason)]
+    #[diagnostic(code(nand7400::errors::emulator::invalid_snapshot))]
+    InvalidSnapshot {
+        /// Why the snapshot is invalid.
+        reason: String,
+    },
+
+    /// A snapshot was saved with a version of the format we don't understand.
+    #[error("Snapshot version {} isn't supported.", version)]
+    #[diagnostic(
+        code(nand7400::errors::emulator::unsupported_snapshot_version),
+        help("This snapshot was probably saved by a newer version of nand7400.")
+    )]
+    UnsupportedSnapshotVersion {
+        /// The version of the snapshot.
+        version: u32,
+    },
+
+    /// A snapshot was saved with a different assembler configuration than the one given.
+    #[error(
+        "The snapshot was saved with config {:016x}, but config {:016x} was given.",
+        expected,
+        found
+    )]
+    #[diagnostic(
+        code(nand7400::errors::emulator::config_mismatch),
+        help("Use the same assembler configuration the snapshot was saved with.")
+    )]
+    ConfigMismatch {
+        /// The fingerprint saved in the snapshot.
+        expected: u64,
+
+        /// The fingerprint of the configuration given.
+        found: u64,
+    },
+
+    /// Reading or writing a file failed.
+    #[error("I/O error: {}.", message)]
+    #[diagnostic(code(nand7400::errors::emulator::io))]
+    Io {
+        /// The error message. This is a string so that the error can still be cloned and compared.
+        message: String,
+    },
+}
@@ -0,0 +1,556 @@
+mod tests;
+
+use super::{decode_hex, encode_hex, errors::EmulatorError, machine::Machine};
+use crate::assembler::parser::ast::Label;
+use std::{
+    collections::{HashMap, HashSet},
+    io::{self, Read, Write},
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+};
+
+#[cfg(unix)]
+use std::{
+    os::unix::net::{UnixListener, UnixStream},
+    path::Path,
+};
+
+/// How many instructions to run between checks for an interrupt (Ctrl-C) from the debugger while continuing.
+const INTERRUPT_POLL_INTERVAL: usize = 1024;
+
+/// The maximum packet size we tell the debugger we support.
+const PACKET_SIZE: usize = 0x1000;
+
+/// A connection to a debugger. This is a plain byte stream, with an extra hook to check if the debugger has sent an
+/// interrupt while the machine is running.
+pub trait Connection: Read + Write {
+    /// Checks (without blocking) whether or not the debugger has sent an interrupt byte (`0x03`), consuming it if so.
+    fn poll_interrupt(&mut self) -> io::Result<bool> {
+        Ok(false)
+    }
+}
+
+impl Connection for TcpStream {
+    fn poll_interrupt(&mut self) -> io::Result<bool> {
+        self.set_nonblocking(true)?;
+
+        let mut byte = [0];
+        let result = match self.peek(&mut byte) {
+            Ok(1) if byte[0] == 0x03 => self.read_exact(&mut byte).map(|_| true),
+            Ok(_) => Ok(false),
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => Ok(false),
+            Err(err) => Err(err),
+        };
+
+        self.set_nonblocking(false)?;
+
+        result
+    }
+}
+
+#[cfg(unix)]
+impl Connection for UnixStream {
+    fn poll_interrupt(&mut self) -> io::Result<bool> {
+        self.set_nonblocking(true)?;
+
+        // Unix streams can't peek, but an interrupt is the only thing the debugger is allowed to send while the
+        // machine is running, so anything else that's read here can be dropped.
+        let mut byte = [0];
+        let result = match self.read(&mut byte) {
+            Ok(1) => Ok(byte[0] == 0x03),
+            Ok(_) => Ok(false),
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => Ok(false),
+            Err(err) => Err(err),
+        };
+
+        self.set_nonblocking(false)?;
+
+        result
+    }
+}
+
+/// A GDB remote serial protocol (RSP) server for a `Machine`. This lets `gdb` (or `lldb`) connect to the simulated
+/// CPU to read and write registers and memory, set software breakpoints, and single-step or continue.
+///
+/// The stub has no way of handing the debugger an ELF file, so symbols are exposed through monitor commands instead:
+/// `monitor symbols` lists every label and its address, and `monitor reset` resets the CPU.
+pub struct GdbStub<'a> {
+    /// The machine being debugged.
+    machine: &'a mut Machine,
+
+    /// The symbol table of the program running on the machine.
+    symbols: HashMap<Label, u32>,
+
+    /// The addresses of all the software breakpoints.
+    breakpoints: HashSet<u16>,
+
+    /// Whether or not the debugger asked to stop sending/expecting acknowledgements.
+    no_ack: bool,
+
+    /// Whether or not to stop sending/expecting acknowledgements once the current reply is sent.
+    start_no_ack: bool,
+}
+
+/// A single thing received from the debugger.
+enum Packet {
+    /// A command packet, with the framing and checksum removed.
+    Command(String),
+
+    /// An interrupt (`0x03`) sent outside of a packet.
+    Interrupt,
+}
+
+/// Public API for the GDB stub.
+impl<'a> GdbStub<'a> {
+    /// Creates a new GDB stub for a machine.
+    pub fn new(machine: &'a mut Machine) -> Self {
+        Self {
+            machine,
+            symbols: HashMap::new(),
+            breakpoints: HashSet::new(),
+            no_ack: false,
+            start_no_ack: false,
+        }
+    }
+
+    /// Sets the symbol table (usually `Ast::symbols`) of the program running on the machine.
+    pub fn with_symbols(mut self, symbols: HashMap<Label, u32>) -> Self {
+        self.symbols = symbols;
+        self
+    }
+
+    /// Gets the target description XML, which tells the debugger what registers the CPU has. The program counter is
+    /// register 0, followed by the general-purpose registers, and then the flags register.
+    pub fn target_xml(&self) -> String {
+        let mut xml = String::from(
+            "<?xml version=\"1.0\"?>\n\
+             <!DOCTYPE target SYSTEM \"gdb-target.dtd\">\n\
+             <target version=\"1.0\">\n\
+             \x20\x20<feature name=\"org.nand7400.core\">\n\
+             \x20\x20\x20\x20<reg name=\"pc\" bitsize=\"16\" type=\"code_ptr\" regnum=\"0\"/>\n",
+        );
+
+        for name in self.machine.register_names() {
+            xml += &format!(
+                "    <reg name=\"{}\" bitsize=\"8\" type=\"uint8\"/>\n",
+                name.to_ascii_lowercase()
+            );
+        }
+
+        xml += "    <reg name=\"flags\" bitsize=\"8\" type=\"uint8\"/>\n  </feature>\n</target>\n";
+
+        xml
+    }
+
+    /// Listens on a TCP address (e.g. `"127.0.0.1:1234"`), and serves the first debugger that connects.
+    pub fn serve_tcp(&mut self, addr: impl ToSocketAddrs) -> io::Result<()> {
+        let listener = TcpListener::bind(addr)?;
+        let (mut stream, _) = listener.accept()?;
+
+        stream.set_nodelay(true)?;
+
+        self.serve(&mut stream)
+    }
+
+    /// Listens on a Unix socket, and serves the first debugger that connects.
+    #[cfg(unix)]
+    pub fn serve_unix(&mut self, path: impl AsRef<Path>) -> io::Result<()> {
+        let listener = UnixListener::bind(path)?;
+        let (mut stream, _) = listener.accept()?;
+
+        self.serve(&mut stream)
+    }
+
+    /// Serves a single debugger over an already-open connection. This returns when the debugger detaches, kills the
+    /// target, or closes the connection.
+    pub fn serve<C: Connection>(&mut self, conn: &mut C) -> io::Result<()> {
+        while let Some(packet) = self.read_packet(conn)? {
+            let reply = match packet {
+                // We're already stopped, so just tell the debugger so.
+                Packet::Interrupt => "S02".to_string(),
+
+                Packet::Command(command) => match self.handle(&command, conn)? {
+                    Some(reply) => reply,
+
+                    // The session is over.
+                    None => return Ok(()),
+                },
+            };
+
+            self.write_packet(conn, &reply)?;
+
+            if self.start_no_ack {
+                self.start_no_ack = false;
+                self.no_ack = true;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<'a> GdbStub<'a> {
+    /// Handles a single command, returning the reply to send, or `None` if the session should end.
+    fn handle<C: Connection>(&mut self, command: &str, conn: &mut C) -> io::Result<Option<String>> {
+        let reply = match command.as_bytes().first() {
+            Some(b'?') => "S05".to_string(),
+
+            Some(b'g') => self.read_registers(),
+            Some(b'G') => ok_or_error(self.write_registers(&command[1..])),
+
+            Some(b'p') => parse_hex(&command[1..])
+                .and_then(|regnum| self.read_register(regnum))
+                .unwrap_or_else(|| "E01".to_string()),
+
+            Some(b'P') => ok_or_error(
+                command[1..]
+                    .split_once('=')
+                    .and_then(|(regnum, value)| self.write_register(parse_hex(regnum)?, value)),
+            ),
+
+            Some(b'm') => self
+                .read_memory(&command[1..])
+                .unwrap_or_else(|| "E01".to_string()),
+
+            Some(b'M') => ok_or_error(self.write_memory(&command[1..])),
+
+            Some(b'c') => self.resume(conn, false, &command[1..])?,
+            Some(b's') => self.resume(conn, true, &command[1..])?,
+
+            Some(b'Z') | Some(b'z') => self.breakpoint(command),
+
+            Some(b'H') => "OK".to_string(),
+
+            // Killing the target just ends the session, as there's no reply to a kill.
+            Some(b'k') => return Ok(None),
+
+            Some(b'D') => {
+                self.write_packet(conn, "OK")?;
+                return Ok(None);
+            }
+
+            Some(b'q') | Some(b'Q') => self.query(command),
+
+            // Anything else isn't supported, which is signalled with an empty reply.
+            _ => String::new(),
+        };
+
+        Ok(Some(reply))
+    }
+
+    /// Handles a general query (`q...`/`Q...`) packet.
+    fn query(&mut self, command: &str) -> String {
+        if command.starts_with("qSupported") {
+            format!(
+                "PacketSize={:x};qXfer:features:read+;QStartNoAckMode+",
+                PACKET_SIZE
+            )
+        } else if command == "QStartNoAckMode" {
+            // The `OK` for this still has to be acknowledged, so the mode is switched after it's sent.
+            self.start_no_ack = true;
+            "OK".to_string()
+        } else if let Some(range) = command.strip_prefix("qXfer:features:read:target.xml:") {
+            self.read_target_xml(range)
+                .unwrap_or_else(|| "E01".to_string())
+        } else if let Some(hex) = command.strip_prefix("qRcmd,") {
+            decode_hex(hex)
+                .and_then(|bytes| String::from_utf8(bytes).ok())
+                .map(|line| self.monitor(&line))
+                .unwrap_or_else(|| "E01".to_string())
+        } else {
+            match command {
+                "qAttached" => "1".to_string(),
+                "qC" => "QC1".to_string(),
+                "qfThreadInfo" => "m1".to_string(),
+                "qsThreadInfo" => "l".to_string(),
+                "qSymbol::" => "OK".to_string(),
+                _ => String::new(),
+            }
+        }
+    }
+
+    /// Runs a monitor command, returning the reply.
+    fn monitor(&mut self, line: &str) -> String {
+        let output = match line.trim() {
+            "symbols" => {
+                let mut symbols = self.symbols.iter().collect::<Vec<_>>();
+                symbols.sort_by_key(|(label, address)| (**address, (*label).clone()));
+
+                symbols
+                    .into_iter()
+                    .map(|(label, address)| format!("{:#06x} {}\n", address, label))
+                    .collect::<String>()
+            }
+
+            "reset" => {
+                self.machine.reset(0);
+                "CPU reset.\n".to_string()
+            }
+
+            other => format!("Unknown monitor command '{}'.\n", other),
+        };
+
+        encode_hex(output.as_bytes())
+    }
+
+    /// Continues or single-steps the machine, returning the stop reply. `addr` is the optional address to resume at.
+    fn resume<C: Connection>(
+        &mut self,
+        conn: &mut C,
+        single_step: bool,
+        addr: &str,
+    ) -> io::Result<String> {
+        if let Some(pc) = parse_hex(addr) {
+            self.machine.cpu.pc = pc as u16;
+        }
+
+        let mut steps: usize = 0;
+
+        loop {
+            match self.machine.step() {
+                Ok(_) => {}
+
+                // An illegal instruction is reported as `SIGILL`.
+                Err(EmulatorError::IllegalOpcode { .. }) => return Ok("S04".to_string()),
+
+                // Everything else (i.e. a halted CPU) just stops the machine.
+                Err(_) => return Ok("S05".to_string()),
+            }
+
+            if single_step || self.breakpoints.contains(&self.machine.cpu.pc) {
+                return Ok("S05".to_string());
+            }
+
+            steps += 1;
+
+            if steps.is_multiple_of(INTERRUPT_POLL_INTERVAL) && conn.poll_interrupt()? {
+                return Ok("S02".to_string());
+            }
+        }
+    }
+
+    /// Handles a `Z`/`z` packet, inserting or removing a breakpoint.
+    fn breakpoint(&mut self, command: &str) -> String {
+        let mut parts = command[1..].split(',');
+
+        // Only software breakpoints are supported.
+        if parts.next() != Some("0") {
+            return String::new();
+        }
+
+        match parts.next().and_then(parse_hex) {
+            Some(addr) if command.starts_with('Z') => {
+                self.breakpoints.insert(addr as u16);
+                "OK".to_string()
+            }
+
+            Some(addr) => {
+                self.breakpoints.remove(&(addr as u16));
+                "OK".to_string()
+            }
+
+            None => "E01".to_string(),
+        }
+    }
+
+    /// Reads all the registers, as hex.
+    fn read_registers(&self) -> String {
+        (0..self.register_count())
+            .filter_map(|regnum| self.read_register(regnum))
+            .collect()
+    }
+
+    /// Writes all the registers from hex.
+    fn write_registers(&mut self, hex: &str) -> Option<()> {
+        let mut rest = hex;
+
+        for regnum in 0..self.register_count() {
+            let len = if regnum == 0 { 4 } else { 2 };
+
+            self.write_register(regnum, rest.get(..len)?)?;
+            rest = &rest[len..];
+        }
+
+        Some(())
+    }
+
+    /// Reads a single register, as hex. Multi-byte registers are little-endian.
+    fn read_register(&self, regnum: usize) -> Option<String> {
+        let cpu = &self.machine.cpu;
+
+        match regnum {
+            0 => Some(encode_hex(&cpu.pc.to_le_bytes())),
+            n if n <= cpu.registers.len() => Some(encode_hex(&[cpu.registers[n - 1]])),
+            n if n == cpu.registers.len() + 1 => Some(encode_hex(&[cpu.flags])),
+            _ => None,
+        }
+    }
+
+    /// Writes a single register from hex. Multi-byte registers are little-endian.
+    fn write_register(&mut self, regnum: usize, hex: &str) -> Option<()> {
+        let bytes = decode_hex(hex)?;
+        let cpu = &mut self.machine.cpu;
+
+        match (regnum, bytes.as_slice()) {
+            (0, [low, high]) => cpu.pc = u16::from_le_bytes([*low, *high]),
+            (n, [value]) if n >= 1 && n <= cpu.registers.len() => cpu.registers[n - 1] = *value,
+            (n, [value]) if n == cpu.registers.len() + 1 => cpu.flags = *value,
+            _ => return None,
+        }
+
+        Some(())
+    }
+
+    /// The total number of registers the debugger sees.
+    fn register_count(&self) -> usize {
+        self.machine.cpu.registers.len() + 2 // +2 for the program counter and flags.
+    }
+
+    /// Reads memory for an `m` packet (`addr,len`). This doesn't go through mapped devices, so that looking at memory
+    /// from the debugger doesn't have any side effects.
+    fn read_memory(&self, args: &str) -> Option<String> {
+        let (addr, len) = args.split_once(',')?;
+        let (addr, len) = (parse_hex(addr)?, parse_hex(len)?);
+
+        let bytes = (addr..addr.checked_add(len)?)
+            .take_while(|addr| *addr <= u16::MAX as usize)
+            .map(|addr| self.machine.bus.peek(addr as u16))
+            .collect::<Vec<_>>();
+
+        Some(encode_hex(&bytes))
+    }
+
+    /// Writes memory for an `M` packet (`addr,len:data`). Like reading, this doesn't go through mapped devices, so that
+    /// changing memory from the debugger doesn't have any side effects.
+    fn write_memory(&mut self, args: &str) -> Option<()> {
+        let (range, data) = args.split_once(':')?;
+        let (addr, len) = range.split_once(',')?;
+        let (addr, len) = (parse_hex(addr)?, parse_hex(len)?);
+        let bytes = decode_hex(data)?;
+
+        if bytes.len() != len || addr.checked_add(len)? > u16::MAX as usize + 1 {
+            return None;
+        }
+
+        for (i, byte) in bytes.into_iter().enumerate() {
+            self.machine.bus.poke((addr + i) as u16, byte);
+        }
+
+        Some(())
+    }
+
+    /// Reads part of the target description XML for a `qXfer` packet (`offset,length`).
+    fn read_target_xml(&self, range: &str) -> Option<String> {
+        let (offset, len) = range.split_once(',')?;
+        let (offset, len) = (parse_hex(offset)?, parse_hex(len)?);
+        let xml = self.target_xml();
+
+        if offset >= xml.len() {
+            return Some("l".to_string());
+        }
+
+        let end = offset.saturating_add(len).min(xml.len());
+        let marker = if end == xml.len() { 'l' } else { 'm' };
+
+        Some(format!("{}{}", marker, &xml[offset..end]))
+    }
+
+    /// Reads the next packet from the debugger, acknowledging it if necessary. Returns `None` if the connection
+    /// was closed.
+    fn read_packet<C: Connection>(&mut self, conn: &mut C) -> io::Result<Option<Packet>> {
+        loop {
+            // Skip everything (i.e. acknowledgements) until the start of a packet.
+            match read_byte(conn)? {
+                None => return Ok(None),
+                Some(b'$') => {}
+                Some(0x03) => return Ok(Some(Packet::Interrupt)),
+                Some(_) => continue,
+            }
+
+            let mut data = vec![];
+            let mut checksum = 0u8;
+
+            loop {
+                match read_byte(conn)? {
+                    None => return Ok(None),
+                    Some(b'#') => break,
+
+                    // Escaped characters are the next byte XOR'ed with 0x20.
+                    Some(b'}') => {
+                        let escaped = read_byte(conn)?.ok_or(io::ErrorKind::UnexpectedEof)?;
+
+                        checksum = checksum.wrapping_add(b'}').wrapping_add(escaped);
+                        data.push(escaped ^ 0x20);
+                    }
+
+                    Some(byte) => {
+                        checksum = checksum.wrapping_add(byte);
+                        data.push(byte);
+                    }
+                }
+            }
+
+            let mut expected = [0; 2];
+            conn.read_exact(&mut expected)?;
+
+            let valid = std::str::from_utf8(&expected)
+                .ok()
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                == Some(checksum);
+
+            if !self.no_ack {
+                conn.write_all(if valid { b"+" } else { b"-" })?;
+            }
+
+            if valid {
+                return Ok(Some(Packet::Command(
+                    String::from_utf8_lossy(&data).into_owned(),
+                )));
+            }
+        }
+    }
+
+    /// Sends a packet to the debugger, waiting for it to be acknowledged if necessary.
+    fn write_packet<C: Connection>(&mut self, conn: &mut C, data: &str) -> io::Result<()> {
+        let checksum = data.bytes().fold(0u8, |acc, byte| acc.wrapping_add(byte));
+        let packet = format!("${}#{:02x}", data, checksum);
+
+        loop {
+            conn.write_all(packet.as_bytes())?;
+            conn.flush()?;
+
+            if self.no_ack {
+                return Ok(());
+            }
+
+            // Resend until the debugger acknowledges the packet.
+            match read_byte(conn)? {
+                Some(b'-') => continue,
+                Some(_) => return Ok(()),
+                None => return Err(io::ErrorKind::UnexpectedEof.into()),
+            }
+        }
+    }
+}
+
+/// Reads a single byte from a connection, returning `None` if it was closed.
+fn read_byte<C: Connection>(conn: &mut C) -> io::Result<Option<u8>> {
+    let mut byte = [0];
+
+    match conn.read(&mut byte)? {
+        0 => Ok(None),
+        _ => Ok(Some(byte[0])),
+    }
+}
+
+/// Turns the result of a command that has no data to reply with into either `OK` or an error.
+fn ok_or_error(result: Option<()>) -> String {
+    match result {
+        Some(()) => "OK".to_string(),
+        None => "E01".to_string(),
+    }
+}
+
+/// Parses a hex number, as used for addresses and lengths.
+fn parse_hex(hex: &str) -> Option<usize> {
+    usize::from_str_radix(hex, 16).ok()
+}
@@ -0,0 +1,232 @@
+#![cfg(test)]
+
+use super::*;
+use crate::emulator::{tests::TestIsa, Bus};
+use std::{net::TcpListener, thread};
+
+/// A scripted RSP client, standing in for `gdb`.
+struct Client {
+    stream: TcpStream,
+
+    /// Whether or not no-ack mode has been turned on.
+    no_ack: bool,
+}
+
+impl Client {
+    /// Sends a command and returns the reply, handling the acknowledgements on both sides.
+    fn command(&mut self, data: &str) -> String {
+        let checksum = data.bytes().fold(0u8, |acc, byte| acc.wrapping_add(byte));
+
+        write!(self.stream, "${}#{:02x}", data, checksum).unwrap();
+
+        if !self.no_ack {
+            assert_eq!(self.read_byte(), b'+', "'{}' wasn't acknowledged", data);
+        }
+
+        let reply = self.read_reply();
+
+        if !self.no_ack {
+            self.stream.write_all(b"+").unwrap();
+        }
+
+        reply
+    }
+
+    /// Reads a reply packet, checking its checksum.
+    fn read_reply(&mut self) -> String {
+        assert_eq!(self.read_byte(), b'$');
+
+        let mut data = vec![];
+
+        loop {
+            match self.read_byte() {
+                b'#' => break,
+                byte => data.push(byte),
+            }
+        }
+
+        let checksum = [self.read_byte(), self.read_byte()];
+        let checksum = u8::from_str_radix(std::str::from_utf8(&checksum).unwrap(), 16).unwrap();
+
+        assert_eq!(
+            data.iter().fold(0u8, |acc, byte| acc.wrapping_add(*byte)),
+            checksum
+        );
+
+        String::from_utf8(data).unwrap()
+    }
+
+    /// Reads a single byte.
+    fn read_byte(&mut self) -> u8 {
+        let mut byte = [0];
+        self.stream.read_exact(&mut byte).unwrap();
+        byte[0]
+    }
+}
+
+/// Runs a GDB stub over TCP on a machine with the given program, and runs `script` against it as the client.
+fn with_stub(program: &[u8], script: impl FnOnce(&mut Client) + Send) -> Machine {
+    let mut machine = Machine::new(Box::new(TestIsa), Bus::new());
+    machine.load(0, program);
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    thread::scope(|scope| {
+        let server = scope.spawn(|| {
+            let (mut stream, _) = listener.accept().unwrap();
+            stream.set_nodelay(true).unwrap();
+
+            GdbStub::new(&mut machine)
+                .with_symbols(HashMap::from([
+                    ("start".to_string(), 0x0000),
+                    ("store".to_string(), 0x0002),
+                ]))
+                .serve(&mut stream)
+                .unwrap();
+        });
+
+        let stream = TcpStream::connect(addr).unwrap();
+        stream.set_nodelay(true).unwrap();
+
+        let mut client = Client {
+            stream,
+            no_ack: false,
+        };
+
+        script(&mut client);
+
+        // Detaching ends the session.
+        assert_eq!(client.command("D"), "OK");
+
+        server.join().unwrap();
+    });
+
+    machine
+}
+
+/// lda #0x2A; sta 0x0100; hlt
+const PROGRAM: [u8; 6] = [0x01, 0x2A, 0x02, 0x00, 0x01, 0xFF];
+
+/// Test the handshake, target description and basic queries.
+#[test]
+fn gdb_handshake() {
+    with_stub(&PROGRAM, |client| {
+        assert!(client
+            .command("qSupported:multiprocess+;xmlRegisters=i386")
+            .contains("qXfer:features:read+"));
+        assert_eq!(client.command("?"), "S05");
+        assert_eq!(client.command("Hg0"), "OK");
+        assert_eq!(client.command("qAttached"), "1");
+        assert_eq!(client.command("vMustReplyEmpty"), "");
+
+        // Read the target description in small chunks.
+        let mut xml = String::new();
+
+        loop {
+            let reply = client.command(&format!(
+                "qXfer:features:read:target.xml:{:x},40",
+                xml.len()
+            ));
+
+            xml += &reply[1..];
+
+            if reply.starts_with('l') {
+                break;
+            }
+        }
+
+        assert!(xml.contains("<reg name=\"pc\" bitsize=\"16\""));
+        assert!(xml.contains("<reg name=\"a\" bitsize=\"8\""));
+        assert!(xml.contains("<reg name=\"flags\" bitsize=\"8\""));
+    });
+}
+
+/// Test reading and writing registers and memory.
+#[test]
+fn gdb_registers_and_memory() {
+    let machine = with_stub(&PROGRAM, |client| {
+        // pc, a, flags
+        assert_eq!(client.command("g"), "00000000");
+        assert_eq!(client.command("P0=0100"), "OK");
+        assert_eq!(client.command("P1=7f"), "OK");
+        assert_eq!(client.command("p0"), "0100");
+        assert_eq!(client.command("p1"), "7f");
+        assert_eq!(client.command("p3"), "E01");
+        assert_eq!(client.command("G0000112200"), "OK");
+        assert_eq!(client.command("g"), "00001122");
+
+        assert_eq!(client.command("m0,6"), "012a020001ff");
+        assert_eq!(client.command("M200,2:beef"), "OK");
+        assert_eq!(client.command("m200,2"), "beef");
+        assert_eq!(client.command("M200,2:be"), "E01");
+
+        // Malformed ranges that would overflow are errors, not crashes.
+        assert_eq!(client.command("mffffffffffffffff,1"), "E01");
+        assert_eq!(client.command("Mffffffffffffffff,1:00"), "E01");
+    });
+
+    assert_eq!(machine.cpu.registers, vec![0x11]);
+    assert_eq!(machine.cpu.flags, 0x22);
+    assert_eq!(&machine.bus.memory()[0x200..0x202], &[0xBE, 0xEF]);
+}
+
+/// Test single-stepping, breakpoints, and continuing until the CPU halts.
+#[test]
+fn gdb_step_and_breakpoints() {
+    let machine = with_stub(&PROGRAM, |client| {
+        assert_eq!(client.command("s"), "S05");
+        assert_eq!(client.command("p0"), "0200");
+        assert_eq!(client.command("p1"), "2a");
+
+        // Break right before the `hlt`.
+        assert_eq!(client.command("Z0,5,1"), "OK");
+        assert_eq!(client.command("c"), "S05");
+        assert_eq!(client.command("p0"), "0500");
+        assert_eq!(client.command("m100,1"), "2a");
+
+        // Continuing from a breakpoint moves past it, until the CPU halts (staying on the `hlt`).
+        assert_eq!(client.command("z0,5,1"), "OK");
+        assert_eq!(client.command("c"), "S05");
+        assert_eq!(client.command("p0"), "0500");
+        assert_eq!(client.command("c"), "S05");
+
+        // Hardware breakpoints aren't supported.
+        assert_eq!(client.command("Z1,5,1"), "");
+    });
+
+    assert!(machine.cpu.halted);
+}
+
+/// Test that illegal instructions are reported as `SIGILL`, and that monitor commands work.
+#[test]
+fn gdb_monitor_and_illegal_instructions() {
+    with_stub(&[0x00, 0x42], |client| {
+        assert_eq!(client.command("c"), "S04");
+
+        let symbols =
+            decode_hex(&client.command(&format!("qRcmd,{}", encode_hex(b"symbols")))).unwrap();
+
+        assert_eq!(
+            String::from_utf8(symbols).unwrap(),
+            "0x0000 start\n0x0002 store\n"
+        );
+
+        client.command(&format!("qRcmd,{}", encode_hex(b"reset")));
+
+        assert_eq!(client.command("p0"), "0000");
+    });
+}
+
+/// Test that no-ack mode stops acknowledgements.
+#[test]
+fn gdb_no_ack_mode() {
+    with_stub(&PROGRAM, |client| {
+        assert_eq!(client.command("QStartNoAckMode"), "OK");
+        client.no_ack = true;
+
+        // From here on, nothing is acknowledged. If the stub still sent an ack, it'd show up instead of the reply.
+        assert_eq!(client.command("?"), "S05");
+        assert_eq!(client.command("p1"), "00");
+    });
+}
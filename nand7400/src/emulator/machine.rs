@@ -0,0 +1,129 @@
+use super::{errors::EmulatorError, Bus};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// The architectural state of the CPU.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Cpu {
+    /// The program counter.
+    pub pc: u16,
+
+    /// The general-purpose registers, in the order given by `InstructionSet::register_names`.
+    pub registers: Vec<u8>,
+
+    /// The flags register.
+    pub flags: u8,
+
+    /// Whether or not the CPU has halted.
+    pub halted: bool,
+
+    /// The number of cycles executed since the last reset.
+    pub cycles: u64,
+}
+
+impl Cpu {
+    /// Creates a new CPU with `register_count` general-purpose registers, all in their reset state.
+    pub fn new(register_count: usize) -> Self {
+        Self {
+            pc: 0,
+            registers: vec![0; register_count],
+            flags: 0,
+            halted: false,
+            cycles: 0,
+        }
+    }
+}
+
+/// The behaviour of each instruction. The assembler configuration only describes how instructions are encoded, not
+/// what they do, so the semantics of the ISA are given to the `Machine` through this trait. The nand7400's own
+/// instruction set is `Nand7400Isa`.
+pub trait InstructionSet: fmt::Debug + Send {
+    /// The names of the general-purpose registers, in order. The CPU will have one 8-bit register per name.
+    fn register_names(&self) -> Vec<String>;
+
+    /// Executes the instruction at `cpu.pc`, updating the CPU and bus as necessary (including advancing `cpu.pc`).
+    /// Returns the number of cycles the instruction took.
+    fn execute(&mut self, cpu: &mut Cpu, bus: &mut Bus) -> Result<u32, EmulatorError>;
+}
+
+/// An in-process nand7400: a CPU and a bus, with instructions executed by an `InstructionSet`.
+#[derive(Debug)]
+pub struct Machine {
+    /// The CPU state.
+    pub cpu: Cpu,
+
+    /// The memory bus, including any mapped devices.
+    pub bus: Bus,
+
+    /// The behaviour of the instructions.
+    isa: Box<dyn InstructionSet>,
+}
+
+/// Information about a single executed instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Step {
+    /// The address of the instruction.
+    pub pc: u16,
+
+    /// The opcode byte of the instruction.
+    pub opcode: u8,
+
+    /// The number of cycles the instruction took.
+    pub cycles: u32,
+}
+
+/// Public API for the machine.
+impl Machine {
+    /// Creates a new machine with the given instruction set and bus.
+    pub fn new(isa: Box<dyn InstructionSet>, bus: Bus) -> Self {
+        Self {
+            cpu: Cpu::new(isa.register_names().len()),
+            bus,
+            isa,
+        }
+    }
+
+    /// Gets the names of the general-purpose registers.
+    pub fn register_names(&self) -> Vec<String> {
+        self.isa.register_names()
+    }
+
+    /// Loads a binary image into memory at `start`.
+    pub fn load(&mut self, start: u16, image: &[u8]) {
+        self.bus.load(start, image);
+    }
+
+    /// Resets the CPU to its initial state, starting execution at `pc`. Memory and devices are left untouched.
+    pub fn reset(&mut self, pc: u16) {
+        self.cpu = Cpu::new(self.cpu.registers.len());
+        self.cpu.pc = pc;
+    }
+
+    /// Executes a single instruction.
+    pub fn step(&mut self) -> Result<Step, EmulatorError> {
+        if self.cpu.halted {
+            return Err(EmulatorError::Halted { pc: self.cpu.pc });
+        }
+
+        let pc = self.cpu.pc;
+        let opcode = self.bus.peek(pc);
+        let cycles = self.isa.execute(&mut self.cpu, &mut self.bus)?;
+
+        self.cpu.cycles += cycles as u64;
+
+        Ok(Step { pc, opcode, cycles })
+    }
+
+    /// Runs until the CPU halts, or until `max_steps` instructions have been executed. Returns the number of
+    /// instructions executed.
+    pub fn run(&mut self, max_steps: usize) -> Result<usize, EmulatorError> {
+        let mut steps = 0;
+
+        while !self.cpu.halted && steps < max_steps {
+            self.step()?;
+            steps += 1;
+        }
+
+        Ok(steps)
+    }
+}
@@ -0,0 +1,285 @@
+pub mod config;
+pub mod devices;
+pub mod errors;
+pub mod gdb;
+pub mod machine;
+pub mod nand7400;
+pub mod profile;
+pub mod snapshot;
+pub mod trace;
+
+mod tests;
+
+use self::{
+    config::{DeviceKind, DeviceMapConfig},
+    devices::{ConsolePort, Device, Keypad, LedRegister, SevenSegment},
+    errors::EmulatorError,
+};
+
+/// The size of the nand7400's address space, in bytes.
+pub const MEMORY_SIZE: usize = 0x10000;
+
+/// The memory bus of the nand7400. This is plain memory, with devices mapped on top of certain address ranges.
+/// Reads and writes to an address that a device is mapped to are sent to that device instead of memory.
+#[derive(Debug)]
+pub struct Bus {
+    /// The raw memory of the bus. Addresses that are mapped to a device are never read from or written to here.
+    memory: Vec<u8>,
+
+    /// The devices mapped onto the bus.
+    devices: Vec<MappedDevice>,
+
+    /// Every access made to the bus since recording was turned on, or `None` if it's off.
+    accesses: Option<Vec<BusAccess>>,
+}
+
+/// A single read or write on the bus.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BusAccess {
+    /// Whether this was a read or a write.
+    pub kind: AccessKind,
+
+    /// The address on the address bus.
+    pub address: u16,
+
+    /// The value on the data bus.
+    pub value: u8,
+}
+
+/// The kind of a bus access.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessKind {
+    /// The CPU read from the bus.
+    Read,
+
+    /// The CPU wrote to the bus.
+    Write,
+}
+
+/// A device that is mapped onto the bus at a certain address.
+#[derive(Debug)]
+struct MappedDevice {
+    /// The name of the device, used to look it up later.
+    name: String,
+
+    /// The first address the device is mapped to.
+    start: u16,
+
+    /// The device itself.
+    device: Box<dyn Device>,
+}
+
+/// Public API for the bus.
+impl Bus {
+    /// Creates a new bus with zeroed memory and no devices.
+    pub fn new() -> Self {
+        Self {
+            memory: vec![0; MEMORY_SIZE],
+            devices: vec![],
+            accesses: None,
+        }
+    }
+
+    /// Creates a new bus with the devices described in the given device map.
+    pub fn from_config(config: &DeviceMapConfig) -> Result<Self, EmulatorError> {
+        let mut bus = Self::new();
+
+        for entry in &config.devices {
+            let device: Box<dyn Device> = match entry.kind {
+                DeviceKind::Console => Box::new(ConsolePort::stdout()),
+                DeviceKind::Leds => Box::new(LedRegister::new()),
+                DeviceKind::SevenSegment => Box::new(SevenSegment::new()),
+                DeviceKind::Keypad => Box::new(Keypad::new()),
+            };
+
+            bus.map(&entry.name, entry.start, device)?;
+        }
+
+        Ok(bus)
+    }
+
+    /// Maps a device onto the bus, starting at `start`. The device takes up `device.size()` addresses. This fails if
+    /// the device doesn't fit in the address space, if it overlaps another device, or if the name is already taken.
+    pub fn map(
+        &mut self,
+        name: &str,
+        start: u16,
+        device: Box<dyn Device>,
+    ) -> Result<(), EmulatorError> {
+        let end = start as usize + device.size() as usize; // Exclusive.
+
+        if device.size() == 0 || end > MEMORY_SIZE {
+            return Err(EmulatorError::DeviceOutOfRange {
+                name: name.to_string(),
+                start,
+                size: device.size(),
+            });
+        }
+
+        if self.devices.iter().any(|mapped| mapped.name == name) {
+            return Err(EmulatorError::DuplicateDevice {
+                name: name.to_string(),
+            });
+        }
+
+        if let Some(other) = self.devices.iter().find(|mapped| {
+            let other_end = mapped.start as usize + mapped.device.size() as usize;
+
+            (start as usize) < other_end && (mapped.start as usize) < end
+        }) {
+            return Err(EmulatorError::DeviceOverlap {
+                name: name.to_string(),
+                other: other.name.clone(),
+            });
+        }
+
+        self.devices.push(MappedDevice {
+            name: name.to_string(),
+            start,
+            device,
+        });
+
+        Ok(())
+    }
+
+    /// Reads a byte from the bus.
+    pub fn read(&mut self, address: u16) -> u8 {
+        let value = match self.device_at_mut(address) {
+            Some((start, device)) => device.read(address - start),
+            None => self.memory[address as usize],
+        };
+
+        self.record(AccessKind::Read, address, value);
+
+        value
+    }
+
+    /// Writes a byte to the bus.
+    pub fn write(&mut self, address: u16, value: u8) {
+        match self.device_at_mut(address) {
+            Some((start, device)) => device.write(address - start, value),
+            None => self.memory[address as usize] = value,
+        }
+
+        self.record(AccessKind::Write, address, value);
+    }
+
+    /// Turns recording of bus accesses on or off. Turning it off throws away anything that was recorded.
+    pub fn record_accesses(&mut self, record: bool) {
+        self.accesses = record.then(Vec::new);
+    }
+
+    /// Takes every access recorded since recording was turned on (or since this was last called).
+    pub fn take_accesses(&mut self) -> Vec<BusAccess> {
+        self.accesses
+            .as_mut()
+            .map(std::mem::take)
+            .unwrap_or_default()
+    }
+
+    /// Reads a byte from memory without going through any mapped devices, so there are no side effects.
+    pub fn peek(&self, address: u16) -> u8 {
+        self.memory[address as usize]
+    }
+
+    /// Writes a byte to memory without going through any mapped devices, so there are no side effects.
+    pub fn poke(&mut self, address: u16, value: u8) {
+        self.memory[address as usize] = value;
+    }
+
+    /// Loads a binary image into memory, starting at `start`. This bypasses any mapped devices, and anything that
+    /// doesn't fit into the address space is cut off.
+    pub fn load(&mut self, start: u16, image: &[u8]) {
+        let start = start as usize;
+        let len = image.len().min(MEMORY_SIZE - start);
+
+        self.memory[start..start + len].copy_from_slice(&image[..len]);
+    }
+
+    /// Gets the raw memory of the bus, without any devices mapped on top.
+    pub fn memory(&self) -> &[u8] {
+        &self.memory
+    }
+
+    /// Iterates over every mapped device, along with its name and starting address.
+    pub fn devices(&self) -> impl Iterator<Item = (&str, u16, &dyn Device)> {
+        self.devices
+            .iter()
+            .map(|mapped| (mapped.name.as_str(), mapped.start, mapped.device.as_ref()))
+    }
+
+    /// Gets a device by its name.
+    pub fn device(&self, name: &str) -> Option<&dyn Device> {
+        self.devices
+            .iter()
+            .find(|mapped| mapped.name == name)
+            .map(|mapped| mapped.device.as_ref())
+    }
+
+    /// Gets a device by its name, mutably.
+    pub fn device_mut(&mut self, name: &str) -> Option<&mut dyn Device> {
+        self.devices
+            .iter_mut()
+            .find(|mapped| mapped.name == name)
+            .map(|mapped| mapped.device.as_mut())
+    }
+
+    /// Gets a device by its name, downcasted to its concrete type. Returns `None` if there's no device with that name,
+    /// or if it isn't a `T`.
+    pub fn device_as<T: Device>(&self, name: &str) -> Option<&T> {
+        self.device(name)?.as_any().downcast_ref()
+    }
+
+    /// Gets a device by its name, mutably downcasted to its concrete type. Returns `None` if there's no device with
+    /// that name, or if it isn't a `T`.
+    pub fn device_as_mut<T: Device>(&mut self, name: &str) -> Option<&mut T> {
+        self.device_mut(name)?.as_any_mut().downcast_mut()
+    }
+}
+
+impl Bus {
+    /// Records a bus access, if recording is turned on.
+    fn record(&mut self, kind: AccessKind, address: u16, value: u8) {
+        if let Some(accesses) = &mut self.accesses {
+            accesses.push(BusAccess {
+                kind,
+                address,
+                value,
+            });
+        }
+    }
+
+    /// Gets the device mapped at an address (if any) along with the address it starts at.
+    fn device_at_mut(&mut self, address: u16) -> Option<(u16, &mut dyn Device)> {
+        self.devices
+            .iter_mut()
+            .find(|mapped| {
+                address >= mapped.start
+                    && (address as usize) < mapped.start as usize + mapped.device.size() as usize
+            })
+            .map(|mapped| (mapped.start, mapped.device.as_mut()))
+    }
+}
+
+impl Default for Bus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Encodes bytes as a lowercase hex string.
+pub(crate) fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Decodes a hex string into bytes.
+pub(crate) fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
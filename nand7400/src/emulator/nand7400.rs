@@ -0,0 +1,85 @@
+use super::{
+    errors::EmulatorError,
+    machine::{Cpu, InstructionSet, Machine},
+    Bus,
+};
+
+/// The carry flag, set when an `add` overflows.
+pub const FLAG_CARRY: u8 = 0b01;
+
+/// The zero flag, set when the result of an `add` is 0.
+pub const FLAG_ZERO: u8 = 0b10;
+
+/// The instruction set of the nand7400 computer, matching the encoding of the `nand7400` assembler profiles (both
+/// revisions encode the same bytes). The registers are `A` and `B`, and arguments that are addresses of data are
+/// zero-page addresses (i.e. a single byte), even when the assembler takes them as immediates:
+///
+/// - `0x00`: `nop` (1 cycle)
+/// - `0x01 n`: `lda #n`, load `n` into `A` (2 cycles)
+/// - `0x02 addr`: `ldb addr`, load the byte at `addr` into `B` (3 cycles)
+/// - `0x03 x y dst`: `add #x #y #dst`, add `x` and `y` and store the sum at `dst`, setting the carry and zero flags
+///   (4 cycles)
+/// - `0x04 lo hi`: `jmp addr`, jump to the 16-bit address `addr` (3 cycles)
+/// - `0xFF`: `hlt` (1 cycle)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Nand7400Isa;
+
+impl InstructionSet for Nand7400Isa {
+    fn register_names(&self) -> Vec<String> {
+        vec!["A".to_string(), "B".to_string()]
+    }
+
+    fn execute(&mut self, cpu: &mut Cpu, bus: &mut Bus) -> Result<u32, EmulatorError> {
+        let pc = cpu.pc;
+        let opcode = bus.read(pc);
+        let mut operand = |i: u16| bus.read(pc.wrapping_add(i));
+
+        let (len, cycles) = match opcode {
+            0x00 => (1, 1),
+
+            0x01 => {
+                cpu.registers[0] = operand(1);
+                (2, 2)
+            }
+
+            0x02 => {
+                let address = operand(1);
+                cpu.registers[1] = bus.read(address as u16);
+                (2, 3)
+            }
+
+            0x03 => {
+                let (x, y, dst) = (operand(1), operand(2), operand(3));
+                let (sum, carry) = x.overflowing_add(y);
+
+                bus.write(dst as u16, sum);
+                cpu.flags =
+                    if carry { FLAG_CARRY } else { 0 } | if sum == 0 { FLAG_ZERO } else { 0 };
+                (4, 4)
+            }
+
+            0x04 => {
+                cpu.pc = u16::from_le_bytes([operand(1), operand(2)]);
+                return Ok(3);
+            }
+
+            0xFF => {
+                cpu.halted = true;
+                return Ok(1);
+            }
+
+            opcode => return Err(EmulatorError::IllegalOpcode { opcode, pc }),
+        };
+
+        cpu.pc = pc.wrapping_add(len);
+
+        Ok(cycles)
+    }
+}
+
+impl Machine {
+    /// Creates a new nand7400 with the given bus, running the nand7400's own instruction set.
+    pub fn nand7400(bus: Bus) -> Self {
+        Self::new(Box::new(Nand7400Isa), bus)
+    }
+}
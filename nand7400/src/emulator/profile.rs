@@ -0,0 +1,187 @@
+use super::{
+    errors::EmulatorError,
+    machine::{Machine, Step},
+};
+use crate::assembler::{
+    parser::ast::Label,
+    source_map::{nearest_label, SourceMap, SourceMapEntryKind},
+};
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::{self, Write},
+};
+
+/// Records which instructions a machine executed, how many times, and how many cycles they took. This is used for
+/// code coverage and for finding out where the cycles in a program go.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Profiler {
+    /// The number of times each instruction was executed, by address.
+    hits: BTreeMap<u16, u64>,
+
+    /// The total number of cycles spent on each instruction, by address.
+    cycles: BTreeMap<u16, u64>,
+}
+
+/// The time spent under a single label.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LabelProfile {
+    /// The label. This is `None` for code before the first label.
+    pub label: Option<Label>,
+
+    /// The address of the label.
+    pub address: u16,
+
+    /// The number of instructions executed under the label.
+    pub instructions: u64,
+
+    /// The number of cycles spent under the label.
+    pub cycles: u64,
+}
+
+/// Public API for the profiler.
+impl Profiler {
+    /// Creates a new, empty profiler.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Executes a single instruction on the machine, recording it.
+    pub fn step(&mut self, machine: &mut Machine) -> Result<Step, EmulatorError> {
+        let step = machine.step()?;
+
+        *self.hits.entry(step.pc).or_default() += 1;
+        *self.cycles.entry(step.pc).or_default() += step.cycles as u64;
+
+        Ok(step)
+    }
+
+    /// Runs the machine until it halts, or until `max_steps` instructions have been executed, recording everything.
+    /// Returns the number of instructions executed.
+    pub fn run(&mut self, machine: &mut Machine, max_steps: usize) -> Result<usize, EmulatorError> {
+        let mut steps = 0;
+
+        while !machine.cpu.halted && steps < max_steps {
+            self.step(machine)?;
+            steps += 1;
+        }
+
+        Ok(steps)
+    }
+
+    /// Gets the number of times the instruction at an address was executed.
+    pub fn hits(&self, address: u16) -> u64 {
+        self.hits.get(&address).copied().unwrap_or(0)
+    }
+
+    /// Gets the total number of cycles recorded.
+    pub fn total_cycles(&self) -> u64 {
+        self.cycles.values().sum()
+    }
+
+    /// Gets the number of times each source line was executed, using the source map. Only lines with code on them
+    /// are included, so lines that were never executed show up with 0 hits.
+    pub fn line_hits(&self, source_map: &SourceMap) -> BTreeMap<usize, u64> {
+        let mut lines = BTreeMap::new();
+
+        for entry in source_map.entries() {
+            if entry.kind == SourceMapEntryKind::Code {
+                // Code past the end of the emulator's memory can never be executed.
+                *lines.entry(entry.line).or_default() +=
+                    u16::try_from(entry.address).map_or(0, |address| self.hits(address));
+            }
+        }
+
+        lines
+    }
+
+    /// Splits up the recorded instructions and cycles by the nearest label at or before each instruction, sorted by
+    /// the number of cycles (most first).
+    pub fn label_profile(&self, symbols: &HashMap<Label, u32>) -> Vec<LabelProfile> {
+        let mut profiles: BTreeMap<(u16, Option<Label>), LabelProfile> = BTreeMap::new();
+
+        for (address, hits) in &self.hits {
+            let (label, label_address) = match nearest_label(symbols, *address as u32) {
+                Some((label, offset)) => (Some(label.clone()), address - offset as u16),
+                None => (None, 0),
+            };
+
+            let profile = profiles
+                .entry((label_address, label.clone()))
+                .or_insert(LabelProfile {
+                    label,
+                    address: label_address,
+                    instructions: 0,
+                    cycles: 0,
+                });
+
+            profile.instructions += hits;
+            profile.cycles += self.cycles[address];
+        }
+
+        let mut profiles = profiles.into_values().collect::<Vec<_>>();
+        profiles.sort_by(|a, b| b.cycles.cmp(&a.cycles).then(a.address.cmp(&b.address)));
+
+        profiles
+    }
+
+    /// Writes the coverage as an LCOV tracefile, for the source file at `source_path`.
+    pub fn write_lcov<W: Write>(
+        &self,
+        writer: &mut W,
+        source_map: &SourceMap,
+        source_path: &str,
+    ) -> io::Result<()> {
+        let lines = self.line_hits(source_map);
+
+        writeln!(writer, "TN:")?;
+        writeln!(writer, "SF:{}", source_path)?;
+
+        for (line, hits) in &lines {
+            writeln!(writer, "DA:{},{}", line, hits)?;
+        }
+
+        writeln!(writer, "LF:{}", lines.len())?;
+        writeln!(
+            writer,
+            "LH:{}",
+            lines.values().filter(|hits| **hits > 0).count()
+        )?;
+        writeln!(writer, "end_of_record")?;
+
+        Ok(())
+    }
+
+    /// Writes a flat profile, with one row per label, sorted by the number of cycles spent under it.
+    pub fn write_profile<W: Write>(
+        &self,
+        writer: &mut W,
+        symbols: &HashMap<Label, u32>,
+    ) -> io::Result<()> {
+        let total = self.total_cycles();
+
+        writeln!(
+            writer,
+            "{:>10} {:>7} {:>10}  {:<6} label",
+            "cycles", "%", "instrs", "addr"
+        )?;
+
+        for profile in self.label_profile(symbols) {
+            let percent = match total {
+                0 => 0.0,
+                total => profile.cycles as f64 * 100.0 / total as f64,
+            };
+
+            writeln!(
+                writer,
+                "{:>10} {:>6.2}% {:>10}  {:04X}   {}",
+                profile.cycles,
+                percent,
+                profile.instructions,
+                profile.address,
+                profile.label.as_deref().unwrap_or("<none>")
+            )?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,229 @@
+use super::{
+    decode_hex, encode_hex,
+    errors::EmulatorError,
+    machine::{Cpu, Machine},
+    MEMORY_SIZE,
+};
+use crate::assembler::config::AssemblerConfig;
+use serde::{Deserialize, Serialize};
+use std::{fs, path::Path};
+
+/// The current version of the snapshot format. This is bumped whenever the format changes in a way that older
+/// snapshots can't be read anymore.
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+/// A saved copy of the entire state of a machine: the CPU, memory, and every mapped device. Snapshots are stored as
+/// versioned JSON, so they can be handed around as files (e.g. "here's the CPU halted mid-routine, find the bug").
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Snapshot {
+    /// The version of the snapshot format.
+    pub version: u32,
+
+    /// The fingerprint of the `AssemblerConfig` the program in memory was assembled with, if known. See
+    /// `AssemblerConfig::fingerprint`.
+    pub config_fingerprint: Option<u64>,
+
+    /// The state of the CPU.
+    pub cpu: Cpu,
+
+    /// The entire memory image, as a hex string.
+    pub memory: String,
+
+    /// The state of every mapped device.
+    pub devices: Vec<DeviceSnapshot>,
+}
+
+/// The saved state of a single device.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeviceSnapshot {
+    /// The name the device is mapped under.
+    pub name: String,
+
+    /// The address the device is mapped at.
+    pub start: u16,
+
+    /// The state of the device (from `Device::save_state`), as a hex string.
+    pub state: String,
+}
+
+/// Public API for snapshots.
+impl Snapshot {
+    /// Takes a snapshot of a machine. If `config` is given, its fingerprint is saved so that the snapshot can only be
+    /// restored alongside the same configuration.
+    pub fn capture(machine: &Machine, config: Option<&AssemblerConfig>) -> Self {
+        Self {
+            version: SNAPSHOT_VERSION,
+            config_fingerprint: config.map(AssemblerConfig::fingerprint),
+            cpu: machine.cpu.clone(),
+            memory: encode_hex(machine.bus.memory()),
+            devices: machine
+                .bus
+                .devices()
+                .map(|(name, start, device)| DeviceSnapshot {
+                    name: name.to_string(),
+                    start,
+                    state: encode_hex(&device.save_state()),
+                })
+                .collect(),
+        }
+    }
+
+    /// Restores a snapshot onto a machine. The machine must have the same registers and exactly the same devices mapped
+    /// at the same addresses as the one the snapshot was taken of. If `config` is given and the snapshot saved a
+    /// configuration fingerprint, they must match.
+    pub fn restore(
+        &self,
+        machine: &mut Machine,
+        config: Option<&AssemblerConfig>,
+    ) -> Result<(), EmulatorError> {
+        if let (Some(expected), Some(config)) = (self.config_fingerprint, config) {
+            if expected != config.fingerprint() {
+                return Err(EmulatorError::ConfigMismatch {
+                    expected,
+                    found: config.fingerprint(),
+                });
+            }
+        }
+
+        if self.cpu.registers.len() != machine.cpu.registers.len() {
+            return Err(invalid(format!(
+                "the snapshot has {} registers, but the machine has {}",
+                self.cpu.registers.len(),
+                machine.cpu.registers.len()
+            )));
+        }
+
+        let memory = self.memory()?;
+
+        // Check as much as possible before changing anything.
+        let mut device_states = vec![];
+
+        for device in &self.devices {
+            let start = machine
+                .bus
+                .devices()
+                .find(|(name, _, _)| *name == device.name)
+                .map(|(_, start, _)| start);
+
+            if start != Some(device.start) {
+                return Err(invalid(format!(
+                    "device '{}' isn't mapped at {:#06X}",
+                    device.name, device.start
+                )));
+            }
+
+            let state = decode_hex(&device.state).ok_or_else(|| {
+                invalid(format!("the state of device '{}' isn't hex", device.name))
+            })?;
+
+            device_states.push((device.name.as_str(), state));
+        }
+
+        // Devices the snapshot doesn't know about would keep their current state, mixing it with the snapshot's.
+        if let Some((name, _, _)) = machine
+            .bus
+            .devices()
+            .find(|(name, _, _)| !self.devices.iter().any(|device| device.name == *name))
+        {
+            return Err(invalid(format!("device '{}' isn't in the snapshot", name)));
+        }
+
+        // Devices only say whether a state is valid by trying to load it, so the states they had are saved first and
+        // put back if any of the new ones fail. That way, a bad snapshot never leaves the machine half-restored.
+        let previous_states = device_states
+            .iter()
+            .map(|(name, _)| {
+                let device = machine
+                    .bus
+                    .device(name)
+                    .expect("The device was just checked to exist!");
+
+                (*name, device.save_state())
+            })
+            .collect::<Vec<_>>();
+
+        for (name, state) in &device_states {
+            let device = machine
+                .bus
+                .device_mut(name)
+                .expect("The device was just checked to exist!");
+
+            if !device.load_state(state) {
+                for (name, state) in &previous_states {
+                    machine
+                        .bus
+                        .device_mut(name)
+                        .expect("The device was just checked to exist!")
+                        .load_state(state);
+                }
+
+                return Err(invalid(format!(
+                    "the state of device '{}' isn't valid",
+                    name
+                )));
+            }
+        }
+
+        machine.cpu = self.cpu.clone();
+        machine.bus.load(0, &memory);
+
+        Ok(())
+    }
+
+    /// Gets the memory image saved in the snapshot.
+    pub fn memory(&self) -> Result<Vec<u8>, EmulatorError> {
+        match decode_hex(&self.memory) {
+            Some(memory) if memory.len() == MEMORY_SIZE => Ok(memory),
+            _ => Err(invalid(format!(
+                "the memory image isn't {} bytes of hex",
+                MEMORY_SIZE
+            ))),
+        }
+    }
+
+    /// Converts the snapshot to JSON.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("Snapshots can always be serialized!")
+    }
+
+    /// Reads a snapshot from JSON, checking that it's a version we understand.
+    pub fn from_json(json: &str) -> Result<Self, EmulatorError> {
+        // Check the version first, so that snapshots from newer versions give a useful error instead of a
+        // confusing one about missing fields.
+        #[derive(Deserialize)]
+        struct Version {
+            version: u32,
+        }
+
+        let version = serde_json::from_str::<Version>(json)
+            .map_err(|err| invalid(err.to_string()))?
+            .version;
+
+        if version != SNAPSHOT_VERSION {
+            return Err(EmulatorError::UnsupportedSnapshotVersion { version });
+        }
+
+        serde_json::from_str(json).map_err(|err| invalid(err.to_string()))
+    }
+
+    /// Saves the snapshot to a file, as JSON.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), EmulatorError> {
+        fs::write(path, self.to_json()).map_err(|err| EmulatorError::Io {
+            message: err.to_string(),
+        })
+    }
+
+    /// Loads a snapshot from a JSON file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, EmulatorError> {
+        let json = fs::read_to_string(path).map_err(|err| EmulatorError::Io {
+            message: err.to_string(),
+        })?;
+
+        Self::from_json(&json)
+    }
+}
+
+/// Makes an `InvalidSnapshot` error.
+fn invalid(reason: String) -> EmulatorError {
+    EmulatorError::InvalidSnapshot { reason }
+}
@@ -0,0 +1,684 @@
+#![cfg(test)]
+
+use super::{
+    config::DeviceConfig,
+    machine::{Cpu, InstructionSet, Machine, Step},
+    nand7400::{FLAG_CARRY, FLAG_ZERO},
+    profile::{LabelProfile, Profiler},
+    snapshot::{Snapshot, SNAPSHOT_VERSION},
+    trace::{Annotations, Trace},
+    *,
+};
+use crate::assembler::{
+    config::{AssemblerConfig, Opcode, OpcodeArg},
+    Assembler,
+};
+
+/// A tiny instruction set used to test the machine:
+///
+/// - `0x00`: `nop` (1 cycle)
+/// - `0x01 n`: `lda #n`, load `n` into `a` (2 cycles)
+/// - `0x02 lo hi`: `sta addr`, store `a` to `addr` (3 cycles)
+/// - `0x03 lo hi`: `jmp addr`, jump to `addr` (2 cycles)
+/// - `0xFF`: `hlt` (1 cycle)
+#[derive(Debug)]
+pub struct TestIsa;
+
+impl InstructionSet for TestIsa {
+    fn register_names(&self) -> Vec<String> {
+        vec!["A".to_string()]
+    }
+
+    fn execute(&mut self, cpu: &mut Cpu, bus: &mut Bus) -> Result<u32, EmulatorError> {
+        let opcode = bus.read(cpu.pc);
+        let operand = |i: u16| bus.peek(cpu.pc.wrapping_add(i));
+        let address = u16::from_le_bytes([operand(1), operand(2)]);
+
+        match opcode {
+            0x00 => {
+                cpu.pc = cpu.pc.wrapping_add(1);
+                Ok(1)
+            }
+
+            0x01 => {
+                cpu.registers[0] = operand(1);
+                cpu.pc = cpu.pc.wrapping_add(2);
+                Ok(2)
+            }
+
+            0x02 => {
+                bus.write(address, cpu.registers[0]);
+                cpu.pc = cpu.pc.wrapping_add(3);
+                Ok(3)
+            }
+
+            0x03 => {
+                cpu.pc = address;
+                Ok(2)
+            }
+
+            0xFF => {
+                cpu.halted = true;
+                Ok(1)
+            }
+
+            opcode => Err(EmulatorError::IllegalOpcode { opcode, pc: cpu.pc }),
+        }
+    }
+}
+
+/// Test that reads and writes go to memory when no device is mapped there.
+#[test]
+fn bus_memory() {
+    let mut bus = Bus::new();
+
+    bus.write(0x1234, 0xAB);
+
+    assert_eq!(bus.read(0x1234), 0xAB);
+    assert_eq!(bus.memory()[0x1234], 0xAB);
+
+    bus.load(0xFFFE, &[0x01, 0x02, 0x03]);
+
+    assert_eq!(bus.read(0xFFFE), 0x01);
+    assert_eq!(bus.read(0xFFFF), 0x02);
+}
+
+/// Test that reads and writes go to devices when they're mapped, and that memory underneath is untouched.
+#[test]
+fn bus_devices() {
+    let mut bus = Bus::new();
+
+    bus.map("console", 0x8000, Box::new(ConsolePort::buffered()))
+        .unwrap();
+    bus.map("leds", 0x8001, Box::new(LedRegister::new()))
+        .unwrap();
+    bus.map("display", 0x8002, Box::new(SevenSegment::new()))
+        .unwrap();
+    bus.map("keypad", 0x8006, Box::new(Keypad::new())).unwrap();
+
+    for byte in b"Hi!" {
+        bus.write(0x8000, *byte);
+    }
+
+    bus.write(0x8001, 0b1000_0101);
+
+    for (i, digit) in [0x1, 0x2, 0xA, 0xF].iter().enumerate() {
+        bus.write(0x8002 + i as u16, *digit);
+    }
+
+    assert_eq!(
+        bus.device_as::<ConsolePort>("console").unwrap().output(),
+        b"Hi!"
+    );
+    assert_eq!(
+        bus.device_as::<LedRegister>("leds").unwrap().leds(),
+        [true, false, true, false, false, false, false, true]
+    );
+    assert_eq!(bus.read(0x8001), 0b1000_0101);
+
+    let display = bus.device_as::<SevenSegment>("display").unwrap();
+    assert_eq!(display.to_string(), "12AF");
+    assert_eq!(display.segments(), [0x06, 0x5B, 0x77, 0x71]);
+
+    // Nothing should've gone through to memory.
+    assert!(bus.memory()[0x8000..0x8008].iter().all(|byte| *byte == 0));
+
+    // Wrong type or name.
+    assert!(bus.device_as::<Keypad>("leds").is_none());
+    assert!(bus.device("nothing").is_none());
+}
+
+/// Test the keypad's input queue.
+#[test]
+fn keypad_queue() {
+    let mut bus = Bus::new();
+
+    bus.map("keypad", 0x9000, Box::new(Keypad::new())).unwrap();
+
+    assert_eq!(bus.read(0x9000), 0);
+    assert_eq!(bus.read(0x9001), 0);
+
+    let keypad = bus.device_as_mut::<Keypad>("keypad").unwrap();
+    keypad.press(0x05);
+    keypad.press(0x0C);
+
+    assert_eq!(bus.read(0x9000), 2);
+    assert_eq!(bus.read(0x9001), 0x05);
+    assert_eq!(bus.read(0x9000), 1);
+    assert_eq!(bus.read(0x9001), 0x0C);
+    assert_eq!(bus.read(0x9000), 0);
+}
+
+/// Test that bad mappings are rejected.
+#[test]
+fn bus_bad_mappings() {
+    let mut bus = Bus::new();
+
+    bus.map("display", 0x8000, Box::new(SevenSegment::new()))
+        .unwrap();
+
+    assert_eq!(
+        bus.map("leds", 0x8003, Box::new(LedRegister::new())),
+        Err(EmulatorError::DeviceOverlap {
+            name: "leds".to_string(),
+            other: "display".to_string(),
+        })
+    );
+
+    assert_eq!(
+        bus.map("display", 0x9000, Box::new(LedRegister::new())),
+        Err(EmulatorError::DuplicateDevice {
+            name: "display".to_string(),
+        })
+    );
+
+    assert_eq!(
+        bus.map("keypad", 0xFFFF, Box::new(Keypad::new())),
+        Err(EmulatorError::DeviceOutOfRange {
+            name: "keypad".to_string(),
+            start: 0xFFFF,
+            size: 2,
+        })
+    );
+
+    // Right after the display is fine.
+    bus.map("leds", 0x8004, Box::new(LedRegister::new()))
+        .unwrap();
+}
+
+/// Test that device maps can be loaded from JSON.
+#[test]
+fn device_map_from_json() {
+    let config: DeviceMapConfig = serde_json::from_str(
+        r#"{
+            "devices": [
+                { "name": "out", "kind": "Console", "start": 65280 },
+                { "name": "leds", "kind": "Leds", "start": 65281 },
+                { "name": "display", "kind": "SevenSegment", "start": 65282 },
+                { "name": "keys", "kind": "Keypad", "start": 65286 }
+            ]
+        }"#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        config.devices[2],
+        DeviceConfig {
+            name: "display".to_string(),
+            kind: DeviceKind::SevenSegment,
+            start: 0xFF02,
+        }
+    );
+
+    let mut bus = Bus::from_config(&config).unwrap();
+
+    bus.write(0xFF01, 0xFF);
+
+    assert_eq!(bus.device_as::<LedRegister>("leds").unwrap().value(), 0xFF);
+    assert!(bus.device_as::<ConsolePort>("out").is_some());
+    assert!(bus.device_as::<Keypad>("keys").is_some());
+}
+
+/// Test running a program on the machine, including writing to a device.
+#[test]
+fn machine_run() {
+    let mut bus = Bus::new();
+    bus.map("leds", 0x8000, Box::new(LedRegister::new()))
+        .unwrap();
+
+    let mut machine = Machine::new(Box::new(TestIsa), bus);
+
+    // lda #0x2A; sta 0x8000; nop; hlt
+    machine.load(0, &[0x01, 0x2A, 0x02, 0x00, 0x80, 0x00, 0xFF]);
+
+    assert_eq!(
+        machine.step(),
+        Ok(Step {
+            pc: 0,
+            opcode: 0x01,
+            cycles: 2,
+        })
+    );
+    assert_eq!(machine.cpu.registers, vec![0x2A]);
+
+    assert_eq!(machine.run(100), Ok(3));
+    assert!(machine.cpu.halted);
+    assert_eq!(machine.cpu.cycles, 7);
+    assert_eq!(
+        machine
+            .bus
+            .device_as::<LedRegister>("leds")
+            .unwrap()
+            .value(),
+        0x2A
+    );
+
+    assert_eq!(machine.step(), Err(EmulatorError::Halted { pc: 6 }));
+
+    machine.load(0x20, &[0x42]);
+    machine.reset(0x20);
+
+    assert!(!machine.cpu.halted);
+    assert_eq!(
+        machine.step(),
+        Err(EmulatorError::IllegalOpcode {
+            opcode: 0x42,
+            pc: 0x20,
+        })
+    );
+}
+
+/// Test running a program assembled with the nand7400 profile on the nand7400's own instruction set.
+#[test]
+fn nand7400_program() {
+    let source = "start:\n\
+                  \tlda #0x2A\n\
+                  \tldb 0x80\n\
+                  \tadd #0x2A #0x16 #0x12\n\
+                  \tadd #0xF0 #0x20 #0x13\n\
+                  \tjmp end\n\
+                  \thlt\n\
+                  end:\n\
+                  \thlt\n";
+
+    let binary = Assembler::new(AssemblerConfig::nand7400())
+        .assemble(source)
+        .unwrap();
+
+    let mut machine = Machine::nand7400(Bus::new());
+    machine.load(0, &binary);
+    machine.load(0x80, &[0xF0]);
+
+    let mut profiler = Profiler::new();
+    assert_eq!(profiler.run(&mut machine, 100), Ok(6));
+
+    assert!(machine.cpu.halted);
+    assert_eq!(machine.cpu.registers, vec![0x2A, 0xF0]);
+    assert_eq!(machine.cpu.pc, 0x0010);
+    assert_eq!(&machine.bus.memory()[0x12..0x14], &[0x40, 0x10]);
+    assert_eq!(machine.cpu.flags, FLAG_CARRY);
+    assert_eq!(profiler.total_cycles(), 17);
+
+    // 0xF0 + 0x10 wraps around to 0, setting both flags.
+    machine.load(0x20, &[0x03, 0xF0, 0x10, 0x12, 0xFF]);
+    machine.reset(0x20);
+
+    assert_eq!(machine.run(100), Ok(2));
+    assert_eq!(machine.bus.memory()[0x12], 0x00);
+    assert_eq!(machine.cpu.flags, FLAG_CARRY | FLAG_ZERO);
+}
+
+/// Gets an assembler for `TestIsa`.
+pub fn test_assembler() -> Assembler {
+    Assembler::new(test_assembler_config())
+}
+
+/// Gets the assembler configuration for `TestIsa`.
+pub fn test_assembler_config() -> AssemblerConfig {
+    AssemblerConfig {
+        opcodes: vec![
+            Opcode {
+                mnemonic: "nop".to_string(),
+                binary: 0x00,
+                args: vec![],
+                ..Default::default()
+            },
+            Opcode {
+                mnemonic: "lda".to_string(),
+                binary: 0x01,
+                args: vec![OpcodeArg::Immediate],
+                ..Default::default()
+            },
+            Opcode {
+                mnemonic: "sta".to_string(),
+                binary: 0x02,
+                args: vec![OpcodeArg::Indirect, OpcodeArg::Indirect],
+                ..Default::default()
+            },
+            Opcode {
+                mnemonic: "jmp".to_string(),
+                binary: 0x03,
+                args: vec![OpcodeArg::Immediate, OpcodeArg::Immediate],
+                ..Default::default()
+            },
+            Opcode {
+                mnemonic: "hlt".to_string(),
+                binary: 0xFF,
+                args: vec![],
+                ..Default::default()
+            },
+        ],
+        registers: vec![],
+        ..Default::default()
+    }
+}
+
+/// A small program for `TestIsa` that writes to the LEDs at 0x8000.
+const LED_PROGRAM: &str = "start:\n    lda #0x2A\n    sta 0x00 0x80\nend:\n    hlt\n";
+
+/// Test tracing a program, and writing the trace as annotated text.
+#[test]
+fn trace_text() {
+    let (binary, ast, source_map) = test_assembler()
+        .assemble_with_source_map(LED_PROGRAM)
+        .unwrap();
+
+    let mut bus = Bus::new();
+    bus.map("leds", 0x8000, Box::new(LedRegister::new()))
+        .unwrap();
+
+    let mut machine = Machine::new(Box::new(TestIsa), bus);
+    machine.load(0, &binary);
+
+    let mut trace = Trace::new(&machine);
+    assert_eq!(trace.run(&mut machine, 100), Ok(3));
+    assert_eq!(trace.entries().len(), 3);
+
+    let mut text = vec![];
+    trace
+        .write_text(
+            &mut text,
+            Some(Annotations {
+                source: LED_PROGRAM,
+                source_map: &source_map,
+                symbols: &ast.symbols,
+            }),
+        )
+        .unwrap();
+
+    assert_eq!(
+        String::from_utf8(text).unwrap(),
+        "       0 0000 01 A=2A F=00 | R 0000=01 ; start (2: lda #0x2A)\n\
+         \x20      2 0002 02 A=2A F=00 | R 0002=02 W 8000=2A ; start+2 (3: sta 0x00 0x80)\n\
+         \x20      5 0005 FF A=2A F=00 | R 0005=FF ; end (5: hlt)\n"
+    );
+
+    let mut plain = vec![];
+    trace.write_text(&mut plain, None).unwrap();
+
+    assert!(String::from_utf8(plain)
+        .unwrap()
+        .starts_with("       0 0000 01 A=2A F=00 | R 0000=01\n"));
+}
+
+/// Test writing a trace as a VCD file.
+#[test]
+fn trace_vcd() {
+    let binary = test_assembler().assemble(LED_PROGRAM).unwrap();
+
+    let mut machine = Machine::new(Box::new(TestIsa), Bus::new());
+    machine.load(0, &binary);
+
+    let mut trace = Trace::new(&machine);
+    trace.run(&mut machine, 100).unwrap();
+
+    let mut vcd = vec![];
+    trace.write_vcd(&mut vcd).unwrap();
+    let vcd = String::from_utf8(vcd).unwrap();
+
+    assert!(vcd.contains("$var wire 16 ! pc $end\n"));
+    assert!(vcd.contains("$var wire 8 & a $end\n"));
+    assert!(vcd.contains("$var wire 8 ' flags $end\n"));
+
+    // The body, after the definitions.
+    let body = vcd.split("$enddefinitions $end\n").nth(1).unwrap();
+
+    assert_eq!(
+        body,
+        "#0\n\
+         b0 !\n\
+         b1 \"\n\
+         b0 #\n\
+         b1 $\n\
+         0%\n\
+         b0 &\n\
+         b0 '\n\
+         #2\n\
+         b10 !\n\
+         b10 \"\n\
+         b10 #\n\
+         b10 $\n\
+         b101010 &\n\
+         #3\n\
+         b1000000000000000 #\n\
+         b101010 $\n\
+         1%\n\
+         #5\n\
+         b101 !\n\
+         b11111111 \"\n\
+         b101 #\n\
+         b11111111 $\n\
+         0%\n"
+    );
+}
+
+/// Test recording coverage and a per-label profile of a loop.
+#[test]
+fn profile_loop() {
+    let source = "start:\n\
+                  \tlda #0x01\n\
+                  loop:\n\
+                  \tnop\n\
+                  \tnop\n\
+                  \tjmp loop\n\
+                  .byte 0x12\n\
+                  never:\n\
+                  \tnop\n";
+
+    let (binary, ast, source_map) = test_assembler().assemble_with_source_map(source).unwrap();
+
+    let mut machine = Machine::new(Box::new(TestIsa), Bus::new());
+    machine.load(0, &binary);
+
+    let mut profiler = Profiler::new();
+    assert_eq!(profiler.run(&mut machine, 7), Ok(7));
+
+    assert_eq!(profiler.hits(0x00), 1);
+    assert_eq!(profiler.hits(0x02), 2);
+    assert_eq!(profiler.hits(0x08), 0);
+    assert_eq!(profiler.total_cycles(), 10);
+
+    assert_eq!(
+        profiler.label_profile(&ast.symbols),
+        vec![
+            LabelProfile {
+                label: Some("loop".to_string()),
+                address: 0x02,
+                instructions: 6,
+                cycles: 8,
+            },
+            LabelProfile {
+                label: Some("start".to_string()),
+                address: 0x00,
+                instructions: 1,
+                cycles: 2,
+            },
+        ]
+    );
+
+    // The `.byte` line isn't code, so it's left out.
+    let mut lcov = vec![];
+    profiler
+        .write_lcov(&mut lcov, &source_map, "loop.asm")
+        .unwrap();
+
+    assert_eq!(
+        String::from_utf8(lcov).unwrap(),
+        "TN:\nSF:loop.asm\nDA:2,1\nDA:4,2\nDA:5,2\nDA:6,2\nDA:9,0\nLF:5\nLH:4\nend_of_record\n"
+    );
+
+    let mut table = vec![];
+    profiler.write_profile(&mut table, &ast.symbols).unwrap();
+
+    assert_eq!(
+        String::from_utf8(table).unwrap(),
+        "    cycles       %     instrs  addr   label\n\
+         \x20        8  80.00%          6  0002   loop\n\
+         \x20        2  20.00%          1  0000   start\n"
+    );
+}
+
+/// Makes a machine with some devices mapped, for snapshot tests.
+fn snapshot_machine() -> Machine {
+    let mut bus = Bus::new();
+    bus.map("leds", 0x8000, Box::new(LedRegister::new()))
+        .unwrap();
+    bus.map("keypad", 0x8001, Box::new(Keypad::new())).unwrap();
+
+    Machine::new(Box::new(TestIsa), bus)
+}
+
+/// Test that a snapshot restores the CPU, memory and devices exactly.
+#[test]
+fn snapshot_roundtrip() {
+    let mut assembler = test_assembler();
+    let binary = assembler.assemble(LED_PROGRAM).unwrap();
+    let config = test_assembler_config();
+
+    let mut machine = snapshot_machine();
+    machine.load(0, &binary);
+    machine.step().unwrap();
+    machine
+        .bus
+        .device_as_mut::<Keypad>("keypad")
+        .unwrap()
+        .press(0x07);
+
+    let snapshot = Snapshot::capture(&machine, Some(&config));
+    assert_eq!(snapshot.version, SNAPSHOT_VERSION);
+
+    let json = snapshot.to_json();
+    let loaded = Snapshot::from_json(&json).unwrap();
+    assert_eq!(loaded, snapshot);
+
+    // Restore onto a fresh machine, and make sure it carries on where the old one left off.
+    let mut restored = snapshot_machine();
+    loaded.restore(&mut restored, Some(&config)).unwrap();
+
+    assert_eq!(restored.cpu, machine.cpu);
+    assert_eq!(restored.bus.memory(), machine.bus.memory());
+    assert_eq!(
+        restored
+            .bus
+            .device_as::<Keypad>("keypad")
+            .unwrap()
+            .pending(),
+        1
+    );
+
+    restored.run(100).unwrap();
+
+    assert!(restored.cpu.halted);
+    assert_eq!(
+        restored
+            .bus
+            .device_as::<LedRegister>("leds")
+            .unwrap()
+            .value(),
+        0x2A
+    );
+}
+
+/// Test that snapshots can be saved to and loaded from files.
+#[test]
+fn snapshot_files() {
+    let mut machine = snapshot_machine();
+    machine.load(0x100, &[0xAB, 0xCD]);
+
+    let path = std::env::temp_dir().join(format!("nand7400-snapshot-{}.json", std::process::id()));
+    let snapshot = Snapshot::capture(&machine, None);
+
+    snapshot.save(&path).unwrap();
+    let loaded = Snapshot::load(&path);
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(loaded, Ok(snapshot));
+    assert!(matches!(
+        Snapshot::load(&path),
+        Err(EmulatorError::Io { .. })
+    ));
+}
+
+/// Test that bad snapshots are rejected, without touching the machine.
+#[test]
+fn snapshot_errors() {
+    let config = test_assembler_config();
+    let mut machine = snapshot_machine();
+    machine.cpu.pc = 0x1234;
+
+    let snapshot = Snapshot::capture(&machine, Some(&config));
+
+    // A different config.
+    let mut other_config = config.clone();
+    other_config.opcodes.pop();
+
+    assert_eq!(
+        snapshot.restore(&mut snapshot_machine(), Some(&other_config)),
+        Err(EmulatorError::ConfigMismatch {
+            expected: config.fingerprint(),
+            found: other_config.fingerprint(),
+        })
+    );
+
+    // Missing devices.
+    let mut bare = Machine::new(Box::new(TestIsa), Bus::new());
+
+    assert!(matches!(
+        snapshot.restore(&mut bare, None),
+        Err(EmulatorError::InvalidSnapshot { .. })
+    ));
+    assert_eq!(bare.cpu.pc, 0);
+
+    // Extra devices.
+    let mut extra = snapshot_machine();
+    extra
+        .bus
+        .map("spare", 0x9000, Box::new(Keypad::new()))
+        .unwrap();
+
+    assert!(matches!(
+        snapshot.restore(&mut extra, None),
+        Err(EmulatorError::InvalidSnapshot { .. })
+    ));
+    assert_eq!(extra.cpu.pc, 0);
+
+    // Bad device state.
+    let mut bad_state = snapshot.clone();
+    bad_state.devices[0].state = "0102".to_string();
+
+    assert!(matches!(
+        bad_state.restore(&mut snapshot_machine(), None),
+        Err(EmulatorError::InvalidSnapshot { .. })
+    ));
+
+    // A bad device state after a good one, which shouldn't leave the good one restored.
+    let mut late_bad_state = bad_state.clone();
+    late_bad_state.devices.reverse();
+    late_bad_state.devices[0].state = "07".to_string();
+
+    let mut target = snapshot_machine();
+
+    assert!(matches!(
+        late_bad_state.restore(&mut target, None),
+        Err(EmulatorError::InvalidSnapshot { .. })
+    ));
+    assert_eq!(
+        target.bus.device_as::<Keypad>("keypad").unwrap().pending(),
+        0
+    );
+
+    // Newer versions.
+    let newer = snapshot.to_json().replace(
+        &format!("\"version\":{}", SNAPSHOT_VERSION),
+        "\"version\":999",
+    );
+
+    assert_eq!(
+        Snapshot::from_json(&newer),
+        Err(EmulatorError::UnsupportedSnapshotVersion { version: 999 })
+    );
+    assert!(matches!(
+        Snapshot::from_json("{}"),
+        Err(EmulatorError::InvalidSnapshot { .. })
+    ));
+}
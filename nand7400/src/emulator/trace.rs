@@ -0,0 +1,294 @@
+use super::{
+    errors::EmulatorError,
+    machine::{Machine, Step},
+    AccessKind, BusAccess,
+};
+use crate::assembler::{
+    parser::ast::Label,
+    source_map::{nearest_label, source_line, SourceMap},
+};
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::{self, Write},
+};
+
+/// A record of everything a machine did while it was being traced: every instruction executed, every bus access it
+/// made, and the registers after each instruction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Trace {
+    /// The names of the general-purpose registers.
+    register_names: Vec<String>,
+
+    /// The registers and flags before the first traced instruction.
+    initial_registers: (Vec<u8>, u8),
+
+    /// The traced instructions, in order.
+    entries: Vec<TraceEntry>,
+}
+
+/// A single traced instruction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceEntry {
+    /// The cycle the instruction started on.
+    pub cycle: u64,
+
+    /// The instruction that was executed.
+    pub step: Step,
+
+    /// The bus accesses the instruction made, in order.
+    pub accesses: Vec<BusAccess>,
+
+    /// The general-purpose registers after the instruction.
+    pub registers: Vec<u8>,
+
+    /// The flags register after the instruction.
+    pub flags: u8,
+}
+
+/// Information used to annotate a trace with labels and source lines.
+#[derive(Debug, Clone, Copy)]
+pub struct Annotations<'a> {
+    /// The source code of the program.
+    pub source: &'a str,
+
+    /// The source map of the program.
+    pub source_map: &'a SourceMap,
+
+    /// The symbol table of the program.
+    pub symbols: &'a HashMap<Label, u32>,
+}
+
+/// Public API for traces.
+impl Trace {
+    /// Starts a new trace of a machine, from its current state.
+    pub fn new(machine: &Machine) -> Self {
+        Self {
+            register_names: machine.register_names(),
+            initial_registers: (machine.cpu.registers.clone(), machine.cpu.flags),
+            entries: vec![],
+        }
+    }
+
+    /// Executes a single instruction on the machine, recording it.
+    pub fn step(&mut self, machine: &mut Machine) -> Result<Step, EmulatorError> {
+        let cycle = machine.cpu.cycles;
+
+        machine.bus.record_accesses(true);
+        let result = machine.step();
+        let accesses = machine.bus.take_accesses();
+        machine.bus.record_accesses(false);
+
+        let step = result?;
+
+        self.entries.push(TraceEntry {
+            cycle,
+            step,
+            accesses,
+            registers: machine.cpu.registers.clone(),
+            flags: machine.cpu.flags,
+        });
+
+        Ok(step)
+    }
+
+    /// Runs the machine until it halts, or until `max_steps` instructions have been executed, recording everything.
+    /// Returns the number of instructions executed.
+    pub fn run(&mut self, machine: &mut Machine, max_steps: usize) -> Result<usize, EmulatorError> {
+        let mut steps = 0;
+
+        while !machine.cpu.halted && steps < max_steps {
+            self.step(machine)?;
+            steps += 1;
+        }
+
+        Ok(steps)
+    }
+
+    /// Gets the traced instructions, in order.
+    pub fn entries(&self) -> &[TraceEntry] {
+        &self.entries
+    }
+
+    /// Writes the trace as text, with one line per instruction. Each line has the starting cycle, program counter,
+    /// opcode, registers after the instruction, and bus accesses. If annotations are given, each line also gets the
+    /// nearest label and the source line of the instruction.
+    pub fn write_text<W: Write>(
+        &self,
+        writer: &mut W,
+        annotations: Option<Annotations>,
+    ) -> io::Result<()> {
+        for entry in &self.entries {
+            write!(
+                writer,
+                "{:>8} {:04X} {:02X}",
+                entry.cycle, entry.step.pc, entry.step.opcode
+            )?;
+
+            for (name, value) in self.register_names.iter().zip(&entry.registers) {
+                write!(writer, " {}={:02X}", name, value)?;
+            }
+
+            write!(writer, " F={:02X} |", entry.flags)?;
+
+            for access in &entry.accesses {
+                let kind = match access.kind {
+                    AccessKind::Read => 'R',
+                    AccessKind::Write => 'W',
+                };
+
+                write!(
+                    writer,
+                    " {} {:04X}={:02X}",
+                    kind, access.address, access.value
+                )?;
+            }
+
+            if let Some(annotations) = annotations {
+                write!(writer, " ;")?;
+
+                if let Some((label, offset)) =
+                    nearest_label(annotations.symbols, entry.step.pc as u32)
+                {
+                    match offset {
+                        0 => write!(writer, " {}", label)?,
+                        offset => write!(writer, " {}+{}", label, offset)?,
+                    }
+                }
+
+                if let Some(source_entry) = annotations.source_map.get(entry.step.pc as u32) {
+                    let line = source_line(annotations.source, source_entry.line).unwrap_or("");
+
+                    write!(writer, " ({}: {})", source_entry.line, line.trim())?;
+                }
+            }
+
+            writeln!(writer)?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes the trace as a VCD (Value Change Dump) file, which can be opened in waveform viewers like GTKWave. One
+    /// time unit is one cycle. The program counter and opcode change at the start of each instruction, the address
+    /// and data buses change once per access (one cycle apart), and the registers change at the end of each
+    /// instruction.
+    pub fn write_vcd<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        // Every signal, as (name, width). The index of each signal is also used to make its identifier.
+        let mut signals = vec![
+            ("pc".to_string(), 16),
+            ("opcode".to_string(), 8),
+            ("address".to_string(), 16),
+            ("data".to_string(), 8),
+            ("write".to_string(), 1),
+        ];
+        signals.extend(
+            self.register_names
+                .iter()
+                .map(|name| (name.to_ascii_lowercase(), 8)),
+        );
+        signals.push(("flags".to_string(), 8));
+
+        let flags_signal = signals.len() - 1;
+
+        writeln!(writer, "$version nand7400 trace $end")?;
+        writeln!(writer, "$timescale 1 us $end")?;
+        writeln!(writer, "$scope module nand7400 $end")?;
+
+        for (i, (name, width)) in signals.iter().enumerate() {
+            writeln!(writer, "$var wire {} {} {} $end", width, vcd_id(i), name)?;
+        }
+
+        writeln!(writer, "$upscope $end")?;
+        writeln!(writer, "$enddefinitions $end")?;
+
+        // Collect every change by time. Later changes at the same time win.
+        let mut changes: BTreeMap<u64, BTreeMap<usize, u64>> = BTreeMap::new();
+        let start = self.entries.first().map_or(0, |entry| entry.cycle);
+        let initial = changes.entry(start).or_default();
+
+        for i in 0..5 {
+            initial.insert(i, 0);
+        }
+
+        for (i, value) in self.initial_registers.0.iter().enumerate() {
+            initial.insert(5 + i, *value as u64);
+        }
+
+        initial.insert(flags_signal, self.initial_registers.1 as u64);
+
+        for entry in &self.entries {
+            let at_start = changes.entry(entry.cycle).or_default();
+            at_start.insert(0, entry.step.pc as u64);
+            at_start.insert(1, entry.step.opcode as u64);
+
+            let last_cycle = entry.step.cycles.saturating_sub(1) as u64;
+
+            for (i, access) in entry.accesses.iter().enumerate() {
+                let at_access = changes
+                    .entry(entry.cycle + (i as u64).min(last_cycle))
+                    .or_default();
+
+                at_access.insert(2, access.address as u64);
+                at_access.insert(3, access.value as u64);
+                at_access.insert(4, (access.kind == AccessKind::Write) as u64);
+            }
+
+            let at_end = changes
+                .entry(entry.cycle + entry.step.cycles as u64)
+                .or_default();
+
+            for (i, value) in entry.registers.iter().enumerate() {
+                at_end.insert(5 + i, *value as u64);
+            }
+
+            at_end.insert(flags_signal, entry.flags as u64);
+        }
+
+        // Now write out only the values that actually changed.
+        let mut current: Vec<Option<u64>> = vec![None; signals.len()];
+
+        for (time, values) in changes {
+            let changed = values
+                .into_iter()
+                .filter(|(signal, value)| current[*signal] != Some(*value))
+                .collect::<Vec<_>>();
+
+            if changed.is_empty() {
+                continue;
+            }
+
+            writeln!(writer, "#{}", time)?;
+
+            for (signal, value) in changed {
+                current[signal] = Some(value);
+
+                match signals[signal].1 {
+                    1 => writeln!(writer, "{}{}", value, vcd_id(signal))?,
+                    _ => writeln!(writer, "b{:b} {}", value, vcd_id(signal))?,
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Makes the VCD identifier for a signal, from its index. Identifiers are made of the printable ASCII characters
+/// from `!` to `~`.
+fn vcd_id(mut index: usize) -> String {
+    const FIRST: u8 = b'!';
+    const COUNT: usize = (b'~' - b'!' + 1) as usize;
+
+    let mut id = String::new();
+
+    loop {
+        id.push((FIRST + (index % COUNT) as u8) as char);
+        index /= COUNT;
+
+        if index == 0 {
+            return id;
+        }
+
+        index -= 1;
+    }
+}
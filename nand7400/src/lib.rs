@@ -1,2 +1,3 @@
 pub mod assembler;
+pub mod emulator;
 pub mod formatter;